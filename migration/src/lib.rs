@@ -1,7 +1,29 @@
 pub use sea_orm_migration::prelude::*;
 
+mod sql;
+
 pub mod m20221121_170216_create_user_table;
 pub mod m20221213_173521_create_url_table;
+pub mod m20230104_120000_create_click_table;
+pub mod m20230110_090000_scope_url_uniqueness_to_live_links;
+pub mod m20230112_100000_scope_url_names_to_owner;
+pub mod m20230115_090000_add_expiration_to_url;
+pub mod m20230116_090000_add_active_from_to_url;
+pub mod m20230118_090000_add_password_to_url;
+pub mod m20230120_090000_add_click_limit_to_url;
+pub mod m20230122_090000_create_refresh_token_table;
+pub mod m20230122_100000_create_revoked_token_table;
+pub mod m20230124_090000_create_session_table;
+pub mod m20230126_090000_create_api_key_table;
+pub mod m20230128_090000_add_oidc_identity_to_user;
+pub mod m20230130_090000_add_email_verified_at_to_user;
+pub mod m20230201_090000_create_login_throttle_table;
+pub mod m20230201_100000_create_security_event_table;
+pub mod m20230203_090000_add_totp_to_user;
+pub mod m20230203_100000_create_recovery_code_table;
+pub mod m20230205_090000_add_tags_to_url;
+pub mod m20230205_100000_add_click_count_to_url;
+pub mod m20230205_110000_add_link_search_indexes;
 
 pub struct Migrator;
 
@@ -11,6 +33,26 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20221121_170216_create_user_table::Migration),
             Box::new(m20221213_173521_create_url_table::Migration),
+            Box::new(m20230104_120000_create_click_table::Migration),
+            Box::new(m20230110_090000_scope_url_uniqueness_to_live_links::Migration),
+            Box::new(m20230112_100000_scope_url_names_to_owner::Migration),
+            Box::new(m20230115_090000_add_expiration_to_url::Migration),
+            Box::new(m20230116_090000_add_active_from_to_url::Migration),
+            Box::new(m20230118_090000_add_password_to_url::Migration),
+            Box::new(m20230120_090000_add_click_limit_to_url::Migration),
+            Box::new(m20230122_090000_create_refresh_token_table::Migration),
+            Box::new(m20230122_100000_create_revoked_token_table::Migration),
+            Box::new(m20230124_090000_create_session_table::Migration),
+            Box::new(m20230126_090000_create_api_key_table::Migration),
+            Box::new(m20230128_090000_add_oidc_identity_to_user::Migration),
+            Box::new(m20230130_090000_add_email_verified_at_to_user::Migration),
+            Box::new(m20230201_090000_create_login_throttle_table::Migration),
+            Box::new(m20230201_100000_create_security_event_table::Migration),
+            Box::new(m20230203_090000_add_totp_to_user::Migration),
+            Box::new(m20230203_100000_create_recovery_code_table::Migration),
+            Box::new(m20230205_090000_add_tags_to_url::Migration),
+            Box::new(m20230205_100000_add_click_count_to_url::Migration),
+            Box::new(m20230205_110000_add_link_search_indexes::Migration),
         ]
     }
 }
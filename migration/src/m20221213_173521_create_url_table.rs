@@ -46,7 +46,7 @@ impl MigrationTrait for Migration {
 
 /// Learn more at https://docs.rs/sea-query#iden
 #[derive(Iden)]
-enum Url {
+pub enum Url {
     Table,
     Id,
     Name,
@@ -0,0 +1,71 @@
+use crate::m20221213_173521_create_url_table::Url;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(Click::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(Click::Id).uuid().not_null().primary_key())
+            .col(ColumnDef::new(Click::UrlId).uuid().not_null())
+            .col(ColumnDef::new(Click::CreatedAt).timestamp().not_null())
+            .col(ColumnDef::new(Click::Referrer).text().null())
+            .col(ColumnDef::new(Click::UserAgent).text().null())
+            .col(ColumnDef::new(Click::IpHash).string().string_len(64).null())
+            .col(
+                ColumnDef::new(Click::Browser)
+                    .string()
+                    .string_len(50)
+                    .null(),
+            )
+            .col(ColumnDef::new(Click::Os).string().string_len(50).null())
+            .col(ColumnDef::new(Click::Device).string().string_len(20).null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("FK_url_clicks_key")
+                    .from(Click::Table, Click::UrlId)
+                    .to(Url::Table, Url::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
+
+        manager.create_table(table).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-click-url-id-created-at")
+                    .table(Click::Table)
+                    .col(Click::UrlId)
+                    .col(Click::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().if_exists().table(Click::Table).to_owned())
+            .await
+    }
+}
+
+/// Learn more at https://docs.rs/sea-query#iden
+#[derive(Iden)]
+pub enum Click {
+    Table,
+    Id,
+    UrlId,
+    CreatedAt,
+    Referrer,
+    UserAgent,
+    IpHash,
+    Browser,
+    Os,
+    Device,
+}
@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+use crate::sql::execute;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Soft deleted links no longer hold on to their slug, and any number of them can share a deletion date
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        execute(
+            manager,
+            vec![
+                r#"ALTER TABLE "url" DROP CONSTRAINT IF EXISTS "idx-url-deleted-at""#,
+                r#"ALTER TABLE "url" DROP CONSTRAINT IF EXISTS "url_slug_key""#,
+                r#"CREATE UNIQUE INDEX "idx-url-slug-live" ON "url" ("slug") WHERE "deleted_at" IS NULL"#,
+                r#"CREATE INDEX "idx-url-deleted-at" ON "url" ("deleted_at")"#,
+            ],
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        execute(
+            manager,
+            vec![
+                r#"DROP INDEX IF EXISTS "idx-url-deleted-at""#,
+                r#"DROP INDEX IF EXISTS "idx-url-slug-live""#,
+                r#"ALTER TABLE "url" ADD CONSTRAINT "url_slug_key" UNIQUE ("slug")"#,
+                r#"ALTER TABLE "url" ADD CONSTRAINT "idx-url-deleted-at" UNIQUE ("deleted_at")"#,
+            ],
+        )
+        .await
+    }
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Url::Table)
+                    .add_column(ColumnDef::new(Url::ExpiresAt).timestamp().null())
+                    .add_column(ColumnDef::new(Url::ExpiredRedirectTo).text().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Url::Table)
+                    .drop_column(Url::ExpiresAt)
+                    .drop_column(Url::ExpiredRedirectTo)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Url {
+    Table,
+    ExpiresAt,
+    ExpiredRedirectTo,
+}
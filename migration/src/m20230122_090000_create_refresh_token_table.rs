@@ -0,0 +1,87 @@
+use crate::m20221121_170216_create_user_table::User;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(RefreshToken::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(RefreshToken::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(RefreshToken::UserId).uuid().not_null())
+            .col(ColumnDef::new(RefreshToken::FamilyId).uuid().not_null())
+            .col(
+                ColumnDef::new(RefreshToken::TokenHash)
+                    .string()
+                    .string_len(64)
+                    .not_null()
+                    .unique_key(),
+            )
+            .col(
+                ColumnDef::new(RefreshToken::CreatedAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(RefreshToken::ExpiresAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(RefreshToken::UsedAt).timestamp().null())
+            .col(ColumnDef::new(RefreshToken::RevokedAt).timestamp().null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("FK_user_refresh_tokens_key")
+                    .from(RefreshToken::Table, RefreshToken::UserId)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
+
+        manager.create_table(table).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-refresh-token-family-id")
+                    .table(RefreshToken::Table)
+                    .col(RefreshToken::FamilyId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(RefreshToken::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+/// Learn more at https://docs.rs/sea-query#iden
+#[derive(Iden)]
+pub enum RefreshToken {
+    Table,
+    Id,
+    UserId,
+    FamilyId,
+    TokenHash,
+    CreatedAt,
+    ExpiresAt,
+    UsedAt,
+    RevokedAt,
+}
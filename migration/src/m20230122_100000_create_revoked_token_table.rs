@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(RevokedToken::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(RevokedToken::Jti)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(RevokedToken::ExpiresAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .to_owned();
+
+        manager.create_table(table).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(RevokedToken::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+/// Learn more at https://docs.rs/sea-query#iden
+#[derive(Iden)]
+pub enum RevokedToken {
+    Table,
+    Jti,
+    ExpiresAt,
+}
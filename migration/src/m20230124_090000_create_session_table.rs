@@ -0,0 +1,74 @@
+use crate::{m20221121_170216_create_user_table::User, sql::execute};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// A session groups the refresh token family started by a login, its id is the family id
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(Session::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(Session::Id).uuid().not_null().primary_key())
+            .col(ColumnDef::new(Session::UserId).uuid().not_null())
+            .col(ColumnDef::new(Session::CreatedAt).timestamp().not_null())
+            .col(ColumnDef::new(Session::LastUsedAt).timestamp().not_null())
+            .col(ColumnDef::new(Session::ExpiresAt).timestamp().not_null())
+            .col(ColumnDef::new(Session::UserAgent).text().null())
+            .col(ColumnDef::new(Session::Ip).string().string_len(45).null())
+            .col(ColumnDef::new(Session::RevokedAt).timestamp().null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("FK_user_sessions_key")
+                    .from(Session::Table, Session::UserId)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
+
+        manager.create_table(table).await?;
+
+        // Logins made before sessions existed get one per refresh token family
+        execute(
+            manager,
+            vec![
+                r#"INSERT INTO "session" ("id", "user_id", "created_at", "last_used_at", "expires_at", "revoked_at")
+                SELECT "family_id", (array_agg("user_id"))[1], MIN("created_at"), MAX("created_at"), MAX("expires_at"),
+                    CASE WHEN bool_and("revoked_at" IS NOT NULL) THEN MAX("revoked_at") END
+                FROM "refresh_token" GROUP BY "family_id""#,
+                r#"ALTER TABLE "refresh_token" ADD CONSTRAINT "FK_session_refresh_tokens_key"
+                FOREIGN KEY ("family_id") REFERENCES "session" ("id") ON DELETE CASCADE ON UPDATE CASCADE"#,
+            ],
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        execute(
+            manager,
+            vec![r#"ALTER TABLE "refresh_token" DROP CONSTRAINT IF EXISTS "FK_session_refresh_tokens_key""#],
+        )
+        .await?;
+
+        manager
+            .drop_table(Table::drop().if_exists().table(Session::Table).to_owned())
+            .await
+    }
+}
+
+/// Learn more at https://docs.rs/sea-query#iden
+#[derive(Iden)]
+pub enum Session {
+    Table,
+    Id,
+    UserId,
+    CreatedAt,
+    LastUsedAt,
+    ExpiresAt,
+    UserAgent,
+    Ip,
+    RevokedAt,
+}
@@ -0,0 +1,69 @@
+use crate::m20221121_170216_create_user_table::User;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(ApiKey::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(ApiKey::Id).uuid().not_null().primary_key())
+            .col(ColumnDef::new(ApiKey::UserId).uuid().not_null())
+            .col(
+                ColumnDef::new(ApiKey::Name)
+                    .string()
+                    .string_len(50)
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(ApiKey::Prefix)
+                    .string()
+                    .string_len(16)
+                    .not_null()
+                    .unique_key(),
+            )
+            .col(
+                ColumnDef::new(ApiKey::KeyHash)
+                    .string()
+                    .string_len(64)
+                    .not_null(),
+            )
+            .col(ColumnDef::new(ApiKey::Scopes).text().not_null())
+            .col(ColumnDef::new(ApiKey::CreatedAt).timestamp().not_null())
+            .col(ColumnDef::new(ApiKey::LastUsedAt).timestamp().null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("FK_user_api_keys_key")
+                    .from(ApiKey::Table, ApiKey::UserId)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
+
+        manager.create_table(table).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().if_exists().table(ApiKey::Table).to_owned())
+            .await
+    }
+}
+
+/// Learn more at https://docs.rs/sea-query#iden
+#[derive(Iden)]
+pub enum ApiKey {
+    Table,
+    Id,
+    UserId,
+    Name,
+    Prefix,
+    KeyHash,
+    Scopes,
+    CreatedAt,
+    LastUsedAt,
+}
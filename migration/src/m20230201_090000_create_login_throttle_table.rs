@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Failed logins, counted per username and per client ip
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(LoginThrottle::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(LoginThrottle::Key)
+                    .string()
+                    .string_len(100)
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(LoginThrottle::Failures).integer().not_null())
+            .col(
+                ColumnDef::new(LoginThrottle::LastFailureAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(LoginThrottle::LockedUntil).timestamp().null())
+            .to_owned();
+
+        manager.create_table(table).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(LoginThrottle::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+/// Learn more at https://docs.rs/sea-query#iden
+#[derive(Iden)]
+pub enum LoginThrottle {
+    Table,
+    Key,
+    Failures,
+    LastFailureAt,
+    LockedUntil,
+}
@@ -0,0 +1,80 @@
+use crate::m20221121_170216_create_user_table::User;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(SecurityEvent::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(SecurityEvent::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(SecurityEvent::UserId).uuid().not_null())
+            .col(
+                ColumnDef::new(SecurityEvent::Kind)
+                    .string()
+                    .string_len(50)
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(SecurityEvent::Ip)
+                    .string()
+                    .string_len(45)
+                    .null(),
+            )
+            .col(
+                ColumnDef::new(SecurityEvent::CreatedAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("FK_user_security_events_key")
+                    .from(SecurityEvent::Table, SecurityEvent::UserId)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
+        manager.create_table(table).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-security-event-user-id")
+                    .table(SecurityEvent::Table)
+                    .col(SecurityEvent::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(SecurityEvent::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+/// Learn more at https://docs.rs/sea-query#iden
+#[derive(Iden)]
+pub enum SecurityEvent {
+    Table,
+    Id,
+    UserId,
+    Kind,
+    Ip,
+    CreatedAt,
+}
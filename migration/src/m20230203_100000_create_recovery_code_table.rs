@@ -0,0 +1,75 @@
+use crate::m20221121_170216_create_user_table::User;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(RecoveryCode::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(RecoveryCode::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(RecoveryCode::UserId).uuid().not_null())
+            .col(
+                ColumnDef::new(RecoveryCode::CodeHash)
+                    .string()
+                    .string_len(64)
+                    .not_null(),
+            )
+            .col(ColumnDef::new(RecoveryCode::UsedAt).timestamp().null())
+            .col(
+                ColumnDef::new(RecoveryCode::CreatedAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("FK_user_recovery_codes_key")
+                    .from(RecoveryCode::Table, RecoveryCode::UserId)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
+        manager.create_table(table).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-recovery-code-user-id")
+                    .table(RecoveryCode::Table)
+                    .col(RecoveryCode::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(RecoveryCode::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+/// Learn more at https://docs.rs/sea-query#iden
+#[derive(Iden)]
+pub enum RecoveryCode {
+    Table,
+    Id,
+    UserId,
+    CodeHash,
+    UsedAt,
+    CreatedAt,
+}
@@ -0,0 +1,32 @@
+use sea_orm_migration::prelude::*;
+
+use crate::sql::execute;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Labels picked by the owner to group their links, searched through a gin index
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        execute(
+            manager,
+            vec![
+                r#"ALTER TABLE "url" ADD COLUMN "tags" text[] NOT NULL DEFAULT '{}'"#,
+                r#"CREATE INDEX "idx-url-tags" ON "url" USING gin ("tags")"#,
+            ],
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        execute(
+            manager,
+            vec![
+                r#"DROP INDEX IF EXISTS "idx-url-tags""#,
+                r#"ALTER TABLE "url" DROP COLUMN "tags""#,
+            ],
+        )
+        .await
+    }
+}
@@ -0,0 +1,33 @@
+use sea_orm_migration::prelude::*;
+
+use crate::sql::execute;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Clicks recorded so far, kept up to date by the click writer so links can be sorted by it
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        execute(
+            manager,
+            vec![
+                r#"ALTER TABLE "url" ADD COLUMN "click_count" bigint NOT NULL DEFAULT 0"#,
+                r#"UPDATE "url" SET "click_count" = (SELECT count(*) FROM "click" WHERE "click"."url_id" = "url"."id")"#,
+                r#"CREATE INDEX "idx-url-owner-click-count" ON "url" ("owner_id", "click_count")"#,
+            ],
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        execute(
+            manager,
+            vec![
+                r#"DROP INDEX IF EXISTS "idx-url-owner-click-count""#,
+                r#"ALTER TABLE "url" DROP COLUMN "click_count""#,
+            ],
+        )
+        .await
+    }
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+use crate::sql::execute;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Indexes behind the search, filters and sorts of the link list. The host expression
+// has to stay in sync with the one used by the domain filter.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        execute(
+            manager,
+            vec![
+                r#"CREATE EXTENSION IF NOT EXISTS pg_trgm"#,
+                r#"CREATE INDEX "idx-url-search" ON "url" USING gin ("name" gin_trgm_ops, "slug" gin_trgm_ops, "redirect_to" gin_trgm_ops)"#,
+                r#"CREATE INDEX "idx-url-owner-created-at" ON "url" ("owner_id", "created_at", "id")"#,
+                r#"CREATE INDEX "idx-url-owner-updated-at" ON "url" ("owner_id", (COALESCE("updated_at", "created_at")))"#,
+                r#"CREATE INDEX "idx-url-owner-host" ON "url" ("owner_id", (lower(substring("redirect_to" from '^[^:/?#]+://(?:[^/?#@]*@)?([^/?#:]+)'))))"#,
+            ],
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        execute(
+            manager,
+            vec![
+                r#"DROP INDEX IF EXISTS "idx-url-owner-host""#,
+                r#"DROP INDEX IF EXISTS "idx-url-owner-updated-at""#,
+                r#"DROP INDEX IF EXISTS "idx-url-owner-created-at""#,
+                r#"DROP INDEX IF EXISTS "idx-url-search""#,
+            ],
+        )
+        .await
+    }
+}
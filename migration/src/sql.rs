@@ -0,0 +1,21 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{ConnectionTrait, Statement},
+};
+
+// Runs raw statements in order, for what the schema builder can't express (partial and
+// expression indexes, extensions, backfills)
+pub(crate) async fn execute(
+    manager: &SchemaManager<'_>,
+    statements: Vec<&str>,
+) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    let backend = manager.get_database_backend();
+
+    for sql in statements {
+        db.execute(Statement::from_string(backend, sql.to_owned()))
+            .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use std::net::IpAddr;
+
+use axum::http::{header, HeaderMap};
+use sea_orm::{prelude::Uuid, ActiveValue::Set};
+use sha2::{Digest, Sha256};
+
+use crate::entity::click;
+
+use super::parse_user_agent;
+
+// A single visit of a link, built from the request that resolved its slug
+#[derive(Debug, Clone)]
+pub struct ClickEvent {
+    pub url_id: Uuid,
+    pub created_at: chrono::NaiveDateTime,
+    pub referrer: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_hash: Option<String>,
+}
+
+impl ClickEvent {
+    pub fn new(url_id: Uuid, headers: &HeaderMap, ip: Option<IpAddr>, secret: &[u8]) -> Self {
+        let header_value = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .filter(|value| !value.is_empty())
+                .map(ToOwned::to_owned)
+        };
+
+        Self {
+            url_id,
+            created_at: chrono::Utc::now().naive_utc(),
+            referrer: header_value(header::REFERER),
+            user_agent: header_value(header::USER_AGENT),
+            ip_hash: ip.map(|ip| hash_ip(secret, &ip)),
+        }
+    }
+}
+
+// Visitor addresses are never stored in clear, only a keyed digest used to count unique visitors
+pub fn hash_ip(secret: &[u8], ip: &IpAddr) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(secret);
+    hasher.update(ip.to_string().as_bytes());
+
+    format!("{:x}", hasher.finalize())
+}
+
+impl From<ClickEvent> for click::ActiveModel {
+    fn from(v: ClickEvent) -> Self {
+        let device_info = v
+            .user_agent
+            .as_deref()
+            .map(parse_user_agent)
+            .unwrap_or_default();
+
+        Self {
+            id: Set(Uuid::new_v4()),
+            url_id: Set(v.url_id),
+            created_at: Set(v.created_at),
+            referrer: Set(v.referrer),
+            user_agent: Set(v.user_agent),
+            ip_hash: Set(v.ip_hash),
+            browser: Set(device_info.browser),
+            os: Set(device_info.os),
+            device: Set(device_info.device),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use sea_orm::{
+    prelude::Uuid, sea_query::Expr, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QuerySelect, TransactionTrait,
+};
+use serde::Serialize;
+use tokio::{
+    sync::mpsc::{self, error::TrySendError},
+    task::JoinHandle,
+    time::MissedTickBehavior,
+};
+
+use crate::{
+    configuration::ClickSettings,
+    entity::{click, url},
+};
+
+use super::ClickEvent;
+
+#[derive(Debug, Default)]
+struct IngestCounters {
+    received: AtomicU64,
+    dropped: AtomicU64,
+    flushed: AtomicU64,
+    failed: AtomicU64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IngestStats {
+    pub received: u64,
+    pub dropped: u64,
+    pub flushed: u64,
+    pub failed: u64,
+    pub queued: usize,
+}
+
+// Handle used by the redirect path to hand clicks over to the background writer.
+// Recording never waits: when the queue is full the click is dropped and counted.
+#[derive(Debug, Clone)]
+pub struct ClickIngestor {
+    sender: mpsc::Sender<ClickEvent>,
+    counters: Arc<IngestCounters>,
+}
+
+impl ClickIngestor {
+    // The writer task stops once every handle is dropped, after flushing what is left in the queue
+    pub fn spawn(db: DatabaseConnection, settings: &ClickSettings) -> (Self, JoinHandle<()>) {
+        let (sender, receiver) = mpsc::channel(settings.queue_size.max(1));
+        let counters = Arc::new(IngestCounters::default());
+
+        let writer = ClickWriter {
+            db,
+            receiver,
+            counters: counters.clone(),
+            batch_size: settings.batch_size.max(1),
+            flush_interval: Duration::from_millis(settings.flush_interval_ms.max(1)),
+        };
+        let handle = tokio::spawn(writer.run());
+
+        (Self { sender, counters }, handle)
+    }
+
+    pub fn record(&self, event: ClickEvent) {
+        self.counters.received.fetch_add(1, Ordering::Relaxed);
+
+        match self.sender.try_send(event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(event)) | Err(TrySendError::Closed(event)) => {
+                self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!(
+                    "click queue unavailable, dropping click for link {}",
+                    event.url_id
+                );
+            }
+        }
+    }
+
+    pub fn stats(&self) -> IngestStats {
+        IngestStats {
+            received: self.counters.received.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            flushed: self.counters.flushed.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            queued: self.sender.max_capacity() - self.sender.capacity(),
+        }
+    }
+}
+
+struct ClickWriter {
+    db: DatabaseConnection,
+    receiver: mpsc::Receiver<ClickEvent>,
+    counters: Arc<IngestCounters>,
+    batch_size: usize,
+    flush_interval: Duration,
+}
+
+impl ClickWriter {
+    async fn run(mut self) {
+        let mut buffer = Vec::with_capacity(self.batch_size);
+        let mut ticker = tokio::time::interval(self.flush_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                event = self.receiver.recv() => match event {
+                    Some(event) => {
+                        buffer.push(event);
+                        if buffer.len() >= self.batch_size {
+                            self.flush(&mut buffer).await;
+                        }
+                    }
+                    // Every handle is gone, the server is shutting down
+                    None => break,
+                },
+                _ = ticker.tick() => self.flush(&mut buffer).await,
+            }
+        }
+
+        self.flush(&mut buffer).await;
+        tracing::debug!("click writer stopped");
+    }
+
+    async fn flush(&self, buffer: &mut Vec<ClickEvent>) {
+        if buffer.is_empty() {
+            return;
+        }
+
+        let count = buffer.len() as u64;
+
+        match write_clicks(&self.db, buffer.drain(..)).await {
+            Ok(discarded) => {
+                self.counters
+                    .flushed
+                    .fetch_add(count - discarded, Ordering::Relaxed);
+                tracing::debug!("flushed {} clicks", count - discarded);
+
+                if discarded > 0 {
+                    self.counters
+                        .dropped
+                        .fetch_add(discarded, Ordering::Relaxed);
+                    tracing::warn!("dropped {} clicks on links deleted meanwhile", discarded);
+                }
+            }
+            Err(err) => {
+                self.counters.failed.fetch_add(count, Ordering::Relaxed);
+                tracing::error!("couldn't write {} clicks: {}", count, err);
+            }
+        }
+    }
+}
+
+// Clicks are stored along with the per link counters, in a single transaction. Links deleted
+// for good since their clicks were recorded would fail the whole batch, their clicks are left
+// out and counted in the returned number.
+async fn write_clicks(
+    db: &DatabaseConnection,
+    events: impl Iterator<Item = ClickEvent>,
+) -> Result<u64, DbErr> {
+    let events: Vec<ClickEvent> = events.collect();
+    let url_ids: HashSet<Uuid> = events.iter().map(|event| event.url_id).collect();
+
+    let txn = db.begin().await?;
+
+    // Locking the links keeps them from being deleted before the clicks are in
+    let live_ids: HashSet<Uuid> = url::Entity::find()
+        .select_only()
+        .column(url::Column::Id)
+        .filter(url::Column::Id.is_in(url_ids))
+        .lock_shared()
+        .into_values::<Uuid, url::Column>()
+        .all(&txn)
+        .await?
+        .into_iter()
+        .collect();
+
+    let mut counts: HashMap<Uuid, i64> = HashMap::new();
+    let total = events.len() as u64;
+    let models: Vec<click::ActiveModel> = events
+        .into_iter()
+        .filter(|event| live_ids.contains(&event.url_id))
+        .inspect(|event| *counts.entry(event.url_id).or_default() += 1)
+        .map(click::ActiveModel::from)
+        .collect();
+    let discarded = total - models.len() as u64;
+
+    if !models.is_empty() {
+        click::Entity::insert_many(models).exec(&txn).await?;
+    }
+
+    for (url_id, count) in counts {
+        url::Entity::update_many()
+            .col_expr(
+                url::Column::ClickCount,
+                Expr::col(url::Column::ClickCount).add(count),
+            )
+            .filter(url::Column::Id.eq(url_id))
+            .exec(&txn)
+            .await?;
+    }
+
+    txn.commit().await?;
+
+    Ok(discarded)
+}
@@ -0,0 +1,7 @@
+mod click_event;
+mod click_ingestor;
+mod user_agent;
+
+pub use click_event::*;
+pub use click_ingestor::*;
+pub use user_agent::*;
@@ -0,0 +1,69 @@
+use woothee::parser::Parser;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub device: Option<String>,
+}
+
+// woothee reports fields it couldn't detect as "UNKNOWN"
+fn known(value: &str) -> Option<String> {
+    match value {
+        "" | woothee::woothee::VALUE_UNKNOWN => None,
+        value => Some(value.to_owned()),
+    }
+}
+
+pub fn parse_user_agent(user_agent: &str) -> DeviceInfo {
+    let Some(result) = Parser::new().parse(user_agent) else {
+        return DeviceInfo::default();
+    };
+
+    let device = match result.category {
+        "pc" => Some("desktop"),
+        "smartphone" | "mobilephone" => Some("mobile"),
+        "crawler" => Some("bot"),
+        "appliance" => Some("appliance"),
+        "misc" => Some("other"),
+        _ => None,
+    };
+
+    DeviceInfo {
+        browser: known(result.name),
+        os: known(result.os),
+        device: device.map(Into::into),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_desktop_browser() {
+        let info = parse_user_agent(
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/108.0.0.0 Safari/537.36",
+        );
+
+        assert_eq!(info.browser.as_deref(), Some("Chrome"));
+        assert_eq!(info.os.as_deref(), Some("Windows 10"));
+        assert_eq!(info.device.as_deref(), Some("desktop"));
+    }
+
+    #[test]
+    fn parse_mobile_browser() {
+        let info = parse_user_agent(
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 16_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.1 Mobile/15E148 Safari/604.1",
+        );
+
+        assert_eq!(info.browser.as_deref(), Some("Safari"));
+        assert_eq!(info.os.as_deref(), Some("iPhone"));
+        assert_eq!(info.device.as_deref(), Some("mobile"));
+    }
+
+    #[test]
+    fn parse_unknown_agent() {
+        assert_eq!(parse_user_agent("made-up-agent"), DeviceInfo::default());
+    }
+}
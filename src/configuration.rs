@@ -1,6 +1,8 @@
+use axum::http::StatusCode;
+use ipnet::IpNet;
 use sea_orm::ConnectOptions;
 use serde_aux::field_attributes::deserialize_number_from_string;
-use std::path::PathBuf;
+use std::{net::IpAddr, path::PathBuf};
 
 use config::{Config, ConfigError, Environment, File, FileFormat};
 use serde::Deserialize;
@@ -13,6 +15,23 @@ pub struct ApplicationSettings {
     pub hash_secret: String,
     pub jwt_secret: String,
     pub cors_origin: String,
+    #[serde(default)]
+    pub links: LinkSettings,
+    #[serde(default)]
+    pub clicks: ClickSettings,
+    #[serde(default)]
+    pub trash: TrashSettings,
+    #[serde(default)]
+    pub auth: AuthSettings,
+    // Sign in through an OpenID Connect provider, disabled when left out
+    #[serde(default)]
+    pub oidc: Option<OidcSettings>,
+    #[serde(default)]
+    pub email: EmailSettings,
+    #[serde(default)]
+    pub password_hashing: PasswordHashingSettings,
+    #[serde(default)]
+    pub proxy: ProxySettings,
 }
 
 impl ApplicationSettings {
@@ -21,6 +40,541 @@ impl ApplicationSettings {
     }
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct LinkSettings {
+    #[serde(default)]
+    pub redirect_status: RedirectStatus,
+    // Characters and starting length of the slugs generated when the client omits one
+    #[serde(default = "LinkSettings::default_slug_alphabet")]
+    pub slug_alphabet: String,
+    #[serde(default = "LinkSettings::default_slug_length")]
+    pub slug_length: usize,
+    #[serde(default)]
+    pub scheduled: ScheduledLinkSettings,
+    // Lifetime of the cookie that spares visitors from re-entering a link password
+    #[serde(default = "LinkSettings::default_unlock_ttl_minutes")]
+    pub unlock_ttl_minutes: i64,
+    // Origin short links are shared with (https://dino.ly), the host of the request is used
+    // when left out
+    #[serde(default)]
+    pub public_url: Option<String>,
+    // Operations accepted in a single batch request
+    #[serde(default = "LinkSettings::default_max_batch_operations")]
+    pub max_batch_operations: usize,
+}
+
+impl LinkSettings {
+    fn default_slug_alphabet() -> String {
+        // Characters that are easily mistaken for one another are left out
+        "abcdefghijkmnpqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ23456789".into()
+    }
+    fn default_slug_length() -> usize {
+        7
+    }
+    fn default_unlock_ttl_minutes() -> i64 {
+        30
+    }
+    fn default_max_batch_operations() -> usize {
+        100
+    }
+}
+
+impl Default for LinkSettings {
+    fn default() -> Self {
+        Self {
+            redirect_status: RedirectStatus::default(),
+            slug_alphabet: Self::default_slug_alphabet(),
+            slug_length: Self::default_slug_length(),
+            scheduled: ScheduledLinkSettings::default(),
+            unlock_ttl_minutes: Self::default_unlock_ttl_minutes(),
+            public_url: None,
+            max_batch_operations: Self::default_max_batch_operations(),
+        }
+    }
+}
+
+// Response served for links whose activation window hasn't opened yet,
+// visitors are redirected instead when `redirect_to` is set
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScheduledLinkSettings {
+    #[serde(default)]
+    pub status: ClientErrorStatus,
+    #[serde(default = "ScheduledLinkSettings::default_message")]
+    pub message: String,
+    #[serde(default)]
+    pub redirect_to: Option<String>,
+}
+
+impl ScheduledLinkSettings {
+    fn default_message() -> String {
+        "link is not available yet".into()
+    }
+}
+
+impl Default for ScheduledLinkSettings {
+    fn default() -> Self {
+        Self {
+            status: ClientErrorStatus::default(),
+            message: Self::default_message(),
+            redirect_to: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "u16")]
+pub struct ClientErrorStatus(StatusCode);
+
+impl Default for ClientErrorStatus {
+    fn default() -> Self {
+        Self(StatusCode::NOT_FOUND)
+    }
+}
+
+impl TryFrom<u16> for ClientErrorStatus {
+    type Error = String;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match StatusCode::from_u16(value) {
+            Ok(status) if status.is_client_error() => Ok(Self(status)),
+            _ => Err(format!("unsupported status {value}, expected a 4xx status")),
+        }
+    }
+}
+
+impl From<ClientErrorStatus> for StatusCode {
+    fn from(value: ClientErrorStatus) -> Self {
+        value.0
+    }
+}
+
+// Sizing of the in-process queue clicks go through before being written in batches
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClickSettings {
+    #[serde(default = "ClickSettings::default_queue_size")]
+    pub queue_size: usize,
+    #[serde(default = "ClickSettings::default_batch_size")]
+    pub batch_size: usize,
+    #[serde(default = "ClickSettings::default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+}
+
+impl ClickSettings {
+    fn default_queue_size() -> usize {
+        10_000
+    }
+    fn default_batch_size() -> usize {
+        500
+    }
+    fn default_flush_interval_ms() -> u64 {
+        1_000
+    }
+}
+
+impl Default for ClickSettings {
+    fn default() -> Self {
+        Self {
+            queue_size: Self::default_queue_size(),
+            batch_size: Self::default_batch_size(),
+            flush_interval_ms: Self::default_flush_interval_ms(),
+        }
+    }
+}
+
+// Reverse proxies the app is deployed behind. Their X-Forwarded-For header is only read when
+// the connection comes from one of them, none are trusted by default.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProxySettings {
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+impl ProxySettings {
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|proxy| proxy.contains(&ip))
+    }
+}
+
+// Cost of the Argon2id password hashes. Raising it upgrades the hash of each user on their
+// next successful login.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PasswordHashingSettings {
+    #[serde(default = "PasswordHashingSettings::default_memory_kib")]
+    pub memory_kib: u32,
+    #[serde(default = "PasswordHashingSettings::default_iterations")]
+    pub iterations: u32,
+    #[serde(default = "PasswordHashingSettings::default_parallelism")]
+    pub parallelism: u32,
+}
+
+impl PasswordHashingSettings {
+    fn default_memory_kib() -> u32 {
+        argon2::Params::DEFAULT_M_COST
+    }
+    fn default_iterations() -> u32 {
+        argon2::Params::DEFAULT_T_COST
+    }
+    fn default_parallelism() -> u32 {
+        argon2::Params::DEFAULT_P_COST
+    }
+
+    pub fn params(&self) -> Result<argon2::Params, argon2::Error> {
+        argon2::Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+    }
+}
+
+impl Default for PasswordHashingSettings {
+    fn default() -> Self {
+        Self {
+            memory_kib: Self::default_memory_kib(),
+            iterations: Self::default_iterations(),
+            parallelism: Self::default_parallelism(),
+        }
+    }
+}
+
+// Access tokens are kept short lived, clients renew them with their refresh token
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthSettings {
+    #[serde(default = "AuthSettings::default_access_token_ttl_minutes")]
+    pub access_token_ttl_minutes: i64,
+    #[serde(default = "AuthSettings::default_refresh_token_ttl_days")]
+    pub refresh_token_ttl_days: i64,
+    #[serde(default)]
+    pub login_throttle: LoginThrottleSettings,
+    #[serde(default)]
+    pub totp: TotpSettings,
+}
+
+impl AuthSettings {
+    fn default_access_token_ttl_minutes() -> i64 {
+        15
+    }
+    fn default_refresh_token_ttl_days() -> i64 {
+        30
+    }
+
+    pub fn access_token_ttl(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.access_token_ttl_minutes)
+    }
+
+    pub fn refresh_token_ttl(&self) -> chrono::Duration {
+        chrono::Duration::days(self.refresh_token_ttl_days)
+    }
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        Self {
+            access_token_ttl_minutes: Self::default_access_token_ttl_minutes(),
+            refresh_token_ttl_days: Self::default_refresh_token_ttl_days(),
+            login_throttle: LoginThrottleSettings::default(),
+            totp: TotpSettings::default(),
+        }
+    }
+}
+
+// Failed logins are counted per username and per client ip. Each failure doubles the wait
+// before the next attempt, reaching the threshold locks logins for a while.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoginThrottleSettings {
+    #[serde(default = "LoginThrottleSettings::default_max_failures_per_username")]
+    pub max_failures_per_username: i32,
+    // Higher, several users can share an address
+    #[serde(default = "LoginThrottleSettings::default_max_failures_per_ip")]
+    pub max_failures_per_ip: i32,
+    #[serde(default = "LoginThrottleSettings::default_base_delay_secs")]
+    pub base_delay_secs: i64,
+    #[serde(default = "LoginThrottleSettings::default_max_delay_secs")]
+    pub max_delay_secs: i64,
+    #[serde(default = "LoginThrottleSettings::default_lockout_minutes")]
+    pub lockout_minutes: i64,
+    // Failures older than that are forgotten
+    #[serde(default = "LoginThrottleSettings::default_failure_window_minutes")]
+    pub failure_window_minutes: i64,
+}
+
+impl LoginThrottleSettings {
+    fn default_max_failures_per_username() -> i32 {
+        5
+    }
+    fn default_max_failures_per_ip() -> i32 {
+        20
+    }
+    fn default_base_delay_secs() -> i64 {
+        1
+    }
+    fn default_max_delay_secs() -> i64 {
+        60
+    }
+    fn default_lockout_minutes() -> i64 {
+        15
+    }
+    fn default_failure_window_minutes() -> i64 {
+        15
+    }
+
+    // Wait imposed after the given number of consecutive failures
+    pub fn delay(&self, failures: i32) -> chrono::Duration {
+        let exponent = failures.saturating_sub(1).clamp(0, 30) as u32;
+        let delay = self
+            .base_delay_secs
+            .saturating_mul(2_i64.saturating_pow(exponent))
+            .min(self.max_delay_secs);
+
+        chrono::Duration::seconds(delay)
+    }
+
+    pub fn lockout(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.lockout_minutes)
+    }
+
+    pub fn failure_window(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.failure_window_minutes)
+    }
+}
+
+impl Default for LoginThrottleSettings {
+    fn default() -> Self {
+        Self {
+            max_failures_per_username: Self::default_max_failures_per_username(),
+            max_failures_per_ip: Self::default_max_failures_per_ip(),
+            base_delay_secs: Self::default_base_delay_secs(),
+            max_delay_secs: Self::default_max_delay_secs(),
+            lockout_minutes: Self::default_lockout_minutes(),
+            failure_window_minutes: Self::default_failure_window_minutes(),
+        }
+    }
+}
+
+// Two-factor login with time-based one-time passwords, users opt in from their account
+#[derive(Debug, Clone, Deserialize)]
+pub struct TotpSettings {
+    // Name authenticator apps show next to the codes
+    #[serde(default = "TotpSettings::default_issuer")]
+    pub issuer: String,
+    // Time given to enter a code once the password has been checked
+    #[serde(default = "TotpSettings::default_challenge_ttl_minutes")]
+    pub challenge_ttl_minutes: i64,
+    #[serde(default = "TotpSettings::default_recovery_codes")]
+    pub recovery_codes: usize,
+}
+
+impl TotpSettings {
+    fn default_issuer() -> String {
+        "Dinoly".into()
+    }
+    fn default_challenge_ttl_minutes() -> i64 {
+        5
+    }
+    fn default_recovery_codes() -> usize {
+        10
+    }
+
+    pub fn challenge_ttl(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.challenge_ttl_minutes)
+    }
+}
+
+impl Default for TotpSettings {
+    fn default() -> Self {
+        Self {
+            issuer: Self::default_issuer(),
+            challenge_ttl_minutes: Self::default_challenge_ttl_minutes(),
+            recovery_codes: Self::default_recovery_codes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcSettings {
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    // Callback registered with the provider, it has to point to /api/user/oidc/callback
+    pub redirect_url: String,
+    #[serde(default = "OidcSettings::default_scopes")]
+    pub scopes: String,
+    // Time given to the user to sign in with the provider
+    #[serde(default = "OidcSettings::default_login_ttl_minutes")]
+    pub login_ttl_minutes: i64,
+    // Issuers trusted to link accounts that sign in with a password, others have to use
+    // the password
+    #[serde(default)]
+    pub trusted_link_issuers: Vec<String>,
+    // How long the discovery document and signing keys of the provider are reused
+    #[serde(default = "OidcSettings::default_metadata_ttl_minutes")]
+    pub metadata_ttl_minutes: u64,
+}
+
+impl OidcSettings {
+    fn default_scopes() -> String {
+        "openid email profile".into()
+    }
+    fn default_login_ttl_minutes() -> i64 {
+        10
+    }
+    fn default_metadata_ttl_minutes() -> u64 {
+        60
+    }
+
+    pub fn login_ttl(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.login_ttl_minutes)
+    }
+
+    pub fn metadata_ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.metadata_ttl_minutes * 60)
+    }
+
+    pub fn can_link_password_accounts(&self, issuer: &str) -> bool {
+        self.trusted_link_issuers.iter().any(|trusted| trusted == issuer)
+    }
+}
+
+// Emails sent to verify addresses and reset passwords, the links point to frontend pages
+// which post the token back to the api
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmailSettings {
+    #[serde(default = "EmailSettings::default_from")]
+    pub from: String,
+    #[serde(default = "EmailSettings::default_verify_email_url")]
+    pub verify_email_url: String,
+    #[serde(default = "EmailSettings::default_reset_password_url")]
+    pub reset_password_url: String,
+    #[serde(default = "EmailSettings::default_verification_ttl_hours")]
+    pub verification_ttl_hours: i64,
+    #[serde(default = "EmailSettings::default_reset_ttl_minutes")]
+    pub reset_ttl_minutes: i64,
+    #[serde(default)]
+    pub mailer: MailerSettings,
+}
+
+impl EmailSettings {
+    fn default_from() -> String {
+        "Dinoly <no-reply@localhost>".into()
+    }
+    fn default_verify_email_url() -> String {
+        "http://localhost:3000/verify-email".into()
+    }
+    fn default_reset_password_url() -> String {
+        "http://localhost:3000/reset-password".into()
+    }
+    fn default_verification_ttl_hours() -> i64 {
+        48
+    }
+    fn default_reset_ttl_minutes() -> i64 {
+        30
+    }
+
+    pub fn verification_ttl(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.verification_ttl_hours)
+    }
+
+    pub fn reset_ttl(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.reset_ttl_minutes)
+    }
+}
+
+impl Default for EmailSettings {
+    fn default() -> Self {
+        Self {
+            from: Self::default_from(),
+            verify_email_url: Self::default_verify_email_url(),
+            reset_password_url: Self::default_reset_password_url(),
+            verification_ttl_hours: Self::default_verification_ttl_hours(),
+            reset_ttl_minutes: Self::default_reset_ttl_minutes(),
+            mailer: MailerSettings::default(),
+        }
+    }
+}
+
+// How emails leave the server, the file and log mailers are meant for development and tests
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum MailerSettings {
+    Smtp {
+        host: String,
+        #[serde(deserialize_with = "deserialize_number_from_string")]
+        port: u16,
+        username: String,
+        password: String,
+    },
+    File {
+        directory: PathBuf,
+    },
+    #[default]
+    Log,
+}
+
+// How long soft deleted links stay restorable before the purge job removes them
+#[derive(Debug, Clone, Deserialize)]
+pub struct TrashSettings {
+    #[serde(default = "TrashSettings::default_retention_days")]
+    pub retention_days: u32,
+    #[serde(default = "TrashSettings::default_purge_interval_secs")]
+    pub purge_interval_secs: u64,
+}
+
+impl TrashSettings {
+    fn default_retention_days() -> u32 {
+        30
+    }
+    fn default_purge_interval_secs() -> u64 {
+        3_600
+    }
+
+    pub fn retention(&self) -> chrono::Duration {
+        chrono::Duration::days(self.retention_days.into())
+    }
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        Self {
+            retention_days: Self::default_retention_days(),
+            purge_interval_secs: Self::default_purge_interval_secs(),
+        }
+    }
+}
+
+// Status code used when redirecting a visitor to a link destination
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "u16")]
+pub enum RedirectStatus {
+    MovedPermanently,
+    Found,
+    #[default]
+    TemporaryRedirect,
+    PermanentRedirect,
+}
+
+impl TryFrom<u16> for RedirectStatus {
+    type Error = String;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            301 => Ok(Self::MovedPermanently),
+            302 => Ok(Self::Found),
+            307 => Ok(Self::TemporaryRedirect),
+            308 => Ok(Self::PermanentRedirect),
+            _ => Err(format!(
+                "unsupported redirect status {value}, expected one of 301, 302, 307 or 308"
+            )),
+        }
+    }
+}
+
+impl From<RedirectStatus> for StatusCode {
+    fn from(value: RedirectStatus) -> Self {
+        match value {
+            RedirectStatus::MovedPermanently => StatusCode::MOVED_PERMANENTLY,
+            RedirectStatus::Found => StatusCode::FOUND,
+            RedirectStatus::TemporaryRedirect => StatusCode::TEMPORARY_REDIRECT,
+            RedirectStatus::PermanentRedirect => StatusCode::PERMANENT_REDIRECT,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DatabaseSettings {
     pub host: String,
@@ -58,19 +612,14 @@ pub struct GlobalConfig {
     pub database: DatabaseSettings,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Default, PartialEq, Eq)]
 pub enum Env {
     Test,
+    #[default]
     Development,
     Production,
 }
 
-impl Default for Env {
-    fn default() -> Self {
-        Self::Development
-    }
-}
-
 impl From<String> for Env {
     fn from(value: String) -> Self {
         match value.to_lowercase().as_str() {
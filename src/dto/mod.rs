@@ -1,2 +1,3 @@
+pub mod stats;
 pub mod url;
 pub mod user;
@@ -0,0 +1,27 @@
+use sea_orm::{prelude::*, FromQueryResult};
+use serde::Serialize;
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct ClickBucket {
+    pub bucket: DateTime,
+    pub clicks: i64,
+}
+
+// A value (referrer, browser...) and the number of clicks it accounts for
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct TopValue {
+    pub value: Option<String>,
+    pub clicks: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkStats {
+    pub total_clicks: i64,
+    pub unique_visitors: i64,
+    pub interval: String,
+    pub from: DateTime,
+    pub to: DateTime,
+    pub series: Vec<ClickBucket>,
+    pub top_referrers: Vec<TopValue>,
+    pub top_browsers: Vec<TopValue>,
+}
@@ -1,5 +1,5 @@
 use sea_orm::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::entity::url;
 
@@ -12,11 +12,29 @@ pub struct Url {
     pub owner_id: Uuid,
     pub created_at: DateTime,
     pub updated_at: Option<DateTime>,
+    pub expires_at: Option<DateTime>,
+    pub expired_redirect_to: Option<String>,
+    pub is_expired: bool,
+    pub active_from: Option<DateTime>,
+    pub state: LinkState,
+    pub has_password: bool,
+    pub max_clicks: Option<i32>,
+    pub remaining_clicks: Option<i32>,
+    pub tags: Vec<String>,
+    pub click_count: i64,
 }
 
 impl From<url::Model> for Url {
     fn from(v: url::Model) -> Self {
+        let now = chrono::Utc::now().naive_utc();
         Self {
+            is_expired: v.is_expired(now),
+            state: LinkState::of(&v, now),
+            has_password: v.password_hash.is_some(),
+            max_clicks: v.max_clicks,
+            remaining_clicks: v.remaining_clicks,
+            tags: v.tags,
+            click_count: v.click_count,
             id: v.id,
             name: v.name,
             slug: v.slug,
@@ -24,6 +42,62 @@ impl From<url::Model> for Url {
             owner_id: v.owner_id,
             created_at: v.created_at,
             updated_at: v.updated_at,
+            expires_at: v.expires_at,
+            expired_redirect_to: v.expired_redirect_to,
+            active_from: v.active_from,
         }
     }
 }
+
+// Where a link stands in its activation window, an expired link stays expired
+// even if its window never opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkState {
+    Scheduled,
+    Active,
+    Expired,
+}
+
+impl LinkState {
+    pub fn of(link: &url::Model, now: DateTime) -> Self {
+        if link.is_expired(now) {
+            Self::Expired
+        } else if link.is_scheduled(now) {
+            Self::Scheduled
+        } else {
+            Self::Active
+        }
+    }
+}
+
+// Claims of the cookie set once a visitor entered the password of a link,
+// the fingerprint ties it to the password it was issued for
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkUnlockClaims {
+    pub sub: String,
+    pub aud: String,
+    pub fp: String,
+    pub exp: i64,
+}
+
+// A soft deleted link, along with the time it will be purged at
+#[derive(Debug, Serialize)]
+pub struct TrashedUrl {
+    #[serde(flatten)]
+    pub link: Url,
+    pub deleted_at: DateTime,
+    pub purge_at: DateTime,
+}
+
+impl TrashedUrl {
+    pub fn new(v: url::Model, retention: chrono::Duration) -> Option<Self> {
+        let deleted_at = v.deleted_at?;
+
+        Some(Self {
+            link: v.into(),
+            deleted_at,
+            purge_at: deleted_at + retention,
+        })
+    }
+}
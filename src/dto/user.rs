@@ -1,7 +1,7 @@
 use sea_orm::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::entity::{sea_orm_active_enums::Provider, user};
+use crate::entity::{api_key, sea_orm_active_enums::Provider, session, user};
 
 #[derive(Debug, Serialize)]
 pub struct User {
@@ -9,6 +9,8 @@ pub struct User {
     pub username: String,
     pub email: String,
     pub provider: Provider,
+    pub email_verified_at: Option<DateTime>,
+    pub two_factor_enabled: bool,
     pub created_at: DateTime,
     pub updated_at: Option<DateTime>,
 }
@@ -16,10 +18,52 @@ pub struct User {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
+    // Identifies the token in the revocation list
+    pub jti: String,
+    // Session the token was issued for
+    pub sid: String,
     pub iat: i64,
     pub exp: i64,
 }
 
+// Claims of the cookie holding an OpenID Connect login in progress, until the provider
+// sends the user back
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OidcLoginClaims {
+    pub aud: String,
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub exp: i64,
+}
+
+// Claims of the tokens mailed to verify an email or reset a password, the fingerprint
+// makes them unusable once the email or the password they were issued for changed
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailTokenClaims {
+    pub sub: String,
+    pub aud: String,
+    pub fp: String,
+    pub exp: i64,
+}
+
+// Claims of the token standing in for the password while a two-factor login completes
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginChallengeClaims {
+    pub sub: String,
+    pub aud: String,
+    pub exp: i64,
+}
+
+// Issued on register, login and refresh
+#[derive(Debug, Serialize)]
+pub struct TokenPair {
+    pub token: String,
+    pub refresh_token: String,
+    // Lifetime of the access token, in seconds
+    pub expires_in: i64,
+}
+
 impl From<user::Model> for User {
     fn from(v: user::Model) -> Self {
         Self {
@@ -27,8 +71,58 @@ impl From<user::Model> for User {
             username: v.username,
             email: v.email,
             provider: v.provider,
+            email_verified_at: v.email_verified_at,
+            two_factor_enabled: v.totp_enabled_at.is_some(),
             created_at: v.created_at,
             updated_at: v.updated_at,
         }
     }
 }
+
+#[derive(Debug, Serialize)]
+pub struct Session {
+    pub id: Uuid,
+    pub created_at: DateTime,
+    pub last_used_at: DateTime,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    // Whether it is the session making the request
+    pub current: bool,
+}
+
+impl Session {
+    pub fn new(v: session::Model, current_session_id: Uuid) -> Self {
+        Self {
+            current: v.id == current_session_id,
+            id: v.id,
+            created_at: v.created_at,
+            last_used_at: v.last_used_at,
+            user_agent: v.user_agent,
+            ip: v.ip,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    // Lets the owner recognize a key, the secret part is never shown again
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime,
+    pub last_used_at: Option<DateTime>,
+}
+
+impl From<api_key::Model> for ApiKey {
+    fn from(v: api_key::Model) -> Self {
+        Self {
+            id: v.id,
+            name: v.name,
+            prefix: v.prefix,
+            scopes: v.scopes.split_whitespace().map(String::from).collect(),
+            created_at: v.created_at,
+            last_used_at: v.last_used_at,
+        }
+    }
+}
@@ -2,6 +2,14 @@
 
 pub mod prelude;
 
+pub mod api_key;
+pub mod click;
+pub mod login_throttle;
+pub mod recovery_code;
+pub mod refresh_token;
+pub mod revoked_token;
 pub mod sea_orm_active_enums;
+pub mod security_event;
+pub mod session;
 pub mod url;
 pub mod user;
@@ -1,4 +1,12 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.10.5
 
+pub use super::api_key::Entity as ApiKey;
+pub use super::click::Entity as Click;
+pub use super::login_throttle::Entity as LoginThrottle;
+pub use super::recovery_code::Entity as RecoveryCode;
+pub use super::refresh_token::Entity as RefreshToken;
+pub use super::revoked_token::Entity as RevokedToken;
+pub use super::security_event::Entity as SecurityEvent;
+pub use super::session::Entity as Session;
 pub use super::url::Entity as Url;
 pub use super::user::Entity as User;
@@ -9,19 +9,30 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
     pub name: String,
-    #[sea_orm(unique)]
     pub slug: String,
     #[sea_orm(column_type = "Text")]
     pub redirect_to: String,
     pub owner_id: Uuid,
     pub created_at: DateTime,
     pub updated_at: Option<DateTime>,
-    #[sea_orm(unique)]
     pub deleted_at: Option<DateTime>,
+    pub expires_at: Option<DateTime>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub expired_redirect_to: Option<String>,
+    pub active_from: Option<DateTime>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[serde(skip_serializing)]
+    pub password_hash: Option<String>,
+    pub max_clicks: Option<i32>,
+    pub remaining_clicks: Option<i32>,
+    pub tags: Vec<String>,
+    pub click_count: i64,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::click::Entity")]
+    Click,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::OwnerId",
@@ -32,6 +43,12 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::click::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Click.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
@@ -39,3 +56,13 @@ impl Related<super::user::Entity> for Entity {
 }
 
 impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn is_expired(&self, now: DateTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    pub fn is_scheduled(&self, now: DateTime) -> bool {
+        self.active_from.is_some_and(|active_from| active_from > now)
+    }
+}
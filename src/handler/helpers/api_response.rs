@@ -4,18 +4,14 @@ use serde::Serialize;
 use super::{ApiResponseError, ApiResponseErrorObject};
 
 // Response types
+#[derive(Default)]
 pub enum ApiResponseType {
+    #[default]
     SuccessWithData,
     StatusCodeOnly,
     Error,
 }
 
-impl Default for ApiResponseType {
-    fn default() -> Self {
-        Self::SuccessWithData
-    }
-}
-
 pub enum ApiResponseData<T: Serialize> {
     Data {
         data: T,
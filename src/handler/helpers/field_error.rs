@@ -5,6 +5,9 @@ use validator::ValidationErrors;
 
 #[derive(Debug, Serialize)]
 pub struct ResponseError {
+    // Machine readable reason, set for errors that aren't plain validation failures
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<&'static str>,
     pub fields: Option<HashMap<String, String>>,
 }
 
@@ -17,6 +20,7 @@ impl From<ValidationErrors> for ResponseError {
             hash_map.insert(k.into(), msg);
         });
         Self {
+            code: None,
             fields: Some(hash_map),
         }
     }
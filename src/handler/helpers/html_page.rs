@@ -0,0 +1,59 @@
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+
+// Browsers ask for html while api clients (curl, fetch...) are served json
+pub fn wants_html(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(|accept| accept.contains("text/html"))
+        .unwrap_or(false)
+}
+
+pub fn html_page(status: StatusCode, title: &str, message: &str) -> Response {
+    let page = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+</head>
+<body>
+<h1>{title}</h1>
+<p>{message}</p>
+</body>
+</html>"#
+    );
+
+    (status, Html(page)).into_response()
+}
+
+// Form posted back to the link path, browsers resolve the empty action to the current url
+pub fn unlock_page(status: StatusCode, error: Option<&str>) -> Response {
+    let error = error
+        .map(|error| format!("<p><strong>{error}</strong></p>\n"))
+        .unwrap_or_default();
+    let page = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Password required</title>
+</head>
+<body>
+<h1>Password required</h1>
+<p>This link is protected, enter its password to continue.</p>
+{error}<form method="post" action="">
+<input type="password" name="password" autocomplete="current-password" required autofocus>
+<button type="submit">Unlock</button>
+</form>
+</body>
+</html>"#
+    );
+
+    (status, Html(page)).into_response()
+}
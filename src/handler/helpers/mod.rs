@@ -1,7 +1,9 @@
 mod api_error;
 mod api_response;
 mod field_error;
+mod html_page;
 
 pub use api_error::*;
 pub use api_response::*;
 pub use field_error::*;
+pub use html_page::*;
@@ -0,0 +1,24 @@
+use std::fmt::Write;
+
+use axum::extract::State;
+
+use crate::analytics::ClickIngestor;
+
+// Click pipeline counters in the prometheus text format
+pub async fn metrics_handler(State(clicks): State<ClickIngestor>) -> String {
+    let stats = clicks.stats();
+    let metrics = [
+        ("clicks_received_total", "counter", stats.received),
+        ("clicks_dropped_total", "counter", stats.dropped),
+        ("clicks_flushed_total", "counter", stats.flushed),
+        ("clicks_failed_total", "counter", stats.failed),
+        ("clicks_queued", "gauge", stats.queued as u64),
+    ];
+
+    metrics
+        .iter()
+        .fold(String::new(), |mut body, (name, kind, value)| {
+            let _ = writeln!(body, "# TYPE dinoly_{name} {kind}\ndinoly_{name} {value}");
+            body
+        })
+}
@@ -1,10 +1,14 @@
 pub mod helpers;
 pub mod utils;
 
+mod metrics_handler;
+mod redirect_handler;
 mod status_handler;
 mod url_handler;
 mod user_handler;
 
+pub use metrics_handler::*;
+pub use redirect_handler::*;
 pub use status_handler::*;
 pub use user_handler::*;
 
@@ -0,0 +1,5 @@
+mod redirect_slug_handler;
+mod unlock_slug_handler;
+
+pub use redirect_slug_handler::*;
+pub use unlock_slug_handler::*;
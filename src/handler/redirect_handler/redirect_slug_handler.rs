@@ -0,0 +1,143 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+};
+
+use crate::{
+    analytics::{ClickEvent, ClickIngestor},
+    configuration::LinkSettings,
+    entity::url,
+    handler::{
+        helpers::{html_page, unlock_page, wants_html, ApiResponseData, ApiResponseError},
+        utils::{is_link_unlocked, ClientIp},
+    },
+    router::Secrets,
+};
+
+enum ApiError {
+    LinkNotFound,
+    LinkExpired,
+    LinkScheduled(StatusCode, String),
+    LinkLocked,
+    ClickLimitReached,
+    DBInternalError,
+}
+
+impl ApiError {
+    fn into_response(self, html: bool) -> Response {
+        let (status, message) = match &self {
+            ApiError::LinkNotFound => (StatusCode::NOT_FOUND, "link not found"),
+            ApiError::LinkExpired => (StatusCode::GONE, "link has expired"),
+            ApiError::LinkScheduled(status, message) => (*status, message.as_str()),
+            ApiError::LinkLocked => (StatusCode::UNAUTHORIZED, "link is password protected"),
+            ApiError::ClickLimitReached => (StatusCode::GONE, "link has reached its click limit"),
+            ApiError::DBInternalError => (StatusCode::INTERNAL_SERVER_ERROR, "internal error"),
+        };
+        if html {
+            if let ApiError::LinkLocked = self {
+                return unlock_page(status, None);
+            }
+            return html_page(
+                status,
+                status.canonical_reason().unwrap_or_default(),
+                message,
+            );
+        }
+        match self {
+            ApiError::DBInternalError => ApiResponseData::<()>::status_code(status),
+            // The scheduled message comes from the settings, it isn't known at compile time
+            _ => ApiResponseData::Error {
+                error: ApiResponseError::Simple(message.to_owned()),
+                status,
+            },
+        }
+        .into_response()
+    }
+}
+
+#[tracing::instrument(skip(secrets, clicks, headers, ip))]
+pub async fn redirect_slug_handler(
+    Path(slug): Path<String>,
+    State(db): State<DatabaseConnection>,
+    State(settings): State<LinkSettings>,
+    State(secrets): State<Secrets>,
+    State(clicks): State<ClickIngestor>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+) -> Response {
+    let html = wants_html(&headers);
+
+    let link = match url::Entity::find()
+        .filter(url::Column::Slug.eq(slug))
+        .filter(url::Column::DeletedAt.is_null())
+        .one(&db)
+        .await
+    {
+        Ok(Some(link)) => link,
+        Ok(None) => return ApiError::LinkNotFound.into_response(html),
+        Err(_) => return ApiError::DBInternalError.into_response(html),
+    };
+
+    let status: StatusCode = settings.redirect_status.into();
+    let now = chrono::Utc::now().naive_utc();
+
+    // Expired links send visitors to their fallback when they have one
+    if link.is_expired(now) {
+        return match link.expired_redirect_to {
+            Some(fallback) => (status, [(header::LOCATION, fallback)]).into_response(),
+            None => ApiError::LinkExpired.into_response(html),
+        };
+    }
+
+    if link.is_scheduled(now) {
+        let scheduled = settings.scheduled;
+        return match scheduled.redirect_to {
+            Some(redirect_to) => (status, [(header::LOCATION, redirect_to)]).into_response(),
+            None => ApiError::LinkScheduled(scheduled.status.into(), scheduled.message)
+                .into_response(html),
+        };
+    }
+
+    // Protected links are only followed once the visitor has entered the password
+    if !is_link_unlocked(secrets.jwt_secret.as_bytes(), &link, &headers) {
+        return ApiError::LinkLocked.into_response(html);
+    }
+
+    // Limited links take a visit before redirecting, the decrement only goes through
+    // while some are left so concurrent visitors can't overshoot the limit
+    if link.remaining_clicks.is_some() {
+        match take_visit(&db, &link).await {
+            Ok(true) => {}
+            Ok(false) => return ApiError::ClickLimitReached.into_response(html),
+            Err(_) => return ApiError::DBInternalError.into_response(html),
+        }
+    }
+
+    // Clicks are written in the background so the redirect never waits on the database
+    clicks.record(ClickEvent::new(
+        link.id,
+        &headers,
+        ip,
+        secrets.hash_secret.as_bytes(),
+    ));
+
+    (status, [(header::LOCATION, link.redirect_to)]).into_response()
+}
+
+async fn take_visit(db: &DatabaseConnection, link: &url::Model) -> Result<bool, DbErr> {
+    let res = url::Entity::update_many()
+        .col_expr(
+            url::Column::RemainingClicks,
+            Expr::col(url::Column::RemainingClicks).sub(1),
+        )
+        .filter(url::Column::Id.eq(link.id))
+        .filter(url::Column::RemainingClicks.gt(0))
+        .exec(db)
+        .await?;
+
+    Ok(res.rows_affected == 1)
+}
@@ -0,0 +1,142 @@
+use std::net::IpAddr;
+
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Form,
+};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Deserialize;
+
+use crate::{
+    configuration::{AuthSettings, LinkSettings},
+    entity::url,
+    handler::{
+        helpers::{html_page, unlock_page},
+        user_handler::{clear_failures, record_failure, retry_after, ThrottleKeys},
+        utils::{encode_link_unlock, link_unlock_cookie, verify_password, ClientIp},
+    },
+    router::Secrets,
+};
+
+#[derive(Deserialize)]
+pub struct UnlockLinkInput {
+    pub password: String,
+}
+
+enum ApiError {
+    LinkNotFound,
+    // Seconds before the next attempt is allowed
+    TooManyAttempts(i64),
+    WrongPassword,
+    InternalError,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match self {
+            ApiError::LinkNotFound => {
+                html_page(StatusCode::NOT_FOUND, "Not Found", "link not found")
+            }
+            ApiError::TooManyAttempts(secs) => (
+                [(header::RETRY_AFTER, secs.to_string())],
+                html_page(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "Too Many Requests",
+                    "too many wrong passwords, try again later",
+                ),
+            )
+                .into_response(),
+            ApiError::WrongPassword => {
+                unlock_page(StatusCode::UNAUTHORIZED, Some("Wrong password"))
+            }
+            ApiError::InternalError => html_page(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal Server Error",
+                "internal error",
+            ),
+        }
+    }
+}
+
+// Submission of the unlock form served by the redirect route. On success the visitor is sent
+// back to the link with a cookie, the redirect and its click are then handled as usual.
+// Wrong passwords count toward the same lockout as wrong logins, per link and per address.
+#[tracing::instrument(skip(secrets, input))]
+pub async fn unlock_slug_handler(
+    Path(slug): Path<String>,
+    State(db): State<DatabaseConnection>,
+    State(settings): State<LinkSettings>,
+    State(auth): State<AuthSettings>,
+    State(secrets): State<Secrets>,
+    ClientIp(ip): ClientIp,
+    Form(input): Form<UnlockLinkInput>,
+) -> Response {
+    unlock_link(&db, &settings, &auth, &secrets, ip, &slug, &input.password)
+        .await
+        .unwrap_or_else(IntoResponse::into_response)
+}
+
+async fn unlock_link(
+    db: &DatabaseConnection,
+    settings: &LinkSettings,
+    auth: &AuthSettings,
+    secrets: &Secrets,
+    ip: Option<IpAddr>,
+    slug: &str,
+    password: &str,
+) -> Result<Response, ApiError> {
+    let link = url::Entity::find()
+        .filter(url::Column::Slug.eq(slug))
+        .filter(url::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|_| ApiError::InternalError)?
+        .ok_or(ApiError::LinkNotFound)?;
+
+    // See other makes the browser follow up with a GET
+    let back_to_link = [(header::LOCATION, format!("/{slug}"))];
+
+    let Some(password_hash) = &link.password_hash else {
+        return Ok((StatusCode::SEE_OTHER, back_to_link).into_response());
+    };
+
+    let keys = ThrottleKeys::for_link(link.id, ip);
+    let wait = retry_after(db, &auth.login_throttle, &keys)
+        .await
+        .map_err(|_| ApiError::InternalError)?;
+    if let Some(secs) = wait {
+        return Err(ApiError::TooManyAttempts(secs));
+    }
+
+    let valid = verify_password(
+        secrets.hash_secret.as_bytes(),
+        password.as_bytes(),
+        password_hash,
+    )
+    .await
+    .map_err(|_| ApiError::InternalError)?;
+
+    if !valid {
+        record_failure(db, &auth.login_throttle, &keys)
+            .await
+            .map_err(|_| ApiError::InternalError)?;
+        return Err(ApiError::WrongPassword);
+    }
+
+    clear_failures(db, &keys)
+        .await
+        .map_err(|_| ApiError::InternalError)?;
+
+    let ttl = chrono::Duration::minutes(settings.unlock_ttl_minutes);
+    let token = encode_link_unlock(secrets.jwt_secret.as_bytes(), &link, password_hash, ttl)
+        .map_err(|_| ApiError::InternalError)?;
+
+    Ok((
+        StatusCode::SEE_OTHER,
+        [(header::SET_COOKIE, link_unlock_cookie(slug, &token, ttl))],
+        back_to_link,
+    )
+        .into_response())
+}
@@ -0,0 +1,298 @@
+use axum::{extract::State, http::StatusCode, Json};
+use sea_orm::{prelude::Uuid, ConnectionTrait, DatabaseConnection, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    configuration::{LinkSettings, PasswordHashingSettings},
+    dto::url::Url,
+    entity::url,
+    handler::{
+        helpers::{
+            ApiResponse, ApiResponseData, ApiResponseError, ApiResponseErrorObject, ResponseError,
+        },
+        utils::UserId,
+    },
+    router::Secrets,
+};
+
+use super::{create_link, delete_link, update_link, CreateLinkInput, LinkSource, UpdateLinkInput};
+
+enum ApiError {
+    EmptyBatch,
+    TooManyOperations,
+    OperationsFailed(BatchLinksResponse),
+    DBInternalError,
+}
+
+impl From<ApiError> for ApiResponseData<BatchLinksResponse> {
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::EmptyBatch => {
+                ApiResponseData::error(None, "no operation to run", StatusCode::BAD_REQUEST)
+            }
+            ApiError::TooManyOperations => ApiResponseData::error(
+                None,
+                "too many operations in a single batch",
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ),
+            ApiError::OperationsFailed(report) => ApiResponseData::error(
+                Some(report),
+                "some operations failed, none were applied",
+                StatusCode::BAD_REQUEST,
+            ),
+            ApiError::DBInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// An atomic batch is applied entirely or not at all, a best effort one keeps
+// the operations that succeeded
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchMode {
+    #[default]
+    Atomic,
+    BestEffort,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BatchOperation {
+    Create {
+        link: CreateLinkInput,
+    },
+    Update {
+        id: Uuid,
+        link: UpdateLinkInput,
+    },
+    Delete {
+        id: Uuid,
+        // Same as the permanent query of the delete handler
+        #[serde(default)]
+        permanent: bool,
+    },
+}
+
+impl BatchOperation {
+    fn name(&self) -> &'static str {
+        match self {
+            BatchOperation::Create { .. } => "create",
+            BatchOperation::Update { .. } => "update",
+            BatchOperation::Delete { .. } => "delete",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchLinksInput {
+    #[serde(default)]
+    pub mode: BatchMode,
+    pub operations: Vec<BatchOperation>,
+}
+
+// Outcome of one operation, status and error are those its own endpoint would answer with.
+// Operations undone because another one of an atomic batch failed are answered with a 409.
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    pub index: usize,
+    pub op: &'static str,
+    pub status: u16,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub rolled_back: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchLinksResponse {
+    pub mode: BatchMode,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub rolled_back: usize,
+    pub results: Vec<BatchResult>,
+}
+
+impl BatchLinksResponse {
+    fn new(mode: BatchMode, results: Vec<BatchResult>) -> Self {
+        let count = |outcome: fn(&BatchResult) -> bool| {
+            results.iter().filter(|result| outcome(result)).count()
+        };
+        let failed = count(|result| result.error.is_some());
+        let rolled_back = count(|result| result.rolled_back);
+
+        Self {
+            mode,
+            succeeded: results.len() - failed - rolled_back,
+            failed,
+            rolled_back,
+            results,
+        }
+    }
+}
+
+#[tracing::instrument(skip(secrets, batch))]
+pub async fn batch_url_handler(
+    UserId(user_id): UserId,
+    State(db): State<DatabaseConnection>,
+    State(settings): State<LinkSettings>,
+    State(secrets): State<Secrets>,
+    State(hashing): State<PasswordHashingSettings>,
+    Json(batch): Json<BatchLinksInput>,
+) -> ApiResponse<BatchLinksResponse, BatchLinksResponse> {
+    if batch.operations.is_empty() {
+        return Err(ApiError::EmptyBatch.into());
+    }
+    if batch.operations.len() > settings.max_batch_operations {
+        return Err(ApiError::TooManyOperations.into());
+    }
+
+    let context = BatchContext {
+        settings: &settings,
+        secrets: &secrets,
+        hashing: &hashing,
+        user_id,
+    };
+
+    let results = match batch.mode {
+        BatchMode::BestEffort => context.run_all(&db, batch.operations, false).await?,
+        BatchMode::Atomic => {
+            let txn = db.begin().await.map_err(|_| ApiError::DBInternalError)?;
+            let results = context.run_all(&txn, batch.operations, true).await?;
+
+            if results.iter().any(|result| result.error.is_some()) {
+                // Dropping the transaction rolls back the operations that succeeded
+                let results = results.into_iter().map(BatchResult::rolled_back).collect();
+                let report = BatchLinksResponse::new(batch.mode, results);
+
+                return Err(ApiError::OperationsFailed(report).into());
+            }
+
+            txn.commit().await.map_err(|_| ApiError::DBInternalError)?;
+            results
+        }
+    };
+
+    let data = BatchLinksResponse::new(batch.mode, results);
+
+    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+}
+
+struct BatchContext<'a> {
+    settings: &'a LinkSettings,
+    secrets: &'a Secrets,
+    hashing: &'a PasswordHashingSettings,
+    user_id: Uuid,
+}
+
+impl BatchContext<'_> {
+    // Operations run in order, a server error stops an atomic batch since its
+    // transaction can't be trusted anymore
+    async fn run_all<C: ConnectionTrait + TransactionTrait>(
+        &self,
+        db: &C,
+        operations: Vec<BatchOperation>,
+        atomic: bool,
+    ) -> Result<Vec<BatchResult>, ApiError> {
+        let mut results = Vec::with_capacity(operations.len());
+
+        for (index, operation) in operations.into_iter().enumerate() {
+            let op = operation.name();
+            let result = match self.run(db, operation).await {
+                Ok(link) => BatchResult {
+                    index,
+                    op,
+                    status: StatusCode::OK.as_u16(),
+                    rolled_back: false,
+                    link: link.map(Into::into),
+                    error: None,
+                },
+                Err(response) => {
+                    let (status, error) = failure(response);
+                    if atomic && status.is_server_error() {
+                        return Err(ApiError::DBInternalError);
+                    }
+
+                    BatchResult {
+                        index,
+                        op,
+                        status: status.as_u16(),
+                        rolled_back: false,
+                        link: None,
+                        error: Some(error),
+                    }
+                }
+            };
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
+    async fn run<C: ConnectionTrait + TransactionTrait>(
+        &self,
+        db: &C,
+        operation: BatchOperation,
+    ) -> Result<Option<url::Model>, ApiResponseData<ResponseError>> {
+        match operation {
+            BatchOperation::Create { link } => create_link(
+                db,
+                self.settings,
+                self.secrets,
+                self.hashing,
+                self.user_id,
+                link,
+                LinkSource::Client,
+            )
+            .await
+            .map(Some)
+            .map_err(Into::into),
+            BatchOperation::Update { id, link } => {
+                update_link(db, self.secrets, self.hashing, self.user_id, id, link)
+                    .await
+                    .map(Some)
+                    .map_err(Into::into)
+            }
+            BatchOperation::Delete { id, permanent } => {
+                delete_link(db, self.user_id, id, permanent)
+                    .await
+                    .map(|_| None)
+                    .map_err(Into::into)
+            }
+        }
+    }
+}
+
+impl BatchResult {
+    // Successful operations of a failed atomic batch didn't actually happen
+    fn rolled_back(self) -> Self {
+        if self.error.is_some() {
+            return self;
+        }
+
+        Self {
+            status: StatusCode::CONFLICT.as_u16(),
+            rolled_back: true,
+            link: None,
+            ..self
+        }
+    }
+}
+
+// Status and error body the operation's own endpoint would have answered with
+fn failure(response: ApiResponseData<ResponseError>) -> (StatusCode, Value) {
+    let (status, error) = match response {
+        ApiResponseData::Error { error, status } => (status, error),
+        ApiResponseData::StatusCode(status) | ApiResponseData::Data { status, .. } => {
+            let reason = status.canonical_reason().unwrap_or("operation failed");
+            (status, ApiResponseError::Simple(reason.to_owned()))
+        }
+    };
+    let error = serde_json::to_value(ApiResponseErrorObject::from(error)).unwrap_or_default();
+
+    (status, error)
+}
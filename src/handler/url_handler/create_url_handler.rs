@@ -1,27 +1,56 @@
 use axum::{extract::State, http::StatusCode, Json};
-use sea_orm::{prelude::Uuid, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, Set};
-use sea_orm::{Condition, QueryFilter};
+use chrono::{DateTime, Utc};
+use sea_orm::{prelude::Uuid, ActiveModelTrait, ConnectionTrait, DatabaseConnection, Set, TransactionTrait};
 use serde::{Deserialize, Serialize};
 use validator::{Validate, ValidationErrors};
 
+use crate::configuration::{LinkSettings, PasswordHashingSettings};
+use crate::router::Secrets;
 use crate::handler::helpers::{ResponseError, ApiResponseData};
 use crate::{
     dto::url::Url,
     entity::url,
     handler::{
         helpers::ApiResponse,
-        utils::UserId,
+        utils::{
+            deserialize_tags, generate_slug, hash_password, validate_active_window, validate_future_date, validate_slug,
+            validate_tags, UserId, MAX_SLUG_LENGTH, MIN_SLUG_LENGTH,
+        },
     },
 };
 
+use super::LinkConflict;
+
+// Generated slugs get one character longer every few collisions
+const SLUG_ATTEMPTS_PER_LENGTH: usize = 3;
+const MAX_SLUG_ATTEMPTS: usize = 10;
+
 #[derive(Debug, Validate, Deserialize)]
 pub struct CreateLinkInput {
     #[validate(length(min = 4, max = 20))]
     pub name: String,
-    #[validate(length(min = 5, max = 20))]
-    pub slug: String,
+    // Generated by the server when omitted
+    #[validate(length(min = 5, max = 20), custom = "validate_slug")]
+    pub slug: Option<String>,
     #[validate(url)]
     pub redirect_to: String,
+    #[validate(custom = "validate_future_date")]
+    pub expires_at: Option<DateTime<Utc>>,
+    // Where visitors are sent once the link has expired, they get a 410 otherwise
+    #[validate(url)]
+    pub expired_redirect_to: Option<String>,
+    // The link redirects nowhere until then
+    pub active_from: Option<DateTime<Utc>>,
+    // Visitors have to enter it before being redirected
+    #[validate(length(min = 4, max = 64))]
+    pub password: Option<String>,
+    // The link stops redirecting after that many visits, 1 makes a single use link
+    #[validate(range(min = 1))]
+    pub max_clicks: Option<i32>,
+    // Lowercase words used to group and filter links
+    #[serde(default, deserialize_with = "deserialize_tags")]
+    #[validate(custom = "validate_tags")]
+    pub tags: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -29,67 +58,185 @@ pub struct CreateLinkResponse {
     pub link: Url,
 }
 
-pub enum ApiError {
+// Reasons a link can't be created, shared with the import
+pub(crate) enum CreateLinkError {
     BadClientData(ValidationErrors),
     DBInternalError,
-    LinkExist,
+    LinkExist(LinkConflict),
+    SlugGenerationFailed,
+    HashingError,
 }
 
-impl From<ApiError> for ApiResponseData<ResponseError> {
-    fn from(value: ApiError) -> Self {
+// Where the link comes from. Imported links keep the expiration date they were exported with,
+// they are created already expired once it has passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LinkSource {
+    Client,
+    Import,
+}
+
+impl From<CreateLinkError> for ApiResponseData<ResponseError> {
+    fn from(value: CreateLinkError) -> Self {
         match value {
-            ApiError::BadClientData(err) => ApiResponseData::error(Some(ResponseError::from(err)), "invalid data from client", StatusCode::BAD_REQUEST),
-            ApiError::DBInternalError => ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR),
-            ApiError::LinkExist => ApiResponseData::error(None, "link with the name or slug provided already exists", StatusCode::BAD_REQUEST),
+            CreateLinkError::BadClientData(err) => ApiResponseData::error(Some(ResponseError::from(err)), "invalid data from client", StatusCode::BAD_REQUEST),
+            CreateLinkError::DBInternalError => ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR),
+            CreateLinkError::LinkExist(conflict) => conflict.into(),
+            CreateLinkError::SlugGenerationFailed => ApiResponseData::error(None, "couldn't generate a unique slug", StatusCode::INTERNAL_SERVER_ERROR),
+            CreateLinkError::HashingError => ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR),
         }
     }
 }
 
-#[tracing::instrument]
+#[tracing::instrument(skip(secrets, create_link_input))]
 pub async fn create_url_handler(
     UserId(user_id): UserId,
     State(db): State<DatabaseConnection>,
-    Json(create_link): Json<CreateLinkInput>,
+    State(settings): State<LinkSettings>,
+    State(secrets): State<Secrets>,
+    State(hashing): State<PasswordHashingSettings>,
+    Json(create_link_input): Json<CreateLinkInput>,
 ) -> ApiResponse<CreateLinkResponse, impl Serialize> {
-    create_link.validate().map_err(ApiError::BadClientData)?;
-
-    // Check if the user has a link with the same name or slug
-    let conditions = Condition::any()
-        .add(url::Column::Name.eq(create_link.name.clone()))
-        .add(url::Column::Slug.eq(create_link.slug.clone()));
-
-    match url::Entity::find()
-        .filter(conditions)
-        .one(&db)
-        .await
-        .map_err(|_| ApiError::DBInternalError)
-    {
-        Ok(link) => {
-            if link.is_some() {
-                return Err(ApiError::LinkExist.into());
-            }
+    let link = create_link(&db, &settings, &secrets, &hashing, user_id, create_link_input, LinkSource::Client).await?;
+
+    let data = CreateLinkResponse {
+        link: link.into(),
+    };
+
+    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+}
+
+// Validate the input and check that its slug and name are free
+pub(crate) async fn check_link<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    create_link: &CreateLinkInput,
+    source: LinkSource,
+) -> Result<(), CreateLinkError> {
+    let validation = match create_link.validate() {
+        // The future date check is the only one made on the expiration date
+        Err(mut errors) if source == LinkSource::Import => {
+            errors.errors_mut().remove("expires_at");
+            if errors.errors().is_empty() { Ok(()) } else { Err(errors) }
         }
-        Err(err) => return Err(err.into()),
+        validation => validation,
+    };
+    validation.map_err(CreateLinkError::BadClientData)?;
+
+    let active_from = create_link.active_from.map(|date| date.naive_utc());
+    let expires_at = create_link.expires_at.map(|date| date.naive_utc());
+    validate_active_window(active_from, expires_at).map_err(CreateLinkError::BadClientData)?;
+
+    let slug = create_link.slug.as_ref().map(|slug| slug.replace(' ', ""));
+
+    // Check if the slug or, among the user's links, the name is already used
+    let conflict = LinkConflict::find(
+        db,
+        user_id,
+        Some(&create_link.name),
+        slug.as_deref(),
+        None,
+    )
+    .await
+    .map_err(|_| CreateLinkError::DBInternalError)?;
+
+    match conflict {
+        Some(conflict) => Err(CreateLinkError::LinkExist(conflict)),
+        None => Ok(()),
+    }
+}
+
+pub(crate) async fn create_link<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    settings: &LinkSettings,
+    secrets: &Secrets,
+    hashing: &PasswordHashingSettings,
+    user_id: Uuid,
+    create_link: CreateLinkInput,
+    source: LinkSource,
+) -> Result<url::Model, CreateLinkError> {
+    check_link(db, user_id, &create_link, source).await?;
+
+    let active_from = create_link.active_from.map(|date| date.naive_utc());
+    let expires_at = create_link.expires_at.map(|date| date.naive_utc());
+    let slug = create_link.slug.map(|slug| slug.replace(' ', ""));
+
+    let password_hash = match create_link.password {
+        Some(password) => Some(
+            hash_password(secrets.hash_secret.as_bytes(), hashing, password.as_bytes())
+                .await
+                .map_err(|_| CreateLinkError::HashingError)?,
+        ),
+        None => None,
     };
 
     let now = chrono::Utc::now();
     let link = url::ActiveModel {
         id: Set(Uuid::new_v4()),
         name: Set(create_link.name),
-        slug: Set(create_link.slug.replace(' ', "")),
         redirect_to: Set(create_link.redirect_to),
         owner_id: Set(user_id),
         created_at: Set(now.naive_utc()),
+        expires_at: Set(expires_at),
+        expired_redirect_to: Set(create_link.expired_redirect_to),
+        active_from: Set(active_from),
+        password_hash: Set(password_hash),
+        max_clicks: Set(create_link.max_clicks),
+        remaining_clicks: Set(create_link.max_clicks),
+        tags: Set(create_link.tags.unwrap_or_default()),
         ..Default::default()
     };
-    let link: url::Model = link
-        .insert(&db)
-        .await
-        .map_err(|_| ApiError::DBInternalError)?;
 
-    let data = CreateLinkResponse {
-        link: link.into(),
-    };
+    match slug {
+        Some(slug) => insert_link(db, link, slug).await,
+        None => insert_link_with_generated_slug(db, settings, link).await,
+    }
+}
 
-    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+async fn insert_link<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    mut link: url::ActiveModel,
+    slug: String,
+) -> Result<url::Model, CreateLinkError> {
+    link.slug = Set(slug);
+
+    // Insert in its own (nested) transaction, so a conflict doesn't abort an enclosing one
+    let txn = db.begin().await.map_err(|_| CreateLinkError::DBInternalError)?;
+
+    // The slug or name may have been taken since they were checked
+    let link = link.insert(&txn).await.map_err(|err| match LinkConflict::from_db_error(&err) {
+        Some(conflict) => CreateLinkError::LinkExist(conflict),
+        None => CreateLinkError::DBInternalError,
+    })?;
+
+    txn.commit().await.map_err(|_| CreateLinkError::DBInternalError)?;
+
+    Ok(link)
+}
+
+async fn insert_link_with_generated_slug<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    settings: &LinkSettings,
+    link: url::ActiveModel,
+) -> Result<url::Model, CreateLinkError> {
+    let alphabet: Vec<char> = settings.slug_alphabet.chars().collect();
+    let mut length = settings.slug_length.clamp(MIN_SLUG_LENGTH, MAX_SLUG_LENGTH);
+
+    for attempt in 1..=MAX_SLUG_ATTEMPTS {
+        let slug = generate_slug(&alphabet, length);
+
+        if slug.chars().count() >= MIN_SLUG_LENGTH && validate_slug(&slug).is_ok() {
+            match insert_link(db, link.clone(), slug).await {
+                Err(CreateLinkError::LinkExist(LinkConflict::Slug)) => {}
+                result => return result,
+            }
+        }
+
+        // The keyspace is getting crowded for this length
+        if attempt % SLUG_ATTEMPTS_PER_LENGTH == 0 {
+            length = (length + 1).min(MAX_SLUG_LENGTH);
+        }
+    }
+
+    tracing::error!("couldn't generate a unique slug after {} attempts", MAX_SLUG_ATTEMPTS);
+    Err(CreateLinkError::SlugGenerationFailed)
 }
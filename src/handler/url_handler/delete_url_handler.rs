@@ -1,55 +1,90 @@
-use sea_orm::{prelude::Uuid, DatabaseConnection, EntityTrait, IntoActiveModel, Set, ActiveModelTrait};
+use sea_orm::{prelude::Uuid, ConnectionTrait, DatabaseConnection, EntityTrait, IntoActiveModel, Set, ActiveModelTrait, ModelTrait};
 use crate::{entity::url::{self, Entity as Link}, handler::helpers::ApiResponseData};
-use serde::Serialize;
-use axum::{http::StatusCode, extract::{Path, State}};
+use serde::{Deserialize, Serialize};
+use axum::{http::StatusCode, extract::{Path, Query, State}};
 
 use crate::handler::{helpers::ApiResponse, utils::UserId};
 
 
-pub enum ApiError {
+// Reasons a link can't be deleted, shared with the batch handler
+pub(crate) enum DeleteLinkError {
     LinkNotFound,
     ForbiddenDelete,
     DBInternalError,
 }
 
 
-impl<E> From<ApiError> for ApiResponseData<E> 
+impl<E> From<DeleteLinkError> for ApiResponseData<E> 
     where
         E: Serialize + 'static,
 {
-    fn from(value: ApiError) -> Self {
+    fn from(value: DeleteLinkError) -> Self {
         match value {
-            ApiError::LinkNotFound => ApiResponseData::error(None, "link not found", StatusCode::NOT_FOUND),
-            ApiError::ForbiddenDelete => ApiResponseData::StatusCode(StatusCode::FORBIDDEN),
-            ApiError::DBInternalError => ApiResponseData::StatusCode(StatusCode::INTERNAL_SERVER_ERROR),
+            DeleteLinkError::LinkNotFound => ApiResponseData::error(None, "link not found", StatusCode::NOT_FOUND),
+            DeleteLinkError::ForbiddenDelete => ApiResponseData::StatusCode(StatusCode::FORBIDDEN),
+            DeleteLinkError::DBInternalError => ApiResponseData::StatusCode(StatusCode::INTERNAL_SERVER_ERROR),
         }
     }
 }
 
+#[derive(Deserialize, Debug, Default)]
+pub struct DeleteParams {
+    // Skip the trash, also used to empty it link by link
+    #[serde(default)]
+    pub permanent: bool,
+}
+
 
 #[tracing::instrument]
 pub async fn delete_url_handler(
     UserId(user_id): UserId,
     Path(link_id): Path<Uuid>,
+    params: Option<Query<DeleteParams>>,
     State(db): State<DatabaseConnection>
 ) -> ApiResponse<(),()> {
+    let Query(params) = params.unwrap_or_default();
+
+    delete_link(&db, user_id, link_id, params.permanent).await?;
+
+    Ok(ApiResponseData::status_code(StatusCode::OK))
+}
+
+pub(crate) async fn delete_link<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    link_id: Uuid,
+    permanent: bool,
+) -> Result<(), DeleteLinkError> {
     let link = Link::find_by_id(link_id)
-        .one(&db)
+        .one(db)
         .await
-        .map_err(|_| ApiError::DBInternalError)?;
+        .map_err(|_| DeleteLinkError::DBInternalError)?;
+
+    let link: url::Model = link.ok_or(DeleteLinkError::LinkNotFound)?;
 
-    let link: url::Model = link.ok_or(ApiError::LinkNotFound)?;
-        
     if link.owner_id != user_id {
-        return Err(ApiError::ForbiddenDelete.into());
+        return Err(DeleteLinkError::ForbiddenDelete);
     };
 
+    if permanent {
+        link.delete(db)
+            .await
+            .map_err(|_| DeleteLinkError::DBInternalError)?;
+
+        return Ok(());
+    }
+
+    // Already in the trash
+    if link.deleted_at.is_some() {
+        return Err(DeleteLinkError::LinkNotFound);
+    }
+
     let mut link_model = link.into_active_model();
     link_model.deleted_at = Set(Some(chrono::Utc::now().naive_utc()));
 
-    link_model.update(&db)
+    link_model.update(db)
         .await
-        .map_err(|_| ApiError::DBInternalError)?;
-    
-    Ok(ApiResponseData::status_code(StatusCode::OK))
+        .map_err(|_| DeleteLinkError::DBInternalError)?;
+
+    Ok(())
 }
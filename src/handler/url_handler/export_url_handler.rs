@@ -0,0 +1,62 @@
+use std::io;
+
+use axum::{
+    body::StreamBody,
+    extract::{Query, State},
+    http::header,
+    response::IntoResponse,
+};
+use futures_util::TryStreamExt;
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use serde::Deserialize;
+
+use crate::{entity::url, handler::utils::UserId};
+
+use super::{ExportedLink, LinkFileFormat};
+
+#[derive(Debug, Deserialize)]
+pub struct ExportLinksParams {
+    // Json lines when omitted
+    pub format: Option<LinkFileFormat>,
+}
+
+// Links are streamed from the database, a failure midway cuts the response short
+#[tracing::instrument]
+pub async fn export_url_handler(
+    UserId(user_id): UserId,
+    Query(params): Query<ExportLinksParams>,
+    State(db): State<DatabaseConnection>,
+) -> impl IntoResponse {
+    let format = params.format.unwrap_or(LinkFileFormat::Json);
+
+    let rows = async_stream::try_stream! {
+        let conditions = Condition::all()
+            .add(url::Column::OwnerId.eq(user_id))
+            .add(url::Column::DeletedAt.is_null());
+        let mut links = url::Entity::find()
+            .filter(conditions)
+            .order_by_asc(url::Column::CreatedAt)
+            .order_by_asc(url::Column::Id)
+            .stream(&db)
+            .await
+            .map_err(io::Error::other)?;
+
+        let mut first = true;
+        while let Some(link) = links.try_next().await.map_err(io::Error::other)? {
+            yield format.encode(&ExportedLink::from(link), first)?;
+            first = false;
+        }
+    };
+    let rows = rows.inspect_err(|err: &io::Error| tracing::error!("link export failed: {err}"));
+
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_owned()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(r#"attachment; filename="links.{}""#, format.extension()),
+            ),
+        ],
+        StreamBody::new(rows),
+    )
+}
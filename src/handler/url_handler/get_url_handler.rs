@@ -12,7 +12,7 @@ use crate::{
 };
 
 
-pub enum ApiError {
+enum ApiError {
     LinkNotFound,
     ForbiddenRequest,
     DBInternalError,
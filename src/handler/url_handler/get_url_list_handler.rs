@@ -1,80 +1,298 @@
 use axum::{extract::{Query, State}, http::StatusCode};
-use sea_orm::QueryOrder;
-use sea_orm::{Condition, ColumnTrait, DatabaseConnection, EntityTrait};
+use chrono::{DateTime, Utc};
+use sea_orm::{sea_query::Expr, Order, PaginatorTrait, QueryOrder};
+use sea_orm::{Condition, ColumnTrait, DatabaseConnection, EntityTrait, Iden};
 use sea_orm::{QueryFilter, QuerySelect};
 use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError, ValidationErrors};
 
-use crate::{entity::url, handler::helpers::ApiResponseData};
+use crate::{entity::url, handler::helpers::{ApiResponseData, ResponseError}};
 use crate::{
-    dto::url::Url,
+    dto::url::{LinkState, Url},
     handler::{
         helpers::ApiResponse,
-        utils::UserId,
+        utils::{validate_tag, UserId},
     },
 };
 
+use super::LinkCursor;
+
+// Host of the destination, the same expression backs the "idx-url-owner-host" index
+const DESTINATION_HOST: &str = r#"lower(substring("url"."redirect_to" from '^[^:/?#]+://(?:[^/?#@]*@)?([^/?#:]+)'))"#;
+// Links never updated count as updated when they were created
+const LAST_UPDATE: &str = r#"COALESCE("url"."updated_at", "url"."created_at")"#;
+
+pub const DEFAULT_PAGE_SIZE: u64 = 10;
+pub const MAX_PAGE_SIZE: u64 = 100;
+
 #[derive(Debug, Serialize)]
 pub struct GetLinkListResponse {
     pub links: Vec<Url>,
+    // Links matching the filters, on every page
+    pub total: u64,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
 }
 
-pub enum ApiError {
+enum ApiError {
+    BadClientData(ValidationErrors),
     DBInternalError,
 }
 
-impl<E> From<ApiError> for ApiResponseData<E>
-    where
-        E: Serialize + 'static
-{
+impl From<ApiError> for ApiResponseData<ResponseError> {
     fn from(value: ApiError) -> Self {
         match value {
+            ApiError::BadClientData(err) => ApiResponseData::error(Some(ResponseError::from(err)), "invalid data from client", StatusCode::BAD_REQUEST),
             ApiError::DBInternalError => ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR),
         }
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default, Validate)]
 pub struct Pagination {
     pub offset: Option<u64>,
+    #[validate(range(min = 1, max = "MAX_PAGE_SIZE"))]
     pub limit: Option<u64>,
 }
 
-impl Default for Pagination {
-    fn default() -> Self {
-        Self {
-            offset: Some(0),
-            limit: Some(10),
+impl Pagination {
+    pub fn limit(&self) -> u64 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE)
+    }
+}
+
+// Opaque position returned as next_cursor or prev_cursor, takes over from the offset
+#[derive(Deserialize, Debug)]
+pub struct PageCursor {
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Validate)]
+pub struct LinkFilter {
+    pub state: Option<LinkState>,
+    // Searched for in the name, slug and destination of the links
+    #[validate(length(min = 1, max = 100))]
+    pub q: Option<String>,
+    #[validate(custom = "validate_tag")]
+    pub tag: Option<String>,
+    // Host of the destination, its subdomains match too
+    #[validate(length(min = 1, max = 253), custom = "validate_domain")]
+    pub domain: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+impl LinkFilter {
+    fn validate_all(&self) -> Result<(), ValidationErrors> {
+        self.validate()?;
+
+        match (self.created_after, self.created_before) {
+            (Some(after), Some(before)) if after > before => {
+                let mut errors = ValidationErrors::new();
+                errors.add("created_before", ValidationError::new("before_created_after"));
+                Err(errors)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn condition(&self) -> Condition {
+        Condition::all()
+            .add(self.state_condition())
+            .add_option(self.q.as_deref().map(search_condition))
+            .add_option(self.tag.as_ref().map(|tag| {
+                Expr::cust_with_values(r#""url"."tags" @> $1"#, [vec![tag.clone()]])
+            }))
+            .add_option(self.domain.as_ref().map(|domain| {
+                let host = format!("({DESTINATION_HOST} = $1 OR {DESTINATION_HOST} LIKE '%.' || $1)");
+                Expr::cust_with_values(&host, [domain.to_lowercase()])
+            }))
+            .add_option(self.created_after.map(|date| url::Column::CreatedAt.gte(date.naive_utc())))
+            .add_option(self.created_before.map(|date| url::Column::CreatedAt.lte(date.naive_utc())))
+    }
+
+    fn state_condition(&self) -> Condition {
+        let now = chrono::Utc::now().naive_utc();
+        let not_expired = Condition::any()
+            .add(url::Column::ExpiresAt.is_null())
+            .add(url::Column::ExpiresAt.gt(now));
+        let started = Condition::any()
+            .add(url::Column::ActiveFrom.is_null())
+            .add(url::Column::ActiveFrom.lte(now));
+
+        match self.state {
+            None => Condition::all(),
+            Some(LinkState::Expired) => Condition::all().add(url::Column::ExpiresAt.lte(now)),
+            Some(LinkState::Scheduled) => Condition::all()
+                .add(url::Column::ActiveFrom.gt(now))
+                .add(not_expired),
+            Some(LinkState::Active) => Condition::all().add(started).add(not_expired),
+        }
+    }
+}
+
+// Plain substring match, served by the trigram index
+fn search_condition(q: &str) -> Condition {
+    let escaped = q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    let pattern = format!("%{escaped}%");
+
+    [url::Column::Name, url::Column::Slug, url::Column::RedirectTo]
+        .into_iter()
+        .fold(Condition::any(), |condition, column| {
+            let search = format!(r#""url"."{}" ILIKE $1"#, column.to_string());
+            condition.add(Expr::cust_with_values(&search, [pattern.clone()]))
+        })
+}
+
+fn validate_domain(domain: &str) -> Result<(), ValidationError> {
+    if domain.starts_with('.')
+        || !domain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.'))
+    {
+        return Err(ValidationError::new("domain"));
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkSort {
+    Name,
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    ClickCount,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl LinkSort {
+    // Sql expression of the sort key, also compared against by the cursors
+    pub(crate) fn expression(&self) -> &'static str {
+        match self {
+            LinkSort::Name => r#""url"."name""#,
+            LinkSort::CreatedAt => r#""url"."created_at""#,
+            LinkSort::UpdatedAt => LAST_UPDATE,
+            LinkSort::ClickCount => r#""url"."click_count""#,
         }
     }
 }
 
+impl SortOrder {
+    fn reverse(self) -> Self {
+        match self {
+            SortOrder::Asc => SortOrder::Desc,
+            SortOrder::Desc => SortOrder::Asc,
+        }
+    }
+}
+
+impl From<SortOrder> for Order {
+    fn from(value: SortOrder) -> Self {
+        match value {
+            SortOrder::Asc => Order::Asc,
+            SortOrder::Desc => Order::Desc,
+        }
+    }
+}
+
+// Newest links first unless asked otherwise, the id breaks ties
+#[derive(Deserialize, Debug)]
+pub struct LinkSorting {
+    pub sort: Option<LinkSort>,
+    pub order: Option<SortOrder>,
+}
+
 #[tracing::instrument]
 pub async fn get_url_list_handler(
     UserId(user_id): UserId,
-    params: Option<Query<Pagination>>,
+    Query(params): Query<Pagination>,
+    Query(page): Query<PageCursor>,
+    Query(filter): Query<LinkFilter>,
+    Query(sorting): Query<LinkSorting>,
     State(db): State<DatabaseConnection>,
-) -> ApiResponse<GetLinkListResponse, ()> {
-    let Query(params) = params.unwrap_or_default();
+) -> ApiResponse<GetLinkListResponse, ResponseError> {
+    params.validate().map_err(ApiError::BadClientData)?;
+    filter.validate_all().map_err(ApiError::BadClientData)?;
+
+    let sort = sorting.sort.unwrap_or_default();
+    let order = sorting.order.unwrap_or_default();
+    let cursor = match page.cursor.as_deref() {
+        // A cursor only makes sense for the sort it was issued for
+        Some(cursor) => Some(
+            LinkCursor::decode(cursor)
+                .filter(|cursor| cursor.sort == sort && cursor.order == order)
+                .ok_or_else(invalid_cursor)?,
+        ),
+        None => None,
+    };
+
     let conditions = Condition::all()
         .add(url::Column::OwnerId.eq(user_id))
-        .add(url::Column::DeletedAt.is_null());
+        .add(url::Column::DeletedAt.is_null())
+        .add(filter.condition());
+    let total = url::Entity::find()
+        .filter(conditions.clone())
+        .count(&db)
+        .await
+        .map_err(|_| ApiError::DBInternalError)?;
+
+    // Paging backward walks the list in reverse from the cursor
+    let backward = cursor.as_ref().is_some_and(|cursor| cursor.backward);
+    let direction = if backward { order.reverse() } else { order };
     let mut query = url::Entity::find()
         .filter(conditions)
-        .order_by_desc(url::Column::CreatedAt);
+        .order_by(Expr::cust(sort.expression()), direction.into())
+        .order_by(url::Column::Id, direction.into());
 
-    if let Some(limit) = params.limit {
-        query = query.limit(limit);
+    match &cursor {
+        Some(cursor) => query = query.filter(cursor.condition().ok_or_else(invalid_cursor)?),
+        None => query = query.offset(params.offset.unwrap_or(0)),
     }
 
-    if let Some(offset) = params.offset {
-        query = query.offset(offset);
+    // One more link than asked tells whether there is another page
+    let limit = params.limit();
+    let mut links = query.limit(limit + 1).all(&db).await.map_err(|_| ApiError::DBInternalError)?;
+    let has_more = links.len() as u64 > limit;
+    links.truncate(limit as usize);
+
+    if backward {
+        links.reverse();
     }
 
-    let links = query.all(&db).await.map_err(|_| ApiError::DBInternalError)?;
+    let (more_before, more_after) = match backward {
+        true => (has_more, true),
+        false => (cursor.is_some() || params.offset.unwrap_or(0) > 0, has_more),
+    };
+    let next_cursor = links
+        .last()
+        .filter(|_| more_after)
+        .map(|link| LinkCursor::new(link, sort, order, false).encode());
+    let prev_cursor = links
+        .first()
+        .filter(|_| more_before)
+        .map(|link| LinkCursor::new(link, sort, order, true).encode());
 
     let data = GetLinkListResponse {
         links: links.into_iter().map(Into::into).collect(),
+        total,
+        next_cursor,
+        prev_cursor,
     };
 
     Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
 }
+
+fn invalid_cursor() -> ApiError {
+    let mut errors = ValidationErrors::new();
+    errors.add("cursor", ValidationError::new("cursor"));
+    ApiError::BadClientData(errors)
+}
@@ -0,0 +1,204 @@
+use std::str::FromStr;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use qrcode::EcLevel;
+use sea_orm::{prelude::Uuid, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Deserialize;
+use validator::{Validate, ValidationErrors};
+
+use crate::{
+    configuration::LinkSettings,
+    entity::url::{self, Entity as Link},
+    handler::{
+        helpers::{ApiResponseData, ResponseError},
+        utils::{run_blocking, validate_color, QrMatrix, QrStyle, RequestOrigin, Rgb, UserId},
+    },
+};
+
+const DEFAULT_SIZE: u32 = 512;
+// Quiet zone recommended by the QR code specification
+const DEFAULT_MARGIN: u32 = 4;
+
+enum ApiError {
+    BadClientData(ValidationErrors),
+    LinkNotFound,
+    ForbiddenRequest,
+    DBInternalError,
+    EncodingError,
+}
+
+impl From<ApiError> for ApiResponseData<ResponseError> {
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::BadClientData(err) => ApiResponseData::error(
+                Some(ResponseError::from(err)),
+                "invalid data from client",
+                StatusCode::BAD_REQUEST,
+            ),
+            ApiError::LinkNotFound => {
+                ApiResponseData::error(None, "link not found", StatusCode::NOT_FOUND)
+            }
+            ApiError::ForbiddenRequest => ApiResponseData::status_code(StatusCode::FORBIDDEN),
+            ApiError::DBInternalError | ApiError::EncodingError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFormat {
+    Svg,
+    Png,
+}
+
+impl QrFormat {
+    // First image type the client accepts, svg when it doesn't say
+    fn from_accept(headers: &HeaderMap) -> Self {
+        let accept = headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        accept
+            .split(',')
+            .map(|media_type| media_type.split(';').next().unwrap_or_default().trim())
+            .find_map(|media_type| match media_type {
+                "image/svg+xml" => Some(QrFormat::Svg),
+                "image/png" => Some(QrFormat::Png),
+                _ => None,
+            })
+            .unwrap_or(QrFormat::Svg)
+    }
+}
+
+// Higher levels survive more damage to a printed code, at the cost of denser codes
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub enum QrErrorCorrection {
+    L,
+    #[default]
+    M,
+    Q,
+    H,
+}
+
+impl From<QrErrorCorrection> for EcLevel {
+    fn from(value: QrErrorCorrection) -> Self {
+        match value {
+            QrErrorCorrection::L => EcLevel::L,
+            QrErrorCorrection::M => EcLevel::M,
+            QrErrorCorrection::Q => EcLevel::Q,
+            QrErrorCorrection::H => EcLevel::H,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QrCodeParams {
+    // Takes precedence over the Accept header
+    pub format: Option<QrFormat>,
+    #[validate(range(min = 64, max = 4096))]
+    pub size: Option<u32>,
+    #[validate(range(max = 32))]
+    pub margin: Option<u32>,
+    pub error_correction: Option<QrErrorCorrection>,
+    #[validate(custom = "validate_color")]
+    pub foreground: Option<String>,
+    #[validate(custom = "validate_color")]
+    pub background: Option<String>,
+}
+
+// QR code of the public short url of the link, to be printed or shared
+#[tracing::instrument]
+pub async fn get_url_qr_handler(
+    UserId(user_id): UserId,
+    Path(link_id): Path<Uuid>,
+    Query(params): Query<QrCodeParams>,
+    RequestOrigin(origin): RequestOrigin,
+    headers: HeaderMap,
+    State(db): State<DatabaseConnection>,
+    State(settings): State<LinkSettings>,
+) -> Response {
+    let public_url = settings.public_url.unwrap_or(origin);
+
+    render(&db, user_id, link_id, params, &headers, &public_url)
+        .await
+        .unwrap_or_else(|err| ApiResponseData::from(err).into_response())
+}
+
+async fn render(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    link_id: Uuid,
+    params: QrCodeParams,
+    headers: &HeaderMap,
+    public_url: &str,
+) -> Result<Response, ApiError> {
+    params.validate().map_err(ApiError::BadClientData)?;
+
+    let link = Link::find_by_id(link_id)
+        .filter(url::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|_| ApiError::DBInternalError)?;
+
+    let link: url::Model = link.ok_or(ApiError::LinkNotFound)?;
+
+    if link.owner_id != user_id {
+        return Err(ApiError::ForbiddenRequest);
+    };
+
+    let short_url = format!("{}/{}", public_url.trim_end_matches('/'), link.slug);
+    let ec_level = params.error_correction.unwrap_or_default().into();
+
+    // Colors were validated above
+    let color = |value: Option<String>, default: Rgb| {
+        value
+            .and_then(|value| Rgb::from_str(&value).ok())
+            .unwrap_or(default)
+    };
+    let style = QrStyle {
+        size: params.size.unwrap_or(DEFAULT_SIZE),
+        margin: params.margin.unwrap_or(DEFAULT_MARGIN),
+        foreground: color(params.foreground, Rgb::BLACK),
+        background: color(params.background, Rgb::WHITE),
+    };
+
+    let format = params
+        .format
+        .unwrap_or_else(|| QrFormat::from_accept(headers));
+    let (content_type, extension) = match format {
+        QrFormat::Svg => ("image/svg+xml", "svg"),
+        QrFormat::Png => ("image/png", "png"),
+    };
+
+    // A large png is millions of pixels to fill and compress, it is drawn on the blocking pool
+    let body = run_blocking(move || {
+        let matrix = QrMatrix::encode(&short_url, ec_level).ok()?;
+
+        match format {
+            QrFormat::Svg => Some(matrix.to_svg(&style).into_bytes()),
+            QrFormat::Png => matrix.to_png(&style).ok(),
+        }
+    })
+    .await
+    .ok_or(ApiError::EncodingError)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_owned()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(r#"inline; filename="{}.{extension}""#, link.slug),
+            ),
+            (header::VARY, header::ACCEPT.to_string()),
+        ],
+        body,
+    )
+        .into_response())
+}
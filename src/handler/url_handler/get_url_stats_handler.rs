@@ -0,0 +1,209 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{
+    prelude::Uuid, sea_query::Expr, ColumnTrait, Condition, DatabaseBackend, DatabaseConnection,
+    EntityTrait, FromQueryResult, QueryFilter, QueryOrder, QuerySelect, Statement,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dto::stats::{ClickBucket, LinkStats, TopValue},
+    entity::{
+        click,
+        url::{self, Entity as Link},
+    },
+    handler::{
+        helpers::{ApiResponse, ApiResponseData},
+        utils::UserId,
+    },
+};
+
+const TOP_VALUES_LIMIT: u64 = 10;
+const MAX_BUCKETS: i64 = 1000;
+
+enum ApiError {
+    LinkNotFound,
+    ForbiddenRequest,
+    InvalidRange,
+    DBInternalError,
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::LinkNotFound => {
+                ApiResponseData::error(None, "link not found", StatusCode::NOT_FOUND)
+            }
+            ApiError::ForbiddenRequest => ApiResponseData::status_code(StatusCode::FORBIDDEN),
+            ApiError::InvalidRange => {
+                ApiResponseData::error(None, "invalid time range", StatusCode::BAD_REQUEST)
+            }
+            ApiError::DBInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsInterval {
+    Hour,
+    #[default]
+    Day,
+    Week,
+}
+
+impl StatsInterval {
+    fn as_str(&self) -> &'static str {
+        match self {
+            StatsInterval::Hour => "hour",
+            StatsInterval::Day => "day",
+            StatsInterval::Week => "week",
+        }
+    }
+
+    fn duration(&self) -> Duration {
+        match self {
+            StatsInterval::Hour => Duration::hours(1),
+            StatsInterval::Day => Duration::days(1),
+            StatsInterval::Week => Duration::weeks(1),
+        }
+    }
+
+    // Range covered when the client doesn't provide a start date
+    fn default_span(&self) -> Duration {
+        match self {
+            StatsInterval::Hour => Duration::hours(48),
+            StatsInterval::Day => Duration::days(30),
+            StatsInterval::Week => Duration::weeks(12),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsParams {
+    pub interval: Option<StatsInterval>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetLinkStatsResponse {
+    pub stats: LinkStats,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct Totals {
+    total_clicks: i64,
+    unique_visitors: i64,
+}
+
+#[tracing::instrument]
+pub async fn get_url_stats_handler(
+    UserId(user_id): UserId,
+    Path(link_id): Path<Uuid>,
+    Query(params): Query<StatsParams>,
+    State(db): State<DatabaseConnection>,
+) -> ApiResponse<GetLinkStatsResponse, ()> {
+    let link = Link::find_by_id(link_id)
+        .filter(url::Column::DeletedAt.is_null())
+        .one(&db)
+        .await
+        .map_err(|_| ApiError::DBInternalError)?;
+
+    let link: url::Model = link.ok_or(ApiError::LinkNotFound)?;
+
+    if link.owner_id != user_id {
+        return Err(ApiError::ForbiddenRequest.into());
+    };
+
+    // Resolve the time range
+    let interval = params.interval.unwrap_or_default();
+    let to = params.to.unwrap_or_else(Utc::now).naive_utc();
+    let from = params
+        .from
+        .map(|from| from.naive_utc())
+        .unwrap_or(to - interval.default_span());
+
+    if from >= to || (to - from).num_seconds() / interval.duration().num_seconds() > MAX_BUCKETS {
+        return Err(ApiError::InvalidRange.into());
+    }
+
+    let conditions = Condition::all()
+        .add(click::Column::UrlId.eq(link.id))
+        .add(click::Column::CreatedAt.between(from, to));
+
+    let totals = click::Entity::find()
+        .select_only()
+        .column_as(Expr::col(click::Column::Id).count(), "total_clicks")
+        .column_as(Expr::cust("COUNT(DISTINCT ip_hash)"), "unique_visitors")
+        .filter(conditions.clone())
+        .into_model::<Totals>()
+        .one(&db)
+        .await
+        .map_err(|_| ApiError::DBInternalError)?
+        .ok_or(ApiError::DBInternalError)?;
+
+    // Buckets without clicks are kept so the series can be charted as is
+    let series = ClickBucket::find_by_statement(Statement::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        &format!(
+            r#"SELECT s.bucket AS bucket, COUNT(c.id) AS clicks
+            FROM generate_series(date_trunc('{unit}', $1::timestamp), $2::timestamp, interval '1 {unit}') AS s(bucket)
+            LEFT JOIN click c ON c.url_id = $3
+                AND c.created_at >= s.bucket AND c.created_at < s.bucket + interval '1 {unit}'
+                AND c.created_at BETWEEN $1 AND $2
+            GROUP BY s.bucket
+            ORDER BY s.bucket"#,
+            unit = interval.as_str()
+        ),
+        vec![from.into(), to.into(), link.id.into()],
+    ))
+    .all(&db)
+    .await
+    .map_err(|_| ApiError::DBInternalError)?;
+
+    let top_referrers = top_values(&db, conditions.clone(), click::Column::Referrer).await?;
+    let top_browsers = top_values(&db, conditions, click::Column::Browser).await?;
+
+    let data = GetLinkStatsResponse {
+        stats: LinkStats {
+            total_clicks: totals.total_clicks,
+            unique_visitors: totals.unique_visitors,
+            interval: interval.as_str().into(),
+            from,
+            to,
+            series,
+            top_referrers,
+            top_browsers,
+        },
+    };
+
+    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+}
+
+async fn top_values(
+    db: &DatabaseConnection,
+    conditions: Condition,
+    column: click::Column,
+) -> Result<Vec<TopValue>, ApiError> {
+    click::Entity::find()
+        .select_only()
+        .column_as(column, "value")
+        .column_as(Expr::col(click::Column::Id).count(), "clicks")
+        .filter(conditions)
+        .group_by(column)
+        .order_by_desc(Expr::cust("clicks"))
+        .limit(TOP_VALUES_LIMIT)
+        .into_model::<TopValue>()
+        .all(db)
+        .await
+        .map_err(|_| ApiError::DBInternalError)
+}
@@ -0,0 +1,69 @@
+use axum::{extract::{Query, State}, http::StatusCode};
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::Serialize;
+use validator::{Validate, ValidationErrors};
+
+use crate::{
+    configuration::TrashSettings,
+    dto::url::TrashedUrl,
+    entity::url,
+    handler::{
+        helpers::{ApiResponse, ApiResponseData, ResponseError},
+        utils::UserId,
+    },
+};
+
+use super::Pagination;
+
+#[derive(Debug, Serialize)]
+pub struct GetTrashResponse {
+    pub links: Vec<TrashedUrl>,
+}
+
+enum ApiError {
+    BadClientData(ValidationErrors),
+    DBInternalError,
+}
+
+impl From<ApiError> for ApiResponseData<ResponseError> {
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::BadClientData(err) => ApiResponseData::error(Some(ResponseError::from(err)), "invalid data from client", StatusCode::BAD_REQUEST),
+            ApiError::DBInternalError => ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+}
+
+#[tracing::instrument]
+pub async fn get_url_trash_handler(
+    UserId(user_id): UserId,
+    Query(params): Query<Pagination>,
+    State(db): State<DatabaseConnection>,
+    State(trash): State<TrashSettings>,
+) -> ApiResponse<GetTrashResponse, ResponseError> {
+    params.validate().map_err(ApiError::BadClientData)?;
+
+    let conditions = Condition::all()
+        .add(url::Column::OwnerId.eq(user_id))
+        .add(url::Column::DeletedAt.is_not_null());
+    let mut query = url::Entity::find()
+        .filter(conditions)
+        .order_by_desc(url::Column::DeletedAt)
+        .limit(params.limit());
+
+    if let Some(offset) = params.offset {
+        query = query.offset(offset);
+    }
+
+    let links = query.all(&db).await.map_err(|_| ApiError::DBInternalError)?;
+
+    let retention = trash.retention();
+    let data = GetTrashResponse {
+        links: links
+            .into_iter()
+            .filter_map(|link| TrashedUrl::new(link, retention))
+            .collect(),
+    };
+
+    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+}
@@ -0,0 +1,324 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use sea_orm::{prelude::Uuid, DatabaseConnection, TransactionTrait};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configuration::{LinkSettings, PasswordHashingSettings},
+    dto::url::Url,
+    handler::{
+        helpers::{ApiResponseData, ResponseError},
+        utils::UserId,
+    },
+    router::Secrets,
+};
+
+use super::{
+    check_link, create_link, CreateLinkError, CreateLinkInput, LinkConflict, LinkFileFormat,
+    LinkSource,
+};
+
+const MAX_IMPORT_ROWS: usize = 1000;
+const MALFORMED_ROW_CODE: &str = "malformed_row";
+
+enum ApiError {
+    UnsupportedFormat,
+    EmptyImport,
+    TooManyRows,
+    InvalidRows(ImportLinksResponse),
+    DBInternalError,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match self {
+            ApiError::UnsupportedFormat => ApiResponseData::<()>::error(
+                None,
+                "links are imported from text/csv or application/x-ndjson",
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            )
+            .into_response(),
+            ApiError::EmptyImport => {
+                ApiResponseData::<()>::error(None, "nothing to import", StatusCode::BAD_REQUEST)
+                    .into_response()
+            }
+            ApiError::TooManyRows => ApiResponseData::<()>::error(
+                None,
+                "too many rows to import at once",
+                StatusCode::PAYLOAD_TOO_LARGE,
+            )
+            .into_response(),
+            ApiError::InvalidRows(report) => ApiResponseData::error(
+                Some(report),
+                "invalid rows in the import, no link was created",
+                StatusCode::BAD_REQUEST,
+            )
+            .into_response(),
+            ApiError::DBInternalError => {
+                ApiResponseData::<()>::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+                    .into_response()
+            }
+        }
+    }
+}
+
+// A dry run only reports what a commit would do
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    #[default]
+    DryRun,
+    Commit,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportLinksParams {
+    pub mode: Option<ImportMode>,
+    // Guessed from the Content-Type header when omitted
+    pub format: Option<LinkFileFormat>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportRowStatus {
+    Valid,
+    Invalid,
+    Created,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportRow {
+    pub line: u64,
+    pub status: ImportRowStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ResponseError>,
+}
+
+impl ImportRow {
+    fn invalid(line: u64, error: ResponseError) -> Self {
+        Self {
+            line,
+            status: ImportRowStatus::Invalid,
+            link: None,
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportLinksResponse {
+    pub mode: ImportMode,
+    pub valid: usize,
+    pub invalid: usize,
+    pub created: usize,
+    pub rows: Vec<ImportRow>,
+}
+
+impl ImportLinksResponse {
+    fn new(mode: ImportMode, rows: Vec<ImportRow>) -> Self {
+        let count = |status| rows.iter().filter(|row| row.status == status).count();
+
+        Self {
+            mode,
+            valid: count(ImportRowStatus::Valid),
+            invalid: count(ImportRowStatus::Invalid),
+            created: count(ImportRowStatus::Created),
+            rows,
+        }
+    }
+}
+
+// Rows are checked like links sent to the create handler, a commit creates every
+// link or none of them
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(secrets, body))]
+pub async fn import_url_handler(
+    UserId(user_id): UserId,
+    Query(params): Query<ImportLinksParams>,
+    headers: HeaderMap,
+    State(db): State<DatabaseConnection>,
+    State(settings): State<LinkSettings>,
+    State(secrets): State<Secrets>,
+    State(hashing): State<PasswordHashingSettings>,
+    body: String,
+) -> Response {
+    let format = match params
+        .format
+        .or_else(|| LinkFileFormat::from_content_type(&headers))
+    {
+        Some(format) => format,
+        None => return ApiError::UnsupportedFormat.into_response(),
+    };
+    let mode = params.mode.unwrap_or_default();
+
+    let import = async {
+        let rows = check_rows(&db, user_id, format, &body).await?;
+
+        match mode {
+            ImportMode::DryRun => {
+                let rows = rows.into_iter().map(|(row, _)| row).collect();
+
+                Ok(ImportLinksResponse::new(mode, rows))
+            }
+            ImportMode::Commit => {
+                commit_rows(&db, &settings, &secrets, &hashing, user_id, rows).await
+            }
+        }
+    };
+
+    match import.await {
+        Ok(report) => ApiResponseData::success_with_data(report, StatusCode::OK).into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+async fn check_rows(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    format: LinkFileFormat,
+    body: &str,
+) -> Result<Vec<(ImportRow, Option<CreateLinkInput>)>, ApiError> {
+    let rows = format.parse_rows(body, MAX_IMPORT_ROWS + 1);
+    if rows.is_empty() {
+        return Err(ApiError::EmptyImport);
+    }
+    if rows.len() > MAX_IMPORT_ROWS {
+        return Err(ApiError::TooManyRows);
+    }
+
+    // Rows of the file can't share a slug or a name either
+    let mut names = HashSet::new();
+    let mut slugs = HashSet::new();
+    let mut checked = Vec::with_capacity(rows.len());
+
+    for (line, row) in rows {
+        let input = match row {
+            Ok(input) => input,
+            Err(_) => {
+                let error = ResponseError {
+                    code: Some(MALFORMED_ROW_CODE),
+                    fields: None,
+                };
+                checked.push((ImportRow::invalid(line, error), None));
+                continue;
+            }
+        };
+
+        let slug = input.slug.as_ref().map(|slug| slug.replace(' ', ""));
+        let conflict = match check_link(db, user_id, &input, LinkSource::Import).await {
+            Ok(()) if slug.as_ref().is_some_and(|slug| slugs.contains(slug)) => {
+                Some(LinkConflict::Slug)
+            }
+            Ok(()) if names.contains(&input.name) => Some(LinkConflict::Name),
+            Ok(()) => None,
+            Err(CreateLinkError::BadClientData(err)) => {
+                checked.push((ImportRow::invalid(line, err.into()), None));
+                continue;
+            }
+            Err(CreateLinkError::LinkExist(conflict)) => Some(conflict),
+            Err(_) => return Err(ApiError::DBInternalError),
+        };
+
+        if let Some(conflict) = conflict {
+            checked.push((ImportRow::invalid(line, conflict.into()), None));
+            continue;
+        }
+
+        names.insert(input.name.clone());
+        slugs.extend(slug);
+        let row = ImportRow {
+            line,
+            status: ImportRowStatus::Valid,
+            link: None,
+            error: None,
+        };
+        checked.push((row, Some(input)));
+    }
+
+    Ok(checked)
+}
+
+async fn commit_rows(
+    db: &DatabaseConnection,
+    settings: &LinkSettings,
+    secrets: &Secrets,
+    hashing: &PasswordHashingSettings,
+    user_id: Uuid,
+    rows: Vec<(ImportRow, Option<CreateLinkInput>)>,
+) -> Result<ImportLinksResponse, ApiError> {
+    let mut all_valid = rows
+        .iter()
+        .all(|(row, _)| row.status == ImportRowStatus::Valid);
+    if !all_valid {
+        let rows = rows.into_iter().map(|(row, _)| row).collect();
+        return Err(ApiError::InvalidRows(ImportLinksResponse::new(
+            ImportMode::Commit,
+            rows,
+        )));
+    }
+
+    let txn = db.begin().await.map_err(|_| ApiError::DBInternalError)?;
+    let mut results = Vec::with_capacity(rows.len());
+
+    for (mut row, input) in rows {
+        let Some(input) = input else {
+            results.push(row);
+            continue;
+        };
+
+        match create_link(
+            &txn,
+            settings,
+            secrets,
+            hashing,
+            user_id,
+            input,
+            LinkSource::Import,
+        )
+        .await
+        {
+            Ok(link) => {
+                row.status = ImportRowStatus::Created;
+                row.link = Some(link.into());
+            }
+            // Another link took the slug or name since the rows were checked
+            Err(CreateLinkError::LinkExist(conflict)) => {
+                all_valid = false;
+                row = ImportRow::invalid(row.line, conflict.into());
+            }
+            Err(CreateLinkError::BadClientData(err)) => {
+                all_valid = false;
+                row = ImportRow::invalid(row.line, err.into());
+            }
+            Err(_) => return Err(ApiError::DBInternalError),
+        }
+        results.push(row);
+    }
+
+    if !all_valid {
+        // Dropping the transaction rolls back the links created so far
+        drop(txn);
+        for row in results.iter_mut() {
+            if row.status == ImportRowStatus::Created {
+                row.status = ImportRowStatus::Valid;
+                row.link = None;
+            }
+        }
+
+        return Err(ApiError::InvalidRows(ImportLinksResponse::new(
+            ImportMode::Commit,
+            results,
+        )));
+    }
+
+    txn.commit().await.map_err(|_| ApiError::DBInternalError)?;
+
+    Ok(ImportLinksResponse::new(ImportMode::Commit, results))
+}
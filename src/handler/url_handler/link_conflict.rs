@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use sea_orm::{
+    prelude::Uuid, ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, QueryFilter,
+};
+
+use crate::{
+    entity::url,
+    handler::{
+        helpers::{ApiResponseData, ResponseError},
+        utils::unique_violation,
+    },
+};
+
+// Partial unique indexes guarding live (not soft deleted) links
+const SLUG_INDEX: &str = "idx-url-slug-live";
+const OWNER_NAME_INDEX: &str = "idx-url-owner-name-live";
+
+// Slugs are unique across every user, names only among the links of their owner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LinkConflict {
+    Slug,
+    Name,
+}
+
+impl LinkConflict {
+    pub(crate) fn from_db_error(err: &DbErr) -> Option<Self> {
+        match unique_violation(err)?.as_str() {
+            SLUG_INDEX => Some(Self::Slug),
+            OWNER_NAME_INDEX => Some(Self::Name),
+            _ => None,
+        }
+    }
+
+    // Look for a live link that already uses the slug or, for the same owner, the name
+    pub(crate) async fn find<C: ConnectionTrait>(
+        db: &C,
+        owner_id: Uuid,
+        name: Option<&str>,
+        slug: Option<&str>,
+        exclude_id: Option<Uuid>,
+    ) -> Result<Option<Self>, DbErr> {
+        if name.is_none() && slug.is_none() {
+            return Ok(None);
+        }
+
+        let mut taken = Condition::any();
+        if let Some(slug) = slug {
+            taken = taken.add(url::Column::Slug.eq(slug));
+        }
+        if let Some(name) = name {
+            taken = taken.add(
+                Condition::all()
+                    .add(url::Column::OwnerId.eq(owner_id))
+                    .add(url::Column::Name.eq(name)),
+            );
+        }
+        let mut conditions = Condition::all()
+            .add(url::Column::DeletedAt.is_null())
+            .add(taken);
+        if let Some(id) = exclude_id {
+            conditions = conditions.add(url::Column::Id.ne(id));
+        }
+
+        let links = url::Entity::find().filter(conditions).all(db).await?;
+
+        if links.iter().any(|link| Some(link.slug.as_str()) == slug) {
+            return Ok(Some(Self::Slug));
+        }
+        Ok(links.first().map(|_| Self::Name))
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            LinkConflict::Slug => "slug_taken",
+            LinkConflict::Name => "name_taken",
+        }
+    }
+
+    fn field(&self) -> &'static str {
+        match self {
+            LinkConflict::Slug => "slug",
+            LinkConflict::Name => "name",
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match self {
+            LinkConflict::Slug => "link with the slug provided already exists",
+            LinkConflict::Name => "you already have a link with the name provided",
+        }
+    }
+}
+
+impl From<LinkConflict> for ResponseError {
+    fn from(value: LinkConflict) -> Self {
+        Self {
+            code: Some(value.code()),
+            fields: Some(HashMap::from([(
+                value.field().to_owned(),
+                "already taken".to_owned(),
+            )])),
+        }
+    }
+}
+
+impl From<LinkConflict> for ApiResponseData<ResponseError> {
+    fn from(value: LinkConflict) -> Self {
+        ApiResponseData::error(
+            Some(ResponseError::from(value)),
+            value.message(),
+            StatusCode::BAD_REQUEST,
+        )
+    }
+}
@@ -0,0 +1,78 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::NaiveDateTime;
+use sea_orm::{
+    prelude::Uuid,
+    sea_query::{Expr, SimpleExpr},
+    Value,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::entity::url;
+
+use super::{LinkSort, SortOrder};
+
+// Position in a link list, only valid for the sort it was issued for. It isn't signed,
+// a forged cursor still only pages through the links of its bearer.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct LinkCursor {
+    pub sort: LinkSort,
+    pub order: SortOrder,
+    // Pages through the links before the position instead of the ones after it
+    pub backward: bool,
+    pub key: serde_json::Value,
+    pub id: Uuid,
+}
+
+impl LinkCursor {
+    pub(crate) fn new(link: &url::Model, sort: LinkSort, order: SortOrder, backward: bool) -> Self {
+        let key = match sort {
+            LinkSort::Name => json!(link.name),
+            LinkSort::CreatedAt => json!(link.created_at),
+            LinkSort::UpdatedAt => json!(link.updated_at.unwrap_or(link.created_at)),
+            LinkSort::ClickCount => json!(link.click_count),
+        };
+
+        Self {
+            sort,
+            order,
+            backward,
+            key,
+            id: link.id,
+        }
+    }
+
+    pub(crate) fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub(crate) fn decode(cursor: &str) -> Option<Self> {
+        let cursor = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+
+        serde_json::from_slice(&cursor).ok()
+    }
+
+    // Links past the position in the direction of the cursor, the id breaks ties like it
+    // does when sorting
+    pub(crate) fn condition(&self) -> Option<SimpleExpr> {
+        let key: Value = match self.sort {
+            LinkSort::Name => self.key.as_str()?.to_owned().into(),
+            LinkSort::CreatedAt | LinkSort::UpdatedAt => {
+                serde_json::from_value::<NaiveDateTime>(self.key.clone())
+                    .ok()?
+                    .into()
+            }
+            LinkSort::ClickCount => self.key.as_i64()?.into(),
+        };
+        let operator = match (self.order, self.backward) {
+            (SortOrder::Asc, false) | (SortOrder::Desc, true) => ">",
+            (SortOrder::Asc, true) | (SortOrder::Desc, false) => "<",
+        };
+        let condition = format!(
+            r#"({}, "url"."id") {operator} ($1, $2)"#,
+            self.sort.expression()
+        );
+
+        Some(Expr::cust_with_values(&condition, [key, self.id.into()]))
+    }
+}
@@ -0,0 +1,163 @@
+use std::io;
+
+use axum::http::{header, HeaderMap};
+use chrono::{DateTime, Utc};
+use sea_orm::prelude::Uuid;
+use serde::{Deserialize, Serialize};
+
+use crate::{dto::url::LinkState, entity::url};
+
+use super::CreateLinkInput;
+
+// Files links are imported from and exported to, json is one object per line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkFileFormat {
+    Csv,
+    Json,
+}
+
+impl LinkFileFormat {
+    pub(crate) fn from_content_type(headers: &HeaderMap) -> Option<Self> {
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())?;
+
+        match content_type.split(';').next()?.trim() {
+            "text/csv" => Some(Self::Csv),
+            "application/x-ndjson" | "application/jsonl" | "application/json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv",
+            Self::Json => "application/x-ndjson",
+        }
+    }
+
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "jsonl",
+        }
+    }
+
+    // Rows of the file along with the line they start at, stops reading after `limit` rows
+    pub(crate) fn parse_rows(
+        &self,
+        body: &str,
+        limit: usize,
+    ) -> Vec<(u64, Result<CreateLinkInput, String>)> {
+        // Spreadsheets like to start their exports with a byte order mark
+        let body = body.trim_start_matches('\u{feff}');
+
+        match self {
+            Self::Csv => {
+                let mut reader = csv::ReaderBuilder::new()
+                    .trim(csv::Trim::All)
+                    .flexible(true)
+                    .from_reader(body.as_bytes());
+                let headers = match reader.headers() {
+                    Ok(headers) => headers.clone(),
+                    Err(err) => return vec![(1, Err(err.to_string()))],
+                };
+
+                reader
+                    .records()
+                    .take(limit)
+                    .map(|record| match record {
+                        Ok(record) => {
+                            let line = record.position().map_or(0, |position| position.line());
+                            let row = record
+                                .deserialize(Some(&headers))
+                                .map_err(|err| err.to_string());
+
+                            (line, row)
+                        }
+                        Err(err) => {
+                            let line = err.position().map_or(0, |position| position.line());
+
+                            (line, Err(err.to_string()))
+                        }
+                    })
+                    .collect()
+            }
+            Self::Json => body
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .take(limit)
+                .map(|(index, line)| {
+                    let row = serde_json::from_str(line).map_err(|err| err.to_string());
+
+                    (index as u64 + 1, row)
+                })
+                .collect(),
+        }
+    }
+
+    // One row of the export, the csv header goes along with the first one
+    pub(crate) fn encode(&self, link: &ExportedLink, first: bool) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(first)
+                    .from_writer(Vec::new());
+                writer.serialize(link)?;
+
+                writer.into_inner().map_err(|err| err.into_error())
+            }
+            Self::Json => {
+                let mut row = serde_json::to_vec(link)?;
+                row.push(b'\n');
+
+                Ok(row)
+            }
+        }
+    }
+}
+
+// Columns accepted by the import come first, so an export can be imported back. Links that
+// have expired since come back expired.
+#[derive(Debug, Serialize)]
+pub(crate) struct ExportedLink {
+    pub name: String,
+    pub slug: String,
+    pub redirect_to: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub expired_redirect_to: Option<String>,
+    pub active_from: Option<DateTime<Utc>>,
+    pub max_clicks: Option<i32>,
+    // Space separated, so the csv column holds a single value
+    pub tags: String,
+    pub id: Uuid,
+    pub remaining_clicks: Option<i32>,
+    pub click_count: i64,
+    pub has_password: bool,
+    pub state: LinkState,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<url::Model> for ExportedLink {
+    fn from(v: url::Model) -> Self {
+        let now = Utc::now().naive_utc();
+        Self {
+            state: LinkState::of(&v, now),
+            has_password: v.password_hash.is_some(),
+            name: v.name,
+            slug: v.slug,
+            redirect_to: v.redirect_to,
+            expires_at: v.expires_at.map(|date| date.and_utc()),
+            expired_redirect_to: v.expired_redirect_to,
+            active_from: v.active_from.map(|date| date.and_utc()),
+            max_clicks: v.max_clicks,
+            tags: v.tags.join(" "),
+            id: v.id,
+            remaining_clicks: v.remaining_clicks,
+            click_count: v.click_count,
+            created_at: v.created_at.and_utc(),
+        }
+    }
+}
@@ -3,9 +3,29 @@ mod get_url_list_handler;
 mod update_url_handler;
 mod delete_url_handler;
 mod get_url_handler;
+mod get_url_qr_handler;
+mod get_url_stats_handler;
+mod get_url_trash_handler;
+mod restore_url_handler;
+mod import_url_handler;
+mod export_url_handler;
+mod batch_url_handler;
+mod link_conflict;
+mod link_cursor;
+mod link_file;
 
 pub use create_url_handler::*;
 pub use get_url_list_handler::*;
 pub use update_url_handler::*;
 pub use delete_url_handler::*;
 pub use get_url_handler::*;
+pub use get_url_qr_handler::*;
+pub use get_url_stats_handler::*;
+pub use get_url_trash_handler::*;
+pub use restore_url_handler::*;
+pub use import_url_handler::*;
+pub use export_url_handler::*;
+pub use batch_url_handler::*;
+pub(crate) use link_conflict::*;
+pub(crate) use link_cursor::*;
+pub use link_file::*;
@@ -0,0 +1,82 @@
+use axum::{extract::{Path, State}, http::StatusCode};
+use sea_orm::{prelude::Uuid, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter, Set};
+use serde::Serialize;
+
+use crate::{
+    dto::url::Url,
+    entity::url::{self, Entity as Link},
+    handler::{
+        helpers::{ApiResponse, ApiResponseData, ResponseError},
+        utils::UserId,
+    },
+};
+
+use super::LinkConflict;
+
+enum ApiError {
+    LinkNotFound,
+    ForbiddenRestore,
+    LinkExist(LinkConflict),
+    DBInternalError,
+}
+
+impl From<ApiError> for ApiResponseData<ResponseError> {
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::LinkNotFound => ApiResponseData::error(None, "link not found in trash", StatusCode::NOT_FOUND),
+            ApiError::ForbiddenRestore => ApiResponseData::status_code(StatusCode::FORBIDDEN),
+            ApiError::LinkExist(conflict) => conflict.into(),
+            ApiError::DBInternalError => ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreLinkResponse {
+    pub link: Url,
+}
+
+#[tracing::instrument]
+pub async fn restore_url_handler(
+    UserId(user_id): UserId,
+    Path(link_id): Path<Uuid>,
+    State(db): State<DatabaseConnection>,
+) -> ApiResponse<RestoreLinkResponse, ResponseError> {
+    let link = Link::find_by_id(link_id)
+        .filter(url::Column::DeletedAt.is_not_null())
+        .one(&db)
+        .await
+        .map_err(|_| ApiError::DBInternalError)?;
+
+    let link: url::Model = link.ok_or(ApiError::LinkNotFound)?;
+
+    if link.owner_id != user_id {
+        return Err(ApiError::ForbiddenRestore.into());
+    };
+
+    // The slug or name may have been reused while the link was in the trash
+    let conflict = LinkConflict::find(&db, user_id, Some(&link.name), Some(&link.slug), Some(link.id))
+        .await
+        .map_err(|_| ApiError::DBInternalError)?;
+
+    if let Some(conflict) = conflict {
+        return Err(ApiError::LinkExist(conflict).into());
+    }
+
+    let mut link_model = link.into_active_model();
+    link_model.deleted_at = Set(None);
+
+    let link = link_model
+        .update(&db)
+        .await
+        .map_err(|err| match LinkConflict::from_db_error(&err) {
+            Some(conflict) => ApiError::LinkExist(conflict),
+            None => ApiError::DBInternalError,
+        })?;
+
+    let data = RestoreLinkResponse {
+        link: link.into(),
+    };
+
+    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+}
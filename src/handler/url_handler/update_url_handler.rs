@@ -4,44 +4,79 @@ use axum::{
     http::StatusCode,
     Json,
 };
-use chrono::Utc;
-use sea_orm::{prelude::Uuid, ActiveModelTrait, DatabaseConnection, EntityTrait, Set, QueryFilter, ColumnTrait};
+use chrono::{DateTime, Utc};
+use sea_orm::{prelude::Uuid, sea_query::Expr, ActiveModelTrait, ConnectionTrait, DatabaseConnection, EntityTrait, Set, QueryFilter, ColumnTrait, TransactionTrait};
 use serde::{Deserialize, Serialize};
 use validator::{Validate, ValidationErrors};
 
 use crate::{
+    configuration::PasswordHashingSettings,
+    router::Secrets,
     dto::url::Url,
     handler::{
         helpers::{ApiResponse, ResponseError},
-        utils::UserId,
+        utils::{
+            deserialize_nullable, deserialize_tags, hash_password, validate_active_window,
+            validate_future_date, validate_slug, validate_tags, UserId,
+        },
     },
 };
 
+use super::LinkConflict;
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct UpdateLinkInput {
     #[validate(length(min = 4, max = 20))]
     pub name: Option<String>,
-    #[validate(length(min = 4, max = 20))]
+    #[validate(length(min = 4, max = 20), custom = "validate_slug")]
     pub slug: Option<String>,
     #[validate(url)]
     pub redirect_to: Option<String>,
+    // An explicit null removes the expiration
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    #[validate(custom = "validate_future_date")]
+    pub expires_at: Option<Option<DateTime<Utc>>>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    #[validate(url)]
+    pub expired_redirect_to: Option<Option<String>>,
+    // An explicit null makes the link live right away
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub active_from: Option<Option<DateTime<Utc>>>,
+    // An explicit null removes the password
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    #[validate(length(min = 4, max = 64))]
+    pub password: Option<Option<String>>,
+    // Visits already made count against the new limit, the recorded clicks when the link had
+    // none before. An explicit null removes it.
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    #[validate(range(min = 1))]
+    pub max_clicks: Option<Option<i32>>,
+    // Replaces every tag of the link, an empty list removes them
+    #[serde(default, deserialize_with = "deserialize_tags")]
+    #[validate(custom = "validate_tags")]
+    pub tags: Option<Vec<String>>,
 }
 
-pub enum ApiError {
+// Reasons a link can't be updated, shared with the batch handler
+pub(crate) enum UpdateLinkError {
     BadClientData(ValidationErrors),
     LinkNotFound,
     ForbiddenUpdate,
+    LinkExist(LinkConflict),
     DBInternalError,
+    HashingError,
 }
 
 
-impl From<ApiError> for ApiResponseData<ResponseError> {
-    fn from(value: ApiError) -> Self {
+impl From<UpdateLinkError> for ApiResponseData<ResponseError> {
+    fn from(value: UpdateLinkError) -> Self {
         match value {
-            ApiError::BadClientData(err) => ApiResponseData::error(Some(ResponseError::from(err)), "invalid data from client", StatusCode::BAD_REQUEST),
-            ApiError::LinkNotFound => ApiResponseData::error(None, "link not found", StatusCode::NOT_FOUND),
-            ApiError::ForbiddenUpdate => ApiResponseData::status_code(StatusCode::FORBIDDEN),
-            ApiError::DBInternalError => ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR),
+            UpdateLinkError::BadClientData(err) => ApiResponseData::error(Some(ResponseError::from(err)), "invalid data from client", StatusCode::BAD_REQUEST),
+            UpdateLinkError::LinkNotFound => ApiResponseData::error(None, "link not found", StatusCode::NOT_FOUND),
+            UpdateLinkError::ForbiddenUpdate => ApiResponseData::status_code(StatusCode::FORBIDDEN),
+            UpdateLinkError::LinkExist(conflict) => conflict.into(),
+            UpdateLinkError::DBInternalError => ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR),
+            UpdateLinkError::HashingError => ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR),
         }
     }
 }
@@ -51,29 +86,74 @@ pub struct UpdateLinkResponse {
     pub link: Url,
 }
 
-#[tracing::instrument]
+#[tracing::instrument(skip(secrets, update_link_input))]
 pub async fn update_url_handler(
     UserId(user_id): UserId,
     Path(link_id): Path<Uuid>,
     State(db): State<DatabaseConnection>,
-    Json(update_link): Json<UpdateLinkInput>,
+    State(secrets): State<Secrets>,
+    State(hashing): State<PasswordHashingSettings>,
+    Json(update_link_input): Json<UpdateLinkInput>,
 ) -> ApiResponse<UpdateLinkResponse, ResponseError> {
+    let link = update_link(&db, &secrets, &hashing, user_id, link_id, update_link_input).await?;
+
+    let data = UpdateLinkResponse {
+        link: link.into(),
+    };
+
+    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+}
+
+pub(crate) async fn update_link<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    secrets: &Secrets,
+    hashing: &PasswordHashingSettings,
+    user_id: Uuid,
+    link_id: Uuid,
+    update_link: UpdateLinkInput,
+) -> Result<url::Model, UpdateLinkError> {
     update_link
         .validate()
-        .map_err(ApiError::BadClientData)?;
+        .map_err(UpdateLinkError::BadClientData)?;
 
     let link = Link::find_by_id(link_id)
         .filter(url::Column::DeletedAt.is_null())
-        .one(&db)
+        .one(db)
         .await
-        .map_err(|_| ApiError::DBInternalError)?;
+        .map_err(|_| UpdateLinkError::DBInternalError)?;
 
-    let link: url::Model = link.ok_or(ApiError::LinkNotFound)?;
+    let link: url::Model = link.ok_or(UpdateLinkError::LinkNotFound)?;
 
     if link.owner_id != user_id {
-        return Err(ApiError::ForbiddenUpdate.into());
+        return Err(UpdateLinkError::ForbiddenUpdate);
     };
 
+    // The window is checked against the values the link will end up with
+    let active_from = match update_link.active_from {
+        Some(active_from) => active_from.map(|date| date.naive_utc()),
+        None => link.active_from,
+    };
+    let expires_at = match update_link.expires_at {
+        Some(expires_at) => expires_at.map(|date| date.naive_utc()),
+        None => link.expires_at,
+    };
+    validate_active_window(active_from, expires_at).map_err(UpdateLinkError::BadClientData)?;
+
+    // Check if the new slug or name is already used by another link
+    let conflict = LinkConflict::find(
+        db,
+        user_id,
+        update_link.name.as_deref(),
+        update_link.slug.as_deref(),
+        Some(link.id),
+    )
+    .await
+    .map_err(|_| UpdateLinkError::DBInternalError)?;
+
+    if let Some(conflict) = conflict {
+        return Err(UpdateLinkError::LinkExist(conflict));
+    }
+
     let mut link: url::ActiveModel = link.into();
 
     if let Some(name) = update_link.name {
@@ -88,16 +168,67 @@ pub async fn update_url_handler(
         link.redirect_to = Set(redirect_to);
     }
 
+    if update_link.expires_at.is_some() {
+        link.expires_at = Set(expires_at);
+    }
+
+    if update_link.active_from.is_some() {
+        link.active_from = Set(active_from);
+    }
+
+    if let Some(expired_redirect_to) = update_link.expired_redirect_to {
+        link.expired_redirect_to = Set(expired_redirect_to);
+    }
+
+    if let Some(password) = update_link.password {
+        let password_hash = match password {
+            Some(password) => Some(
+                hash_password(secrets.hash_secret.as_bytes(), hashing, password.as_bytes())
+                    .await
+                    .map_err(|_| UpdateLinkError::HashingError)?,
+            ),
+            None => None,
+        };
+        link.password_hash = Set(password_hash);
+    }
+
+    if let Some(tags) = update_link.tags {
+        link.tags = Set(tags);
+    }
+
     link.updated_at = Set(Some(Utc::now().naive_utc()));
 
+    // Update in its own (nested) transaction, so a conflict doesn't abort an enclosing one
+    let txn = db.begin().await.map_err(|_| UpdateLinkError::DBInternalError)?;
+
+    // The clicks used so far are carried over in sql, so visits taken meanwhile aren't lost
+    if let Some(max_clicks) = update_link.max_clicks {
+        let remaining_clicks = match max_clicks {
+            Some(max_clicks) => Expr::cust_with_values(
+                r#"GREATEST($1 - COALESCE("max_clicks" - "remaining_clicks", "click_count"), 0)"#,
+                [max_clicks],
+            ),
+            None => Expr::val(Option::<i32>::None).into(),
+        };
+
+        Link::update_many()
+            .col_expr(url::Column::MaxClicks, Expr::val(max_clicks).into())
+            .col_expr(url::Column::RemainingClicks, remaining_clicks)
+            .filter(url::Column::Id.eq(link_id))
+            .exec(&txn)
+            .await
+            .map_err(|_| UpdateLinkError::DBInternalError)?;
+    }
+
     let updated_link = link
-        .update(&db)
+        .update(&txn)
         .await
-        .map_err(|_| ApiError::DBInternalError)?;
+        .map_err(|err| match LinkConflict::from_db_error(&err) {
+            Some(conflict) => UpdateLinkError::LinkExist(conflict),
+            None => UpdateLinkError::DBInternalError,
+        })?;
 
-    let data = UpdateLinkResponse {
-        link: updated_link.into(),
-    };
+    txn.commit().await.map_err(|_| UpdateLinkError::DBInternalError)?;
 
-    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+    Ok(updated_link)
 }
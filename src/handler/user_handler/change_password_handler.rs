@@ -0,0 +1,105 @@
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait};
+use serde::Deserialize;
+use validator::{Validate, ValidationErrors};
+
+use crate::configuration::PasswordHashingSettings;
+use crate::entity::{sea_orm_active_enums::Provider, user};
+use crate::handler::helpers::{ApiResponse, ApiResponseData, ResponseError};
+use crate::handler::utils::{hash_password, verify_password, AccessToken};
+use crate::router::Secrets;
+
+use super::tokens::revoke_other_sessions;
+
+#[derive(Validate, Deserialize)]
+pub struct ChangePasswordInput {
+    pub current_password: String,
+    #[validate(length(min = 5, max = 25))]
+    pub new_password: String,
+}
+
+enum ApiError {
+    BadClientData(ValidationErrors),
+    UserProviderNotValid,
+    BadCredentials,
+    UserNotFound,
+    DbInternalError,
+    HashingError,
+}
+
+impl From<ApiError> for ApiResponseData<ResponseError> {
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::BadClientData(err) => ApiResponseData::error(
+                Some(ResponseError::from(err)),
+                "invalid data from client",
+                StatusCode::BAD_REQUEST,
+            ),
+            ApiError::UserProviderNotValid => {
+                ApiResponseData::error(None, "bad provider", StatusCode::BAD_REQUEST)
+            }
+            ApiError::BadCredentials => ApiResponseData::status_code(StatusCode::FORBIDDEN),
+            ApiError::UserNotFound => ApiResponseData::status_code(StatusCode::BAD_REQUEST),
+            ApiError::DbInternalError | ApiError::HashingError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// The session changing the password stays signed in, the other ones are signed out
+#[tracing::instrument(skip(token, secrets, input))]
+pub async fn change_password_handler(
+    token: AccessToken,
+    State(db_connection): State<DatabaseConnection>,
+    State(secrets): State<Secrets>,
+    State(hashing): State<PasswordHashingSettings>,
+    Json(input): Json<ChangePasswordInput>,
+) -> ApiResponse<(), ResponseError> {
+    input.validate().map_err(ApiError::BadClientData)?;
+
+    let user = user::Entity::find_by_id(token.user_id)
+        .one(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?
+        .ok_or(ApiError::UserNotFound)?;
+
+    let password_hash = match (&user.provider, &user.password_hash) {
+        (Provider::Local, Some(password_hash)) => password_hash,
+        _ => return Err(ApiError::UserProviderNotValid.into()),
+    };
+
+    let is_match = verify_password(
+        secrets.hash_secret.as_bytes(),
+        input.current_password.as_bytes(),
+        password_hash,
+    )
+    .await
+    .map_err(|_| ApiError::HashingError)?;
+    if !is_match {
+        return Err(ApiError::BadCredentials.into());
+    }
+
+    let new_password_hash = hash_password(
+        secrets.hash_secret.as_bytes(),
+        &hashing,
+        input.new_password.as_bytes(),
+    )
+    .await
+    .map_err(|_| ApiError::HashingError)?;
+
+    let mut user: user::ActiveModel = user.into();
+    user.password_hash = Set(Some(new_password_hash));
+    user.updated_at = Set(Some(chrono::Utc::now().naive_utc()));
+    user.update(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    revoke_other_sessions(&db_connection, token.user_id, token.session_id)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    Ok(ApiResponseData::status_code(StatusCode::OK))
+}
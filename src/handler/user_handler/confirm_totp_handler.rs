@@ -0,0 +1,115 @@
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, TransactionTrait};
+use serde::{Deserialize, Serialize};
+
+use crate::configuration::AuthSettings;
+use crate::entity::user;
+use crate::handler::helpers::{ApiResponse, ApiResponseData};
+use crate::handler::utils::{AccessToken, ClientIp};
+
+use super::security_events::{record_security_event, TOTP_ENABLED_EVENT};
+use super::two_factor::{check_second_factor, replace_recovery_codes};
+
+#[derive(Deserialize, Debug)]
+pub struct ConfirmTotpInput {
+    pub code: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ConfirmTotpResponseObject {
+    // Only shown this once, each of them can replace a code a single time
+    recovery_codes: Vec<String>,
+}
+
+enum ApiError {
+    NotEnrolled,
+    AlreadyEnabled,
+    InvalidCode,
+    UserNotFound,
+    DbInternalError,
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::NotEnrolled => ApiResponseData::error(
+                None,
+                "two-factor enrollment not started",
+                StatusCode::BAD_REQUEST,
+            ),
+            ApiError::AlreadyEnabled => ApiResponseData::error(
+                None,
+                "two-factor login already enabled",
+                StatusCode::CONFLICT,
+            ),
+            ApiError::InvalidCode => {
+                ApiResponseData::error(None, "invalid code", StatusCode::BAD_REQUEST)
+            }
+            ApiError::UserNotFound => ApiResponseData::status_code(StatusCode::BAD_REQUEST),
+            ApiError::DbInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// A first code from the authenticator app proves it was set up, two-factor login is
+// enabled from then on
+#[tracing::instrument(skip(token, input))]
+pub async fn confirm_totp_handler(
+    token: AccessToken,
+    ClientIp(ip): ClientIp,
+    State(db_connection): State<DatabaseConnection>,
+    State(settings): State<AuthSettings>,
+    Json(input): Json<ConfirmTotpInput>,
+) -> ApiResponse<ConfirmTotpResponseObject, ()> {
+    let user = user::Entity::find_by_id(token.user_id)
+        .one(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?
+        .ok_or(ApiError::UserNotFound)?;
+
+    if user.totp_enabled_at.is_some() {
+        return Err(ApiError::AlreadyEnabled.into());
+    }
+    if user.totp_secret.is_none() {
+        return Err(ApiError::NotEnrolled.into());
+    }
+
+    let txn = db_connection
+        .begin()
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    check_second_factor(&txn, &user, Some(&input.code), None)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?
+        .ok_or(ApiError::InvalidCode)?;
+
+    user::Entity::update_many()
+        .col_expr(
+            user::Column::TotpEnabledAt,
+            Expr::value(chrono::Utc::now().naive_utc()),
+        )
+        .filter(user::Column::Id.eq(user.id))
+        .exec(&txn)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+    let recovery_codes = replace_recovery_codes(&txn, user.id, settings.totp.recovery_codes)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+    record_security_event(&txn, user.id, TOTP_ENABLED_EVENT, ip)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    txn.commit().await.map_err(|_| ApiError::DbInternalError)?;
+
+    let data = ConfirmTotpResponseObject { recovery_codes };
+
+    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+}
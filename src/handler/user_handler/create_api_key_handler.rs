@@ -0,0 +1,83 @@
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use sea_orm::prelude::Uuid;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, DatabaseConnection};
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationErrors};
+
+use crate::dto::user::ApiKey;
+use crate::entity::api_key;
+use crate::handler::helpers::{ApiResponse, ApiResponseData, ResponseError};
+use crate::handler::utils::{generate_api_key, sha256_hex, validate_scopes, AccessToken};
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct CreateApiKeyInput {
+    #[validate(length(min = 1, max = 50))]
+    pub name: String,
+    #[validate(custom = "validate_scopes")]
+    pub scopes: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CreateApiKeyResponse {
+    pub api_key: ApiKey,
+    // Only returned once, the server keeps a hash of it
+    pub key: String,
+}
+
+enum ApiError {
+    BadClientData(ValidationErrors),
+    DbInternalError,
+}
+
+impl From<ApiError> for ApiResponseData<ResponseError> {
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::BadClientData(err) => ApiResponseData::error(
+                Some(ResponseError::from(err)),
+                "invalid data from client",
+                StatusCode::BAD_REQUEST,
+            ),
+            ApiError::DbInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// Keys are managed with a user session, an api key can't create other keys
+#[tracing::instrument(skip(token))]
+pub async fn create_api_key_handler(
+    token: AccessToken,
+    State(db_connection): State<DatabaseConnection>,
+    Json(input): Json<CreateApiKeyInput>,
+) -> ApiResponse<CreateApiKeyResponse, ResponseError> {
+    input.validate().map_err(ApiError::BadClientData)?;
+
+    let mut scopes = input.scopes;
+    scopes.sort();
+    scopes.dedup();
+
+    let generated = generate_api_key();
+    let api_key = api_key::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(token.user_id),
+        name: Set(input.name),
+        prefix: Set(generated.prefix),
+        key_hash: Set(sha256_hex(&generated.key)),
+        scopes: Set(scopes.join(" ")),
+        created_at: Set(chrono::Utc::now().naive_utc()),
+        last_used_at: Set(None),
+    }
+    .insert(&db_connection)
+    .await
+    .map_err(|_| ApiError::DbInternalError)?;
+
+    let data = CreateApiKeyResponse {
+        api_key: api_key.into(),
+        key: generated.key,
+    };
+
+    Ok(ApiResponseData::success_with_data(data, StatusCode::CREATED))
+}
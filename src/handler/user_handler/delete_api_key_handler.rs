@@ -0,0 +1,50 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use sea_orm::{prelude::Uuid, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Serialize;
+
+use crate::entity::api_key;
+use crate::handler::helpers::{ApiResponse, ApiResponseData};
+use crate::handler::utils::AccessToken;
+
+enum ApiError {
+    ApiKeyNotFound,
+    DbInternalError,
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::ApiKeyNotFound => {
+                ApiResponseData::error(None, "api key not found", StatusCode::NOT_FOUND)
+            }
+            ApiError::DbInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// Revoking a key takes effect on the next request made with it
+#[tracing::instrument(skip(token))]
+pub async fn delete_api_key_handler(
+    token: AccessToken,
+    Path(key_id): Path<Uuid>,
+    State(db_connection): State<DatabaseConnection>,
+) -> ApiResponse<(), ()> {
+    let result = api_key::Entity::delete_many()
+        .filter(api_key::Column::Id.eq(key_id))
+        .filter(api_key::Column::UserId.eq(token.user_id))
+        .exec(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    if result.rows_affected == 0 {
+        return Err(ApiError::ApiKeyNotFound.into());
+    }
+
+    Ok(ApiResponseData::status_code(StatusCode::OK))
+}
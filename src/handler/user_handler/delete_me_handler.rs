@@ -0,0 +1,66 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, TransactionTrait};
+use serde::Serialize;
+
+use crate::entity::{api_key, url, user};
+use crate::handler::helpers::{ApiResponse, ApiResponseData};
+use crate::handler::utils::UserId;
+
+use super::tokens::revoke_sessions;
+
+enum ApiError {
+    DbInternalError,
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::DbInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// Soft deletes the account. Its links go to the trash, where the purge job removes them
+// after the retention period, and every session and api key stops working.
+#[tracing::instrument]
+pub async fn delete_me_handler(
+    UserId(user_id): UserId,
+    State(db_connection): State<DatabaseConnection>,
+) -> ApiResponse<(), ()> {
+    db_connection
+        .transaction::<_, (), DbErr>(|txn| {
+            Box::pin(async move {
+                let now = chrono::Utc::now().naive_utc();
+
+                user::Entity::update_many()
+                    .col_expr(user::Column::DeletedAt, Expr::value(now))
+                    .filter(user::Column::Id.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                url::Entity::update_many()
+                    .col_expr(url::Column::DeletedAt, Expr::value(now))
+                    .filter(url::Column::OwnerId.eq(user_id))
+                    .filter(url::Column::DeletedAt.is_null())
+                    .exec(txn)
+                    .await?;
+                api_key::Entity::delete_many()
+                    .filter(api_key::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                revoke_sessions(txn, user_id, None).await?;
+
+                Ok(())
+            })
+        })
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    Ok(ApiResponseData::status_code(StatusCode::OK))
+}
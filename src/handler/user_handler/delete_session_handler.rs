@@ -0,0 +1,49 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use sea_orm::{prelude::Uuid, DatabaseConnection};
+use serde::Serialize;
+
+use crate::handler::helpers::{ApiResponse, ApiResponseData};
+use crate::handler::utils::UserId;
+
+use super::tokens::revoke_sessions;
+
+enum ApiError {
+    SessionNotFound,
+    DbInternalError,
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::SessionNotFound => {
+                ApiResponseData::error(None, "session not found", StatusCode::NOT_FOUND)
+            }
+            ApiError::DbInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// Signs out a single session, which may be the current one
+#[tracing::instrument]
+pub async fn delete_session_handler(
+    UserId(user_id): UserId,
+    Path(session_id): Path<Uuid>,
+    State(db_connection): State<DatabaseConnection>,
+) -> ApiResponse<(), ()> {
+    let revoked = revoke_sessions(&db_connection, user_id, Some(session_id))
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    // Sessions of other users look the same as unknown ones
+    if revoked == 0 {
+        return Err(ApiError::SessionNotFound.into());
+    }
+
+    Ok(ApiResponseData::status_code(StatusCode::OK))
+}
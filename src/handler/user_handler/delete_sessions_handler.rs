@@ -0,0 +1,39 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+
+use crate::handler::helpers::{ApiResponse, ApiResponseData};
+use crate::handler::utils::UserId;
+
+use super::tokens::revoke_sessions;
+
+enum ApiError {
+    DbInternalError,
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::DbInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// Signs out everywhere, the current session included
+#[tracing::instrument]
+pub async fn delete_sessions_handler(
+    UserId(user_id): UserId,
+    State(db_connection): State<DatabaseConnection>,
+) -> ApiResponse<(), ()> {
+    revoke_sessions(&db_connection, user_id, None)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    Ok(ApiResponseData::status_code(StatusCode::OK))
+}
@@ -0,0 +1,118 @@
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, TransactionTrait};
+use serde::{Deserialize, Serialize};
+
+use crate::entity::user;
+use crate::handler::helpers::{ApiResponse, ApiResponseData};
+use crate::handler::utils::{verify_password, AccessToken, ClientIp};
+use crate::router::Secrets;
+
+use super::security_events::{record_security_event, TOTP_DISABLED_EVENT};
+use super::two_factor::{check_second_factor, delete_recovery_codes};
+
+// Either a code of the authenticator app or a recovery code
+#[derive(Deserialize, Debug)]
+pub struct DisableTotpInput {
+    pub password: String,
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+enum ApiError {
+    NotEnabled,
+    BadCredentials,
+    UserNotFound,
+    DbInternalError,
+    HashingError,
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::NotEnabled => ApiResponseData::error(
+                None,
+                "two-factor login not enabled",
+                StatusCode::BAD_REQUEST,
+            ),
+            ApiError::BadCredentials => ApiResponseData::status_code(StatusCode::FORBIDDEN),
+            ApiError::UserNotFound => ApiResponseData::status_code(StatusCode::BAD_REQUEST),
+            ApiError::DbInternalError | ApiError::HashingError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// Asks for both factors, a stolen access token alone can't turn two-factor login off
+#[tracing::instrument(skip(token, secrets, input))]
+pub async fn disable_totp_handler(
+    token: AccessToken,
+    ClientIp(ip): ClientIp,
+    State(db_connection): State<DatabaseConnection>,
+    State(secrets): State<Secrets>,
+    Json(input): Json<DisableTotpInput>,
+) -> ApiResponse<(), ()> {
+    let user = user::Entity::find_by_id(token.user_id)
+        .one(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?
+        .ok_or(ApiError::UserNotFound)?;
+
+    if user.totp_enabled_at.is_none() {
+        return Err(ApiError::NotEnabled.into());
+    }
+
+    let password_hash = user
+        .password_hash
+        .as_deref()
+        .ok_or(ApiError::BadCredentials)?;
+    let is_match = verify_password(
+        secrets.hash_secret.as_bytes(),
+        input.password.as_bytes(),
+        password_hash,
+    )
+    .await
+    .map_err(|_| ApiError::HashingError)?;
+    if !is_match {
+        return Err(ApiError::BadCredentials.into());
+    }
+
+    let txn = db_connection
+        .begin()
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    check_second_factor(
+        &txn,
+        &user,
+        input.code.as_deref(),
+        input.recovery_code.as_deref(),
+    )
+    .await
+    .map_err(|_| ApiError::DbInternalError)?
+    .ok_or(ApiError::BadCredentials)?;
+
+    let user_id = user.id;
+    let mut user: user::ActiveModel = user.into();
+    user.totp_secret = Set(None);
+    user.totp_enabled_at = Set(None);
+    user.totp_last_step = Set(None);
+    user.update(&txn)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+    delete_recovery_codes(&txn, user_id)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+    record_security_event(&txn, user_id, TOTP_DISABLED_EVENT, ip)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    txn.commit().await.map_err(|_| ApiError::DbInternalError)?;
+
+    Ok(ApiResponseData::status_code(StatusCode::OK))
+}
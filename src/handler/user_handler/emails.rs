@@ -0,0 +1,81 @@
+use chrono::Duration;
+
+use crate::{
+    configuration::EmailSettings,
+    entity::user,
+    handler::utils::{encode_email_token, EmailTokenPurpose},
+    mailer::{Email, Mailer},
+    router::Secrets,
+};
+
+// Sending is best effort, a failure is logged and doesn't fail the request that triggered it
+pub(super) async fn send_email_token(
+    mailer: &dyn Mailer,
+    settings: &EmailSettings,
+    secrets: &Secrets,
+    purpose: EmailTokenPurpose,
+    user: &user::Model,
+) {
+    let (ttl, page_url, subject, intro) = match purpose {
+        EmailTokenPurpose::VerifyEmail => (
+            settings.verification_ttl(),
+            &settings.verify_email_url,
+            "Verify your email",
+            "Confirm this email address belongs to you by opening the link below.",
+        ),
+        EmailTokenPurpose::ResetPassword => (
+            settings.reset_ttl(),
+            &settings.reset_password_url,
+            "Reset your password",
+            "Someone asked to reset your password, open the link below to choose a new one. \
+             You can ignore this email if it wasn't you.",
+        ),
+    };
+
+    let token = match encode_email_token(secrets.jwt_secret.as_bytes(), purpose, user, ttl) {
+        Ok(token) => token,
+        Err(err) => {
+            tracing::error!("couldn't create email token: {err}");
+            return;
+        }
+    };
+
+    let email = Email {
+        from: settings.from.clone(),
+        to: user.email.clone(),
+        subject: subject.into(),
+        body: format!(
+            "Hi {},\n\n{intro}\n\n{page_url}?token={token}\n",
+            user.username
+        ),
+    };
+
+    if let Err(err) = mailer.send(email).await {
+        tracing::error!("couldn't send email to user {}: {err}", user.id);
+    }
+}
+
+// Tells the user someone kept failing to sign in as them, also best effort
+pub(super) async fn send_lockout_notice(
+    mailer: &dyn Mailer,
+    settings: &EmailSettings,
+    user: &user::Model,
+    lockout: Duration,
+) {
+    let email = Email {
+        from: settings.from.clone(),
+        to: user.email.clone(),
+        subject: "Sign-in temporarily locked".into(),
+        body: format!(
+            "Hi {},\n\nThere were too many failed attempts to sign in to your account, \
+             signing in is locked for the next {} minutes.\n\n\
+             If it wasn't you, consider changing your password once the lock is lifted.\n",
+            user.username,
+            lockout.num_minutes()
+        ),
+    };
+
+    if let Err(err) = mailer.send(email).await {
+        tracing::error!("couldn't send email to user {}: {err}", user.id);
+    }
+}
@@ -0,0 +1,85 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait};
+use serde::Serialize;
+
+use crate::configuration::AuthSettings;
+use crate::entity::{sea_orm_active_enums::Provider, user};
+use crate::handler::helpers::{ApiResponse, ApiResponseData};
+use crate::handler::utils::{generate_totp_secret, totp_uri, AccessToken};
+
+#[derive(Serialize, Debug)]
+pub struct EnrollTotpResponseObject {
+    // For apps the uri can't be scanned into
+    secret: String,
+    otpauth_uri: String,
+}
+
+enum ApiError {
+    UserProviderNotValid,
+    AlreadyEnabled,
+    UserNotFound,
+    DbInternalError,
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::UserProviderNotValid => {
+                ApiResponseData::error(None, "bad provider", StatusCode::BAD_REQUEST)
+            }
+            ApiError::AlreadyEnabled => ApiResponseData::error(
+                None,
+                "two-factor login already enabled",
+                StatusCode::CONFLICT,
+            ),
+            ApiError::UserNotFound => ApiResponseData::status_code(StatusCode::BAD_REQUEST),
+            ApiError::DbInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// Starts over with a new secret each time, two-factor login is only enabled once a first
+// code is confirmed. Users signing in through a provider rely on its own second factor.
+#[tracing::instrument(skip(token))]
+pub async fn enroll_totp_handler(
+    token: AccessToken,
+    State(db_connection): State<DatabaseConnection>,
+    State(settings): State<AuthSettings>,
+) -> ApiResponse<EnrollTotpResponseObject, ()> {
+    let user = user::Entity::find_by_id(token.user_id)
+        .one(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?
+        .ok_or(ApiError::UserNotFound)?;
+
+    if user.provider != Provider::Local {
+        return Err(ApiError::UserProviderNotValid.into());
+    }
+    if user.totp_enabled_at.is_some() {
+        return Err(ApiError::AlreadyEnabled.into());
+    }
+
+    let secret = generate_totp_secret();
+    let otpauth_uri = totp_uri(&settings.totp.issuer, &user.username, &secret);
+
+    let mut user: user::ActiveModel = user.into();
+    user.totp_secret = Set(Some(secret.clone()));
+    user.totp_last_step = Set(None);
+    user.update(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    let data = EnrollTotpResponseObject {
+        secret,
+        otpauth_uri,
+    };
+
+    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+}
@@ -0,0 +1,75 @@
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Deserialize;
+use validator::{Validate, ValidationErrors};
+
+use crate::configuration::EmailSettings;
+use crate::entity::{sea_orm_active_enums::Provider, user};
+use crate::handler::helpers::{ApiResponse, ApiResponseData, ResponseError};
+use crate::handler::utils::EmailTokenPurpose;
+use crate::mailer::SharedMailer;
+use crate::router::Secrets;
+
+use super::emails::send_email_token;
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct ForgotPasswordInput {
+    #[validate(email)]
+    pub email: String,
+}
+
+enum ApiError {
+    BadClientData(ValidationErrors),
+    DbInternalError,
+}
+
+impl From<ApiError> for ApiResponseData<ResponseError> {
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::BadClientData(err) => ApiResponseData::error(
+                Some(ResponseError::from(err)),
+                "invalid data from client",
+                StatusCode::BAD_REQUEST,
+            ),
+            ApiError::DbInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// Answers the same whether the email belongs to a user or not, so it can't be used to
+// find out who has an account
+#[tracing::instrument(skip(secrets, mailer))]
+pub async fn forgot_password_handler(
+    State(db_connection): State<DatabaseConnection>,
+    State(secrets): State<Secrets>,
+    State(mailer): State<SharedMailer>,
+    State(settings): State<EmailSettings>,
+    Json(input): Json<ForgotPasswordInput>,
+) -> ApiResponse<(), ResponseError> {
+    input.validate().map_err(ApiError::BadClientData)?;
+
+    // Users signing in through a provider have no password to reset
+    let user = user::Entity::find()
+        .filter(user::Column::Email.eq(input.email))
+        .filter(user::Column::Provider.eq(Provider::Local))
+        .filter(user::Column::DeletedAt.is_null())
+        .one(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    if let Some(user) = user {
+        send_email_token(
+            mailer.as_ref(),
+            &settings,
+            &secrets,
+            EmailTokenPurpose::ResetPassword,
+            &user,
+        )
+        .await;
+    }
+
+    Ok(ApiResponseData::status_code(StatusCode::ACCEPTED))
+}
@@ -0,0 +1,50 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+
+use crate::dto::user::ApiKey;
+use crate::entity::api_key;
+use crate::handler::helpers::{ApiResponse, ApiResponseData};
+use crate::handler::utils::AccessToken;
+
+#[derive(Serialize, Debug)]
+pub struct GetApiKeysResponse {
+    pub api_keys: Vec<ApiKey>,
+}
+
+enum ApiError {
+    DbInternalError,
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::DbInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+#[tracing::instrument(skip(token))]
+pub async fn get_api_keys_handler(
+    token: AccessToken,
+    State(db_connection): State<DatabaseConnection>,
+) -> ApiResponse<GetApiKeysResponse, ()> {
+    let api_keys = api_key::Entity::find()
+        .filter(api_key::Column::UserId.eq(token.user_id))
+        .order_by_desc(api_key::Column::CreatedAt)
+        .all(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    let data = GetApiKeysResponse {
+        api_keys: api_keys.into_iter().map(ApiKey::from).collect(),
+    };
+
+    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+}
@@ -0,0 +1,58 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+
+use crate::dto::user::Session;
+use crate::entity::session;
+use crate::handler::helpers::{ApiResponse, ApiResponseData};
+use crate::handler::utils::AccessToken;
+
+#[derive(Serialize, Debug)]
+pub struct GetSessionsResponse {
+    pub sessions: Vec<Session>,
+}
+
+enum ApiError {
+    DbInternalError,
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::DbInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+#[tracing::instrument(skip(token))]
+pub async fn get_sessions_handler(
+    token: AccessToken,
+    State(db_connection): State<DatabaseConnection>,
+) -> ApiResponse<GetSessionsResponse, ()> {
+    let conditions = Condition::all()
+        .add(session::Column::UserId.eq(token.user_id))
+        .add(session::Column::RevokedAt.is_null())
+        .add(session::Column::ExpiresAt.gt(chrono::Utc::now().naive_utc()));
+
+    let sessions = session::Entity::find()
+        .filter(conditions)
+        .order_by_desc(session::Column::LastUsedAt)
+        .all(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    let data = GetSessionsResponse {
+        sessions: sessions
+            .into_iter()
+            .map(|session| Session::new(session, token.session_id))
+            .collect(),
+    };
+
+    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+}
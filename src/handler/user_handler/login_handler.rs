@@ -1,14 +1,29 @@
+use std::net::IpAddr;
+
 use axum::extract::State;
-use axum::{http::StatusCode, Json};
-use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::sea_query::Expr;
+use sea_orm::{prelude::Uuid, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
 use serde::{Deserialize, Serialize};
 
+use crate::configuration::{AuthSettings, EmailSettings, PasswordHashingSettings};
+use crate::dto::user::TokenPair;
 use crate::entity::sea_orm_active_enums::Provider;
 use crate::entity::user;
-use crate::handler::helpers::{ApiResponse, ApiResponseData};
-use crate::handler::utils::{encode_jwt, verify_password};
+use crate::handler::helpers::ApiResponseData;
+use crate::handler::utils::{
+    encode_login_challenge, hash_password, needs_rehash, verify_password, ClientIp,
+};
+use crate::mailer::SharedMailer;
 use crate::router::Secrets;
 
+use super::throttle::{clear_failures, record_login_failure, retry_after, ThrottleKeys};
+use super::tokens::{issue_tokens, start_session, TokenError};
+
 // Client input
 #[derive(Deserialize, Debug)]
 pub struct LoginUserInput {
@@ -18,30 +33,65 @@ pub struct LoginUserInput {
 
 // Response Object
 #[derive(Serialize, Debug)]
-pub struct LoginResponseObject {
-    token: String,
+#[serde(untagged)]
+pub enum LoginResponseObject {
+    Tokens(TokenPair),
+    // Users with two-factor login enabled get a challenge to complete on /login/2fa instead
+    TwoFactorChallenge {
+        two_factor_required: bool,
+        challenge_token: String,
+        // Lifetime of the challenge, in seconds
+        expires_in: i64,
+    },
 }
 
-pub enum ApiError {
-    UserNotFound,
-    BadCredentials,
-    UserProviderNotValid,
+impl LoginResponseObject {
+    pub(super) fn two_factor_challenge(
+        secrets: &Secrets,
+        settings: &AuthSettings,
+        user_id: Uuid,
+    ) -> jsonwebtoken::errors::Result<Self> {
+        let ttl = settings.totp.challenge_ttl();
+        let challenge_token = encode_login_challenge(secrets.jwt_secret.as_bytes(), user_id, ttl)?;
+
+        Ok(LoginResponseObject::TwoFactorChallenge {
+            two_factor_required: true,
+            challenge_token,
+            expires_in: ttl.num_seconds(),
+        })
+    }
+}
+
+enum ApiError {
+    // Seconds before the next attempt is allowed
+    TooManyAttempts(i64),
+    InvalidCredentials,
     InternalError,
     JWTEncodingError,
 }
 
+impl From<TokenError> for ApiError {
+    fn from(value: TokenError) -> Self {
+        match value {
+            TokenError::DbInternalError => ApiError::InternalError,
+            TokenError::JWTEncodingError => ApiError::JWTEncodingError,
+        }
+    }
+}
+
 impl<E> From<ApiError> for ApiResponseData<E>
 where
     E: Serialize + 'static,
 {
     fn from(value: ApiError) -> Self {
         match value {
-            ApiError::UserNotFound => {
-                ApiResponseData::error(None, "user not found", StatusCode::NOT_ACCEPTABLE)
-            }
-            ApiError::BadCredentials => ApiResponseData::status_code(StatusCode::FORBIDDEN),
-            ApiError::UserProviderNotValid => {
-                ApiResponseData::error(None, "bad provider", StatusCode::BAD_REQUEST)
+            ApiError::TooManyAttempts(_) => ApiResponseData::error(
+                None,
+                "too many login attempts",
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            ApiError::InvalidCredentials => {
+                ApiResponseData::error(None, "invalid credentials", StatusCode::UNAUTHORIZED)
             }
             ApiError::InternalError | ApiError::JWTEncodingError => {
                 ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
@@ -50,43 +100,217 @@ where
     }
 }
 
-#[tracing::instrument(skip(secrets))]
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let retry_after = match self {
+            ApiError::TooManyAttempts(secs) => Some(secs),
+            _ => None,
+        };
+        let response = ApiResponseData::<()>::from(self).into_response();
+
+        match retry_after {
+            Some(secs) => ([(header::RETRY_AFTER, secs.to_string())], response).into_response(),
+            None => response,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(secrets, mailer, headers))]
 pub async fn login_handler(
     State(secrets): State<Secrets>,
+    State(hashing): State<PasswordHashingSettings>,
+    State(settings): State<AuthSettings>,
+    State(mailer): State<SharedMailer>,
+    State(email_settings): State<EmailSettings>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
     State(db_connection): State<DatabaseConnection>,
     Json(user_input): Json<LoginUserInput>,
-) -> ApiResponse<LoginResponseObject, ()> {
-    let user = user::Entity::find()
-        .filter(user::Column::Username.eq(user_input.username))
-        .one(&db_connection)
-        .await
-        .map_err(|_| ApiError::UserNotFound)?;
+) -> Response {
+    let keys = ThrottleKeys::new(&user_input.username, ip);
 
-    let user = user.ok_or(ApiError::UserNotFound)?;
+    let user = match check_credentials(
+        &db_connection,
+        &secrets,
+        &hashing,
+        &settings,
+        &keys,
+        &user_input,
+    )
+    .await
+    {
+        Ok(user) => user,
+        Err(LoginFailure::Rejected(err)) => return err.into_response(),
+        Err(LoginFailure::BadCredentials(user)) => {
+            let recorded = record_login_failure(
+                &db_connection,
+                &settings.login_throttle,
+                mailer.as_ref(),
+                &email_settings,
+                &keys,
+                user.as_deref(),
+                ip,
+            )
+            .await;
 
-    let password = match user.provider {
-        Provider::Google => return Err(ApiError::UserProviderNotValid.into()),
-        Provider::Local => user.password_hash,
+            return match recorded {
+                Ok(()) => ApiError::InvalidCredentials.into_response(),
+                Err(_) => ApiError::InternalError.into_response(),
+            };
+        }
     };
 
-    let hashed_password = password.ok_or(ApiError::InternalError)?;
+    login(
+        &db_connection,
+        &secrets,
+        &settings,
+        &keys,
+        &headers,
+        ip,
+        user,
+    )
+    .await
+    .map(|data| ApiResponseData::success_with_data(data, StatusCode::OK).into_response())
+    .unwrap_or_else(ApiError::into_response)
+}
+
+enum LoginFailure {
+    Rejected(ApiError),
+    // Carries the user the attempt was made on when it exists
+    BadCredentials(Option<Box<user::Model>>),
+}
+
+impl From<ApiError> for LoginFailure {
+    fn from(value: ApiError) -> Self {
+        LoginFailure::Rejected(value)
+    }
+}
+
+async fn check_credentials(
+    db_connection: &DatabaseConnection,
+    secrets: &Secrets,
+    hashing: &PasswordHashingSettings,
+    settings: &AuthSettings,
+    keys: &ThrottleKeys,
+    user_input: &LoginUserInput,
+) -> Result<user::Model, LoginFailure> {
+    let wait = retry_after(db_connection, &settings.login_throttle, keys)
+        .await
+        .map_err(|_| ApiError::InternalError)?;
+    if let Some(secs) = wait {
+        return Err(ApiError::TooManyAttempts(secs).into());
+    }
+
+    let user = user::Entity::find()
+        .filter(user::Column::Username.eq(user_input.username.as_str()))
+        .filter(user::Column::DeletedAt.is_null())
+        .one(db_connection)
+        .await
+        .map_err(|_| ApiError::InternalError)?;
+
+    // Unknown users and users signing in through a provider get the same answer as a wrong
+    // password, so the response doesn't tell which usernames exist
+    let password_hash = match &user {
+        Some(user::Model {
+            provider: Provider::Local,
+            password_hash: Some(password_hash),
+            ..
+        }) => password_hash,
+        _ => {
+            // Hashing anyway keeps the response time close to the one of a known user
+            let _ = hash_password(
+                secrets.hash_secret.as_bytes(),
+                hashing,
+                user_input.password.as_bytes(),
+            )
+            .await;
+            return Err(LoginFailure::BadCredentials(user.map(Box::new)));
+        }
+    };
 
     let is_match = verify_password(
         secrets.hash_secret.as_bytes(),
         user_input.password.as_bytes(),
-        &hashed_password,
+        password_hash,
     )
+    .await
     .map_err(|_| ApiError::InternalError)?;
 
-    if !is_match {
-        return Err(ApiError::BadCredentials.into());
+    let outdated_hash = needs_rehash(password_hash, hashing);
+
+    let user = match (is_match, user) {
+        (true, Some(user)) => user,
+        (_, user) => return Err(LoginFailure::BadCredentials(user.map(Box::new))),
+    };
+
+    // The password is only known now, it is the chance to bring its hash up to the
+    // configured cost
+    if outdated_hash {
+        rehash_password(db_connection, secrets, hashing, &user, user_input).await;
+    }
+
+    Ok(user)
+}
+
+// Failing to rehash doesn't fail the login, the next one tries again
+async fn rehash_password(
+    db_connection: &DatabaseConnection,
+    secrets: &Secrets,
+    hashing: &PasswordHashingSettings,
+    user: &user::Model,
+    user_input: &LoginUserInput,
+) {
+    let password_hash = match hash_password(
+        secrets.hash_secret.as_bytes(),
+        hashing,
+        user_input.password.as_bytes(),
+    )
+    .await
+    {
+        Ok(password_hash) => password_hash,
+        Err(err) => {
+            tracing::error!("couldn't rehash the password of user {}: {err}", user.id);
+            return;
+        }
     };
 
-    // Creating the jwt token
-    let token = encode_jwt(secrets.jwt_secret.as_bytes(), &user.id)
-        .map_err(|_| ApiError::JWTEncodingError)?;
+    // Only replaces the hash that was checked, a password changed meanwhile is kept
+    let updated = user::Entity::update_many()
+        .col_expr(user::Column::PasswordHash, Expr::value(password_hash))
+        .filter(user::Column::Id.eq(user.id))
+        .filter(user::Column::PasswordHash.eq(user.password_hash.clone()))
+        .exec(db_connection)
+        .await;
+
+    if let Err(err) = updated {
+        tracing::error!("couldn't rehash the password of user {}: {err}", user.id);
+    }
+}
+
+async fn login(
+    db_connection: &DatabaseConnection,
+    secrets: &Secrets,
+    settings: &AuthSettings,
+    keys: &ThrottleKeys,
+    headers: &HeaderMap,
+    ip: Option<IpAddr>,
+    user: user::Model,
+) -> Result<LoginResponseObject, ApiError> {
+    // Failures are only cleared once the second factor is given too, or guessing codes
+    // would be free in between two correct passwords
+    if user.totp_enabled_at.is_some() {
+        return LoginResponseObject::two_factor_challenge(secrets, settings, user.id)
+            .map_err(|_| ApiError::JWTEncodingError);
+    }
+
+    clear_failures(db_connection, keys)
+        .await
+        .map_err(|_| ApiError::InternalError)?;
 
-    let data = LoginResponseObject { token };
+    // Starting a session and creating its tokens
+    let session_id = start_session(db_connection, settings, user.id, headers, ip).await?;
+    let tokens = issue_tokens(db_connection, secrets, settings, user.id, session_id).await?;
 
-    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+    Ok(LoginResponseObject::Tokens(tokens))
 }
@@ -0,0 +1,208 @@
+use std::net::IpAddr;
+
+use axum::extract::State;
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::configuration::{AuthSettings, EmailSettings};
+use crate::dto::user::TokenPair;
+use crate::entity::user;
+use crate::handler::helpers::ApiResponseData;
+use crate::handler::utils::{decode_login_challenge, ClientIp};
+use crate::mailer::SharedMailer;
+use crate::router::Secrets;
+
+use super::security_events::{record_security_event, RECOVERY_CODE_USED_EVENT};
+use super::throttle::{clear_failures, record_login_failure, retry_after, ThrottleKeys};
+use super::tokens::{issue_tokens, start_session, TokenError};
+use super::two_factor::{check_second_factor, SecondFactor};
+
+// Either a code of the authenticator app or a recovery code
+#[derive(Deserialize, Debug)]
+pub struct LoginTwoFactorInput {
+    pub challenge_token: String,
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+// Response Object
+#[derive(Serialize, Debug)]
+pub struct LoginTwoFactorResponseObject {
+    #[serde(flatten)]
+    tokens: TokenPair,
+}
+
+enum ApiError {
+    InvalidChallenge,
+    // Seconds before the next attempt is allowed
+    TooManyAttempts(i64),
+    InvalidCode,
+    InternalError,
+    JWTEncodingError,
+}
+
+impl From<TokenError> for ApiError {
+    fn from(value: TokenError) -> Self {
+        match value {
+            TokenError::DbInternalError => ApiError::InternalError,
+            TokenError::JWTEncodingError => ApiError::JWTEncodingError,
+        }
+    }
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::InvalidChallenge => ApiResponseData::error(
+                None,
+                "invalid or expired challenge",
+                StatusCode::UNAUTHORIZED,
+            ),
+            ApiError::TooManyAttempts(_) => ApiResponseData::error(
+                None,
+                "too many login attempts",
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            ApiError::InvalidCode => {
+                ApiResponseData::error(None, "invalid code", StatusCode::UNAUTHORIZED)
+            }
+            ApiError::InternalError | ApiError::JWTEncodingError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let retry_after = match self {
+            ApiError::TooManyAttempts(secs) => Some(secs),
+            _ => None,
+        };
+        let response = ApiResponseData::<()>::from(self).into_response();
+
+        match retry_after {
+            Some(secs) => ([(header::RETRY_AFTER, secs.to_string())], response).into_response(),
+            None => response,
+        }
+    }
+}
+
+// Second step of the login of users with two-factor login enabled. Wrong codes count toward
+// the same lockout as wrong passwords.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(secrets, mailer, headers, input))]
+pub async fn login_two_factor_handler(
+    State(secrets): State<Secrets>,
+    State(settings): State<AuthSettings>,
+    State(mailer): State<SharedMailer>,
+    State(email_settings): State<EmailSettings>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    State(db_connection): State<DatabaseConnection>,
+    Json(input): Json<LoginTwoFactorInput>,
+) -> Response {
+    let user_id =
+        match decode_login_challenge(secrets.jwt_secret.as_bytes(), &input.challenge_token) {
+            Some(user_id) => user_id,
+            None => return ApiError::InvalidChallenge.into_response(),
+        };
+
+    let user = user::Entity::find_by_id(user_id)
+        .filter(user::Column::DeletedAt.is_null())
+        .filter(user::Column::TotpEnabledAt.is_not_null())
+        .one(&db_connection)
+        .await;
+    let user = match user {
+        Ok(Some(user)) => user,
+        Ok(None) => return ApiError::InvalidChallenge.into_response(),
+        Err(_) => return ApiError::InternalError.into_response(),
+    };
+    let keys = ThrottleKeys::new(&user.username, ip);
+
+    match retry_after(&db_connection, &settings.login_throttle, &keys).await {
+        Ok(None) => {}
+        Ok(Some(secs)) => return ApiError::TooManyAttempts(secs).into_response(),
+        Err(_) => return ApiError::InternalError.into_response(),
+    }
+
+    let second_factor = check_second_factor(
+        &db_connection,
+        &user,
+        input.code.as_deref(),
+        input.recovery_code.as_deref(),
+    )
+    .await;
+    let second_factor = match second_factor {
+        Ok(Some(second_factor)) => second_factor,
+        Ok(None) => {
+            let recorded = record_login_failure(
+                &db_connection,
+                &settings.login_throttle,
+                mailer.as_ref(),
+                &email_settings,
+                &keys,
+                Some(&user),
+                ip,
+            )
+            .await;
+
+            return match recorded {
+                Ok(()) => ApiError::InvalidCode.into_response(),
+                Err(_) => ApiError::InternalError.into_response(),
+            };
+        }
+        Err(_) => return ApiError::InternalError.into_response(),
+    };
+
+    login(
+        &db_connection,
+        &secrets,
+        &settings,
+        &keys,
+        &headers,
+        ip,
+        &user,
+        second_factor,
+    )
+    .await
+    .map(|data| ApiResponseData::success_with_data(data, StatusCode::OK).into_response())
+    .unwrap_or_else(ApiError::into_response)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn login(
+    db_connection: &DatabaseConnection,
+    secrets: &Secrets,
+    settings: &AuthSettings,
+    keys: &ThrottleKeys,
+    headers: &HeaderMap,
+    ip: Option<IpAddr>,
+    user: &user::Model,
+    second_factor: SecondFactor,
+) -> Result<LoginTwoFactorResponseObject, ApiError> {
+    // Each recovery code used leaves one less, the user should know when it wasn't them
+    if let SecondFactor::RecoveryCode = second_factor {
+        record_security_event(db_connection, user.id, RECOVERY_CODE_USED_EVENT, ip)
+            .await
+            .map_err(|_| ApiError::InternalError)?;
+    }
+
+    clear_failures(db_connection, keys)
+        .await
+        .map_err(|_| ApiError::InternalError)?;
+
+    // Starting a session and creating its tokens
+    let session_id = start_session(db_connection, settings, user.id, headers, ip).await?;
+    let tokens = issue_tokens(db_connection, secrets, settings, user.id, session_id).await?;
+
+    Ok(LoginTwoFactorResponseObject { tokens })
+}
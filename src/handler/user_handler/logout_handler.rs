@@ -0,0 +1,52 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, Set};
+use serde::Serialize;
+
+use crate::entity::revoked_token;
+use crate::handler::helpers::{ApiResponse, ApiResponseData};
+use crate::handler::utils::AccessToken;
+
+use super::tokens::revoke_sessions;
+
+enum ApiError {
+    DbInternalError,
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::DbInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// Ends the session the access token belongs to, along with its refresh tokens
+#[tracing::instrument(skip(token))]
+pub async fn logout_handler(
+    token: AccessToken,
+    State(db_connection): State<DatabaseConnection>,
+) -> ApiResponse<(), ()> {
+    // Kept until the token would have expired anyway
+    let expires_at = chrono::DateTime::from_timestamp(token.claims.exp, 0)
+        .unwrap_or_else(chrono::Utc::now)
+        .naive_utc();
+    revoked_token::ActiveModel {
+        jti: Set(token.jti),
+        expires_at: Set(expires_at),
+    }
+    .insert(&db_connection)
+    .await
+    .map_err(|_| ApiError::DbInternalError)?;
+
+    revoke_sessions(&db_connection, token.user_id, Some(token.session_id))
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    Ok(ApiResponseData::status_code(StatusCode::OK))
+}
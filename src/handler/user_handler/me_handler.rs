@@ -1,26 +1,16 @@
-use std::str::FromStr;
-
 use crate::{
     dto::user::User,
     entity::user::Entity as Users,
     handler::{
         helpers::{ApiResponse, ApiResponseData, ResponseError},
-        utils::decode_jwt,
+        utils::UserId,
     },
-    router::Secrets,
-};
-use axum::{
-    extract::State,
-    headers::{authorization::Bearer, Authorization},
-    http::StatusCode,
-    TypedHeader,
 };
-use sea_orm::{prelude::Uuid, DatabaseConnection, EntityTrait};
+use axum::{extract::State, http::StatusCode};
+use sea_orm::{DatabaseConnection, EntityTrait};
 use serde::Serialize;
 
-pub enum ApiError {
-    InvalidJwtToken,
-    InvalidIdFormat,
+enum ApiError {
     DbInternalError,
     UserNotFound,
 }
@@ -36,26 +26,17 @@ impl<E> From<ApiError> for ApiResponseData<E>
 {
     fn from(value: ApiError) -> Self {
         match value {
-            ApiError::InvalidJwtToken | ApiError::InvalidIdFormat => ApiResponseData::status_code(StatusCode::NOT_ACCEPTABLE),
             ApiError::DbInternalError => ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR),
             ApiError::UserNotFound => ApiResponseData::status_code(StatusCode::BAD_REQUEST),
         }
     }
 }
 
-#[tracing::instrument(skip(secrets))]
+#[tracing::instrument]
 pub async fn me_handler(
     State(db_connection): State<DatabaseConnection>,
-    State(secrets): State<Secrets>,
-    TypedHeader(token): TypedHeader<Authorization<Bearer>>,
+    UserId(user_id): UserId,
 ) -> ApiResponse<MeResponse, ResponseError> {
-    let token = token.token();
-
-    let claims = decode_jwt(secrets.jwt_secret.as_bytes(), token)
-        .map_err(|_| ApiError::InvalidJwtToken)?;
-
-    let user_id = Uuid::from_str(&claims.sub).map_err(|_| ApiError::InvalidIdFormat)?;
-
     let res = Users::find_by_id(user_id)
         .one(&db_connection)
         .await
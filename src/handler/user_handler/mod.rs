@@ -1,7 +1,53 @@
+mod change_password_handler;
+mod confirm_totp_handler;
+mod create_api_key_handler;
+mod delete_api_key_handler;
+mod delete_me_handler;
+mod delete_session_handler;
+mod delete_sessions_handler;
+mod disable_totp_handler;
+mod emails;
+mod enroll_totp_handler;
+mod forgot_password_handler;
+mod get_api_keys_handler;
+mod get_sessions_handler;
 mod login_handler;
+mod login_two_factor_handler;
+mod logout_handler;
 mod me_handler;
+mod oidc_authorize_handler;
+mod oidc_callback_handler;
+mod refresh_handler;
 mod register_handler;
+mod reset_password_handler;
+mod security_events;
+mod throttle;
+mod tokens;
+mod two_factor;
+mod update_me_handler;
+mod verify_email_handler;
 
+pub use change_password_handler::*;
+pub use confirm_totp_handler::*;
+pub use create_api_key_handler::*;
+pub use delete_api_key_handler::*;
+pub use delete_me_handler::*;
+pub use delete_session_handler::*;
+pub use delete_sessions_handler::*;
+pub use disable_totp_handler::*;
+pub use enroll_totp_handler::*;
+pub use forgot_password_handler::*;
+pub use get_api_keys_handler::*;
+pub use get_sessions_handler::*;
 pub use login_handler::*;
+pub use login_two_factor_handler::*;
+pub use logout_handler::*;
 pub use me_handler::*;
+pub use oidc_authorize_handler::*;
+pub use oidc_callback_handler::*;
+pub use refresh_handler::*;
 pub use register_handler::*;
+pub use reset_password_handler::*;
+pub use update_me_handler::*;
+pub use verify_email_handler::*;
+pub(crate) use throttle::{clear_failures, record_failure, retry_after, ThrottleKeys};
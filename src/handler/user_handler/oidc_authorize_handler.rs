@@ -0,0 +1,79 @@
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+
+use crate::handler::helpers::ApiResponseData;
+use crate::handler::utils::{encode_oidc_login, oidc_login_cookie};
+use crate::oidc::{code_challenge, generate_code_verifier, random_token, OidcClient};
+use crate::router::Secrets;
+
+const STATE_LENGTH: usize = 32;
+
+enum ApiError {
+    OidcNotConfigured,
+    ProviderUnreachable,
+    InternalError,
+}
+
+impl From<ApiError> for ApiResponseData<()> {
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::OidcNotConfigured => {
+                ApiResponseData::error(None, "oidc login is not configured", StatusCode::NOT_FOUND)
+            }
+            ApiError::ProviderUnreachable => {
+                ApiResponseData::error(None, "couldn't reach the provider", StatusCode::BAD_GATEWAY)
+            }
+            ApiError::InternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// Sends the user to the provider, the state, nonce and code verifier of the login are kept
+// in a signed cookie until the provider redirects to the callback
+#[tracing::instrument(skip(oidc, secrets))]
+pub async fn oidc_authorize_handler(
+    State(oidc): State<Option<OidcClient>>,
+    State(secrets): State<Secrets>,
+) -> Response {
+    authorize(oidc.as_ref(), &secrets)
+        .await
+        .unwrap_or_else(|err| ApiResponseData::from(err).into_response())
+}
+
+async fn authorize(oidc: Option<&OidcClient>, secrets: &Secrets) -> Result<Response, ApiError> {
+    let oidc = oidc.ok_or(ApiError::OidcNotConfigured)?;
+
+    let state = random_token(STATE_LENGTH);
+    let nonce = random_token(STATE_LENGTH);
+    let code_verifier = generate_code_verifier();
+
+    let authorization_url = oidc
+        .authorization_url(&state, &nonce, &code_challenge(&code_verifier))
+        .await
+        .map_err(|err| {
+            tracing::error!("couldn't start oidc login: {err}");
+            ApiError::ProviderUnreachable
+        })?;
+
+    let ttl = oidc.settings().login_ttl();
+    let token = encode_oidc_login(
+        secrets.jwt_secret.as_bytes(),
+        &state,
+        &nonce,
+        &code_verifier,
+        ttl,
+    )
+    .map_err(|_| ApiError::InternalError)?;
+
+    Ok((
+        StatusCode::SEE_OTHER,
+        [
+            (header::SET_COOKIE, oidc_login_cookie(&token, ttl)),
+            (header::LOCATION, authorization_url),
+        ],
+    )
+        .into_response())
+}
@@ -0,0 +1,299 @@
+use std::net::IpAddr;
+
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use rand::Rng;
+use sea_orm::prelude::Uuid;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::configuration::{AuthSettings, OidcSettings};
+use crate::entity::sea_orm_active_enums::Provider;
+use crate::entity::user;
+use crate::handler::helpers::ApiResponseData;
+use crate::handler::utils::{clear_oidc_login_cookie, decode_oidc_login, ClientIp};
+use crate::oidc::{IdTokenClaims, OidcClient};
+use crate::router::Secrets;
+
+use super::tokens::{issue_tokens, start_session, TokenError};
+use super::LoginResponseObject;
+
+const USERNAME_BASE_MAX_LENGTH: usize = 15;
+const USERNAME_MIN_LENGTH: usize = 6;
+const USERNAME_ATTEMPTS: usize = 5;
+
+// Sent by the provider, `error` replaces the code when the user didn't consent
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OidcLoginResponse {
+    #[serde(flatten)]
+    login: LoginResponseObject,
+}
+
+enum ApiError {
+    OidcNotConfigured,
+    LoginDenied,
+    InvalidState,
+    ProviderUnreachable,
+    InvalidIdToken,
+    EmailNotVerified,
+    EmailLinkedToOtherIdentity,
+    PasswordAccountExists,
+    AccountDeleted,
+    DbInternalError,
+    JWTEncodingError,
+}
+
+impl From<TokenError> for ApiError {
+    fn from(value: TokenError) -> Self {
+        match value {
+            TokenError::DbInternalError => ApiError::DbInternalError,
+            TokenError::JWTEncodingError => ApiError::JWTEncodingError,
+        }
+    }
+}
+
+impl From<ApiError> for ApiResponseData<()> {
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::OidcNotConfigured => {
+                ApiResponseData::error(None, "oidc login is not configured", StatusCode::NOT_FOUND)
+            }
+            ApiError::LoginDenied => {
+                ApiResponseData::error(None, "oidc login was denied", StatusCode::UNAUTHORIZED)
+            }
+            ApiError::InvalidState => {
+                ApiResponseData::error(None, "invalid oidc state", StatusCode::BAD_REQUEST)
+            }
+            ApiError::ProviderUnreachable => {
+                ApiResponseData::error(None, "couldn't reach the provider", StatusCode::BAD_GATEWAY)
+            }
+            ApiError::InvalidIdToken => {
+                ApiResponseData::error(None, "invalid id token", StatusCode::UNAUTHORIZED)
+            }
+            ApiError::EmailNotVerified => ApiResponseData::error(
+                None,
+                "email not verified by the provider",
+                StatusCode::FORBIDDEN,
+            ),
+            ApiError::EmailLinkedToOtherIdentity => ApiResponseData::error(
+                None,
+                "email already linked to another identity",
+                StatusCode::CONFLICT,
+            ),
+            ApiError::PasswordAccountExists => ApiResponseData::error(
+                None,
+                "an account with this email signs in with a password",
+                StatusCode::CONFLICT,
+            ),
+            ApiError::AccountDeleted => {
+                ApiResponseData::error(None, "account deleted", StatusCode::FORBIDDEN)
+            }
+            ApiError::DbInternalError | ApiError::JWTEncodingError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// Where the provider sends the user back. The code is exchanged for an id token, the user
+// it belongs to is found, linked by email or created, and gets our own tokens. Users with
+// two-factor login enabled get the same challenge as with a password instead.
+#[tracing::instrument(skip(oidc, secrets, headers))]
+pub async fn oidc_callback_handler(
+    State(oidc): State<Option<OidcClient>>,
+    State(secrets): State<Secrets>,
+    State(settings): State<AuthSettings>,
+    State(db_connection): State<DatabaseConnection>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Query(params): Query<OidcCallbackParams>,
+) -> Response {
+    let response = callback(
+        oidc.as_ref(),
+        &secrets,
+        &settings,
+        &db_connection,
+        ip,
+        &headers,
+        params,
+    )
+    .await
+    .map(|login| {
+        ApiResponseData::success_with_data(OidcLoginResponse { login }, StatusCode::OK)
+            .into_response()
+    })
+    .unwrap_or_else(|err| ApiResponseData::from(err).into_response());
+
+    // The login state is only good for a single callback
+    ([(header::SET_COOKIE, clear_oidc_login_cookie())], response).into_response()
+}
+
+async fn callback(
+    oidc: Option<&OidcClient>,
+    secrets: &Secrets,
+    settings: &AuthSettings,
+    db: &DatabaseConnection,
+    ip: Option<IpAddr>,
+    headers: &HeaderMap,
+    params: OidcCallbackParams,
+) -> Result<LoginResponseObject, ApiError> {
+    let oidc = oidc.ok_or(ApiError::OidcNotConfigured)?;
+
+    let login =
+        decode_oidc_login(secrets.jwt_secret.as_bytes(), headers).ok_or(ApiError::InvalidState)?;
+    if params.state.as_deref() != Some(login.state.as_str()) {
+        return Err(ApiError::InvalidState);
+    }
+    let code = match (params.code, params.error) {
+        (Some(code), None) => code,
+        _ => return Err(ApiError::LoginDenied),
+    };
+
+    let claims = oidc
+        .exchange_code(&code, &login.code_verifier, &login.nonce)
+        .await
+        .map_err(|err| {
+            tracing::warn!("oidc code exchange failed: {err}");
+            if err.is_unreachable() {
+                ApiError::ProviderUnreachable
+            } else {
+                ApiError::InvalidIdToken
+            }
+        })?;
+
+    let user = find_or_create_user(db, oidc.settings(), claims).await?;
+
+    // The provider only stands in for the password
+    if user.totp_enabled_at.is_some() {
+        return LoginResponseObject::two_factor_challenge(secrets, settings, user.id)
+            .map_err(|_| ApiError::JWTEncodingError);
+    }
+
+    let session_id = start_session(db, settings, user.id, headers, ip).await?;
+    let tokens = issue_tokens(db, secrets, settings, user.id, session_id).await?;
+
+    Ok(LoginResponseObject::Tokens(tokens))
+}
+
+async fn find_or_create_user(
+    db: &DatabaseConnection,
+    oidc_settings: &OidcSettings,
+    claims: IdTokenClaims,
+) -> Result<user::Model, ApiError> {
+    let known_user = user::Entity::find()
+        .filter(user::Column::OidcIssuer.eq(claims.iss.as_str()))
+        .filter(user::Column::OidcSubject.eq(claims.sub.as_str()))
+        .one(db)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+    if let Some(user) = known_user {
+        return match user.deleted_at {
+            Some(_) => Err(ApiError::AccountDeleted),
+            None => Ok(user),
+        };
+    }
+
+    // Accounts are only matched on an email the provider vouches for
+    let email = match &claims.email {
+        Some(email) if claims.email_verified => email.clone(),
+        _ => return Err(ApiError::EmailNotVerified),
+    };
+
+    let now = chrono::Utc::now().naive_utc();
+    let existing_user = user::Entity::find()
+        .filter(user::Column::Email.eq(email.as_str()))
+        .one(db)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+    if let Some(user) = existing_user {
+        if user.deleted_at.is_some() {
+            return Err(ApiError::AccountDeleted);
+        }
+        if user.oidc_subject.is_some() {
+            return Err(ApiError::EmailLinkedToOtherIdentity);
+        }
+        // Owning the email isn't proof enough to take over an account with a password
+        if user.provider == Provider::Local
+            && user.password_hash.is_some()
+            && !oidc_settings.can_link_password_accounts(&claims.iss)
+        {
+            return Err(ApiError::PasswordAccountExists);
+        }
+
+        let verified_at = user.email_verified_at.unwrap_or(now);
+        let mut user: user::ActiveModel = user.into();
+        user.oidc_issuer = Set(Some(claims.iss));
+        user.oidc_subject = Set(Some(claims.sub));
+        user.email_verified_at = Set(Some(verified_at));
+        user.updated_at = Set(Some(now));
+
+        return user.update(db).await.map_err(|_| ApiError::DbInternalError);
+    }
+
+    let username = available_username(db, &claims).await?;
+
+    user::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        username: Set(username),
+        email: Set(email),
+        password_hash: Set(None),
+        provider: Set(Provider::Google),
+        created_at: Set(now),
+        oidc_issuer: Set(Some(claims.iss)),
+        oidc_subject: Set(Some(claims.sub)),
+        email_verified_at: Set(Some(now)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(|_| ApiError::DbInternalError)
+}
+
+// Derived from the profile of the user, a numeric suffix is added when it is taken or too short
+async fn available_username(
+    db: &DatabaseConnection,
+    claims: &IdTokenClaims,
+) -> Result<String, ApiError> {
+    let source = claims
+        .preferred_username
+        .as_deref()
+        .or_else(|| claims.email.as_deref()?.split('@').next())
+        .unwrap_or_default();
+    let mut base: String = source
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .take(USERNAME_BASE_MAX_LENGTH)
+        .collect::<String>()
+        .to_lowercase();
+    if base.len() < 2 {
+        base = "user".into();
+    }
+
+    for attempt in 0..USERNAME_ATTEMPTS {
+        let username = if attempt == 0 && base.len() >= USERNAME_MIN_LENGTH {
+            base.clone()
+        } else {
+            format!("{base}{:04}", rand::thread_rng().gen_range(0..10_000))
+        };
+
+        let taken = user::Entity::find()
+            .filter(user::Column::Username.eq(username.as_str()))
+            .one(db)
+            .await
+            .map_err(|_| ApiError::DbInternalError)?;
+        if taken.is_none() {
+            return Ok(username);
+        }
+    }
+
+    Err(ApiError::DbInternalError)
+}
@@ -0,0 +1,119 @@
+use axum::extract::State;
+use axum::{http::StatusCode, Json};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::configuration::AuthSettings;
+use crate::dto::user::TokenPair;
+use crate::entity::{refresh_token, session};
+use crate::handler::helpers::{ApiResponse, ApiResponseData};
+use crate::handler::utils::hash_refresh_token;
+use crate::router::Secrets;
+
+use super::tokens::{issue_tokens, revoke_sessions, TokenError};
+
+// Client input
+#[derive(Deserialize)]
+pub struct RefreshTokenInput {
+    pub refresh_token: String,
+}
+
+enum ApiError {
+    InvalidRefreshToken,
+    DbInternalError,
+    JWTEncodingError,
+}
+
+impl From<TokenError> for ApiError {
+    fn from(value: TokenError) -> Self {
+        match value {
+            TokenError::DbInternalError => ApiError::DbInternalError,
+            TokenError::JWTEncodingError => ApiError::JWTEncodingError,
+        }
+    }
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::InvalidRefreshToken => {
+                ApiResponseData::error(None, "invalid refresh token", StatusCode::UNAUTHORIZED)
+            }
+            ApiError::DbInternalError | ApiError::JWTEncodingError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+#[tracing::instrument(skip(secrets, input))]
+pub async fn refresh_handler(
+    State(secrets): State<Secrets>,
+    State(settings): State<AuthSettings>,
+    State(db_connection): State<DatabaseConnection>,
+    Json(input): Json<RefreshTokenInput>,
+) -> ApiResponse<TokenPair, ()> {
+    let token = refresh_token::Entity::find()
+        .filter(refresh_token::Column::TokenHash.eq(hash_refresh_token(&input.refresh_token)))
+        .one(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    let token = token.ok_or(ApiError::InvalidRefreshToken)?;
+
+    let now = chrono::Utc::now().naive_utc();
+    if token.revoked_at.is_some() || token.expires_at <= now {
+        return Err(ApiError::InvalidRefreshToken.into());
+    }
+
+    let txn = db_connection
+        .begin()
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    // Only one request gets to rotate a token, even when they race
+    let rotated = refresh_token::Entity::update_many()
+        .col_expr(refresh_token::Column::UsedAt, Expr::value(now))
+        .filter(refresh_token::Column::Id.eq(token.id))
+        .filter(refresh_token::Column::UsedAt.is_null())
+        .filter(refresh_token::Column::RevokedAt.is_null())
+        .exec(&txn)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    if rotated.rows_affected == 0 {
+        // An already rotated token came back, it may have been stolen: the whole session goes
+        revoke_sessions(&txn, token.user_id, Some(token.family_id))
+            .await
+            .map_err(|_| ApiError::DbInternalError)?;
+        txn.commit().await.map_err(|_| ApiError::DbInternalError)?;
+
+        tracing::warn!("refresh token reuse detected for user {}", token.user_id);
+        return Err(ApiError::InvalidRefreshToken.into());
+    }
+
+    // Sessions last as long as their refresh tokens keep being rotated
+    session::Entity::update_many()
+        .col_expr(session::Column::LastUsedAt, Expr::value(now))
+        .col_expr(
+            session::Column::ExpiresAt,
+            Expr::value(now + settings.refresh_token_ttl()),
+        )
+        .filter(session::Column::Id.eq(token.family_id))
+        .exec(&txn)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    let data = issue_tokens(&txn, &secrets, &settings, token.user_id, token.family_id)
+        .await
+        .map_err(ApiError::from)?;
+
+    txn.commit().await.map_err(|_| ApiError::DbInternalError)?;
+
+    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+}
@@ -1,7 +1,10 @@
 use std::fmt::Debug;
 
 use axum::extract::State;
-use axum::{extract::Json, http::StatusCode};
+use axum::{
+    extract::Json,
+    http::{HeaderMap, StatusCode},
+};
 use sea_orm::prelude::Uuid;
 use sea_orm::ActiveValue::Set;
 use sea_orm::{query::Condition, ActiveModelTrait, EntityTrait, QueryFilter};
@@ -9,12 +12,18 @@ use sea_orm::{ColumnTrait, DatabaseConnection};
 use serde::{Deserialize, Serialize};
 use validator::{Validate, ValidationErrors};
 
+use crate::configuration::{AuthSettings, EmailSettings, PasswordHashingSettings};
+use crate::dto::user::TokenPair;
 use crate::entity::sea_orm_active_enums::Provider;
 use crate::entity::user;
 use crate::handler::helpers::{ApiResponse, ApiResponseData, ResponseError};
-use crate::handler::utils::{encode_jwt, hash_password};
+use crate::handler::utils::{hash_password, ClientIp, EmailTokenPurpose};
+use crate::mailer::SharedMailer;
 use crate::router::Secrets;
 
+use super::emails::send_email_token;
+use super::tokens::{issue_tokens, start_session, TokenError};
+
 // Client data to create a User
 #[derive(Debug, Validate, Deserialize)]
 pub struct RegisterUserInput {
@@ -29,12 +38,13 @@ pub struct RegisterUserInput {
 // Response Object
 #[derive(Serialize, Debug)]
 pub struct RegisterResponseObject {
-    token: String,
+    #[serde(flatten)]
+    tokens: TokenPair,
 }
 
 // Errors
 #[derive(Debug)]
-pub enum ApiError {
+enum ApiError {
     BadClientData(ValidationErrors),
     UserAlreadyRegistered,
     DbInternalError,
@@ -42,6 +52,15 @@ pub enum ApiError {
     JWTEncodingError,
 }
 
+impl From<TokenError> for ApiError {
+    fn from(value: TokenError) -> Self {
+        match value {
+            TokenError::DbInternalError => ApiError::DbInternalError,
+            TokenError::JWTEncodingError => ApiError::JWTEncodingError,
+        }
+    }
+}
+
 impl From<ApiError> for ApiResponseData<ResponseError> {
     fn from(value: ApiError) -> Self {
         match value {
@@ -60,10 +79,17 @@ impl From<ApiError> for ApiResponseData<ResponseError> {
     }
 }
 
-#[tracing::instrument(skip(secrets))]
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(secrets, mailer, headers))]
 pub async fn register_handler(
     State(db_connection): State<DatabaseConnection>,
     State(secrets): State<Secrets>,
+    State(hashing): State<PasswordHashingSettings>,
+    State(settings): State<AuthSettings>,
+    State(mailer): State<SharedMailer>,
+    State(email_settings): State<EmailSettings>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
     Json(create_user): Json<RegisterUserInput>,
 ) -> ApiResponse<RegisterResponseObject, ResponseError> {
     // Validating user input
@@ -93,8 +119,10 @@ pub async fn register_handler(
 
     let hashed_password = hash_password(
         secrets.hash_secret.as_bytes(),
+        &hashing,
         create_user.password.as_bytes(),
     )
+    .await
     .map_err(|_| ApiError::HashingError)?;
 
     // Creating User model and inserting it
@@ -114,11 +142,25 @@ pub async fn register_handler(
         .await
         .map_err(|_| ApiError::DbInternalError)?;
 
-    // Creating the jwt token
-    let token = encode_jwt(secrets.jwt_secret.as_bytes(), &user.id)
-        .map_err(|_| ApiError::JWTEncodingError)?;
+    // The account is usable right away, the email gets verified on the side
+    send_email_token(
+        mailer.as_ref(),
+        &email_settings,
+        &secrets,
+        EmailTokenPurpose::VerifyEmail,
+        &user,
+    )
+    .await;
+
+    // Starting a session and creating its tokens
+    let session_id = start_session(&db_connection, &settings, user.id, &headers, ip)
+        .await
+        .map_err(ApiError::from)?;
+    let tokens = issue_tokens(&db_connection, &secrets, &settings, user.id, session_id)
+        .await
+        .map_err(ApiError::from)?;
 
-    let data = RegisterResponseObject { token };
+    let data = RegisterResponseObject { tokens };
 
     Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
 }
@@ -0,0 +1,104 @@
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait};
+use serde::Deserialize;
+use validator::{Validate, ValidationErrors};
+
+use crate::configuration::PasswordHashingSettings;
+use crate::entity::{sea_orm_active_enums::Provider, user};
+use crate::handler::helpers::{ApiResponse, ApiResponseData, ResponseError};
+use crate::handler::utils::{
+    decode_email_token, hash_password, is_email_token_current, EmailTokenPurpose,
+};
+use crate::router::Secrets;
+
+use super::tokens::revoke_sessions;
+
+#[derive(Validate, Deserialize)]
+pub struct ResetPasswordInput {
+    pub token: String,
+    #[validate(length(min = 5, max = 25))]
+    pub password: String,
+}
+
+enum ApiError {
+    BadClientData(ValidationErrors),
+    InvalidToken,
+    DbInternalError,
+    HashingError,
+}
+
+impl From<ApiError> for ApiResponseData<ResponseError> {
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::BadClientData(err) => ApiResponseData::error(
+                Some(ResponseError::from(err)),
+                "invalid data from client",
+                StatusCode::BAD_REQUEST,
+            ),
+            ApiError::InvalidToken => {
+                ApiResponseData::error(None, "invalid or expired token", StatusCode::BAD_REQUEST)
+            }
+            ApiError::DbInternalError | ApiError::HashingError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+#[tracing::instrument(skip(secrets, input))]
+pub async fn reset_password_handler(
+    State(db_connection): State<DatabaseConnection>,
+    State(secrets): State<Secrets>,
+    State(hashing): State<PasswordHashingSettings>,
+    Json(input): Json<ResetPasswordInput>,
+) -> ApiResponse<(), ResponseError> {
+    input.validate().map_err(ApiError::BadClientData)?;
+
+    let purpose = EmailTokenPurpose::ResetPassword;
+    let (user_id, claims) =
+        decode_email_token(secrets.jwt_secret.as_bytes(), purpose, &input.token)
+            .ok_or(ApiError::InvalidToken)?;
+
+    let user = user::Entity::find_by_id(user_id)
+        .one(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?
+        .ok_or(ApiError::InvalidToken)?;
+
+    // The new password changes the fingerprint, the token can't be used twice
+    if user.deleted_at.is_some()
+        || user.provider != Provider::Local
+        || !is_email_token_current(purpose, &claims, &user)
+    {
+        return Err(ApiError::InvalidToken.into());
+    }
+
+    let password_hash = hash_password(
+        secrets.hash_secret.as_bytes(),
+        &hashing,
+        input.password.as_bytes(),
+    )
+    .await
+    .map_err(|_| ApiError::HashingError)?;
+
+    let now = chrono::Utc::now().naive_utc();
+    let verified_at = user.email_verified_at.unwrap_or(now);
+    let mut user: user::ActiveModel = user.into();
+    user.password_hash = Set(Some(password_hash));
+    // Following the emailed link proves the address as well
+    user.email_verified_at = Set(Some(verified_at));
+    user.updated_at = Set(Some(now));
+    let user = user
+        .update(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    // Whoever knew the previous password is signed out
+    revoke_sessions(&db_connection, user.id, None)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    Ok(ApiResponseData::status_code(StatusCode::OK))
+}
@@ -0,0 +1,30 @@
+use std::net::IpAddr;
+
+use sea_orm::{prelude::Uuid, ActiveModelTrait, ConnectionTrait, DbErr, Set};
+
+use crate::entity::security_event;
+
+// Kinds of the events kept on the account of a user
+pub(super) const LOGIN_LOCKOUT_EVENT: &str = "login_lockout";
+pub(super) const TOTP_ENABLED_EVENT: &str = "totp_enabled";
+pub(super) const TOTP_DISABLED_EVENT: &str = "totp_disabled";
+pub(super) const RECOVERY_CODE_USED_EVENT: &str = "recovery_code_used";
+
+pub(super) async fn record_security_event<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    kind: &str,
+    ip: Option<IpAddr>,
+) -> Result<(), DbErr> {
+    security_event::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        kind: Set(kind.to_owned()),
+        ip: Set(ip.map(|ip| ip.to_string())),
+        created_at: Set(chrono::Utc::now().naive_utc()),
+    }
+    .insert(db)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use std::net::IpAddr;
+
+use sea_orm::{
+    prelude::Uuid, sea_query::Expr, ColumnTrait, ConnectionTrait, DatabaseBackend, DbErr,
+    EntityTrait, QueryFilter, Statement,
+};
+
+use crate::{
+    configuration::{EmailSettings, LoginThrottleSettings},
+    entity::{login_throttle, user},
+    mailer::Mailer,
+};
+
+use super::emails::send_lockout_notice;
+use super::security_events::{record_security_event, LOGIN_LOCKOUT_EVENT};
+
+// Counters an attempt is checked against, the account or link it targets and the client ip
+pub(crate) struct ThrottleKeys {
+    subject: String,
+    ip: Option<String>,
+}
+
+impl ThrottleKeys {
+    pub(super) fn new(username: &str, ip: Option<IpAddr>) -> Self {
+        Self {
+            subject: format!("username:{}", username.to_lowercase()),
+            ip: ip.map(|ip| format!("ip:{ip}")),
+        }
+    }
+
+    // Unlocking a password protected link, guessed at like a login
+    pub(crate) fn for_link(link_id: Uuid, ip: Option<IpAddr>) -> Self {
+        Self {
+            subject: format!("link:{link_id}"),
+            ip: ip.map(|ip| format!("ip:{ip}")),
+        }
+    }
+
+    fn all(&self) -> Vec<String> {
+        std::iter::once(self.subject.clone())
+            .chain(self.ip.clone())
+            .collect()
+    }
+}
+
+// Seconds left before another attempt is allowed, the longest of the subject and ip waits.
+// Rounded up so a client retrying right on time isn't turned away again.
+pub(crate) async fn retry_after<C: ConnectionTrait>(
+    db: &C,
+    settings: &LoginThrottleSettings,
+    keys: &ThrottleKeys,
+) -> Result<Option<i64>, DbErr> {
+    let now = chrono::Utc::now().naive_utc();
+    let counters = login_throttle::Entity::find()
+        .filter(login_throttle::Column::Key.is_in(keys.all()))
+        .all(db)
+        .await?;
+
+    let wait = counters
+        .iter()
+        .filter_map(|counter| {
+            let allowed_at = match counter.locked_until {
+                Some(locked_until) if locked_until > now => locked_until,
+                _ => counter.last_failure_at + settings.delay(counter.failures),
+            };
+
+            (allowed_at > now).then(|| allowed_at - now)
+        })
+        .max()
+        .map(|wait| ((wait.num_milliseconds() + 999) / 1000).max(1));
+
+    Ok(wait)
+}
+
+// Counts a failure against both keys, returns whether it locked the subject. Links are
+// locked after as many failures as usernames.
+pub(crate) async fn record_failure<C: ConnectionTrait>(
+    db: &C,
+    settings: &LoginThrottleSettings,
+    keys: &ThrottleKeys,
+) -> Result<bool, DbErr> {
+    let subject_locked = increment(
+        db,
+        settings,
+        &keys.subject,
+        settings.max_failures_per_username,
+    )
+    .await?;
+    if let Some(ip) = &keys.ip {
+        increment(db, settings, ip, settings.max_failures_per_ip).await?;
+    }
+
+    Ok(subject_locked)
+}
+
+// Concurrent failures are counted in a single statement so none of them is lost
+async fn increment<C: ConnectionTrait>(
+    db: &C,
+    settings: &LoginThrottleSettings,
+    key: &str,
+    max_failures: i32,
+) -> Result<bool, DbErr> {
+    let now = chrono::Utc::now().naive_utc();
+    let sql = r#"INSERT INTO "login_throttle" ("key", "failures", "last_failure_at")
+        VALUES ($1, 1, $2)
+        ON CONFLICT ("key") DO UPDATE SET
+            "failures" = CASE
+                WHEN "login_throttle"."last_failure_at" < $3 THEN 1
+                ELSE "login_throttle"."failures" + 1
+            END,
+            "last_failure_at" = EXCLUDED."last_failure_at"
+        RETURNING "failures""#;
+
+    let row = db
+        .query_one(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            sql,
+            [
+                key.into(),
+                now.into(),
+                (now - settings.failure_window()).into(),
+            ],
+        ))
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(key.to_owned()))?;
+    let failures: i32 = row.try_get("", "failures")?;
+
+    if failures < max_failures {
+        return Ok(false);
+    }
+
+    login_throttle::Entity::update_many()
+        .col_expr(
+            login_throttle::Column::LockedUntil,
+            Expr::value(now + settings.lockout()),
+        )
+        .filter(login_throttle::Column::Key.eq(key))
+        .exec(db)
+        .await?;
+
+    Ok(true)
+}
+
+// Counts a wrong password or second factor and, when it locks the account, records it and
+// notifies the user
+pub(super) async fn record_login_failure<C: ConnectionTrait>(
+    db: &C,
+    settings: &LoginThrottleSettings,
+    mailer: &dyn Mailer,
+    email_settings: &EmailSettings,
+    keys: &ThrottleKeys,
+    user: Option<&user::Model>,
+    ip: Option<IpAddr>,
+) -> Result<(), DbErr> {
+    let locked = record_failure(db, settings, keys).await?;
+
+    if let (true, Some(user)) = (locked, user) {
+        record_security_event(db, user.id, LOGIN_LOCKOUT_EVENT, ip).await?;
+        send_lockout_notice(mailer, email_settings, user, settings.lockout()).await;
+    }
+
+    Ok(())
+}
+
+// Failures from the ip are kept, a valid login on one account says nothing about the others
+pub(crate) async fn clear_failures<C: ConnectionTrait>(
+    db: &C,
+    keys: &ThrottleKeys,
+) -> Result<(), DbErr> {
+    login_throttle::Entity::delete_by_id(keys.subject.clone())
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use std::net::IpAddr;
+
+use axum::http::{header, HeaderMap};
+use sea_orm::{
+    prelude::Uuid, sea_query::Expr, ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr,
+    EntityTrait, QueryFilter, Set,
+};
+
+use crate::{
+    configuration::AuthSettings,
+    dto::user::TokenPair,
+    entity::{refresh_token, session},
+    handler::utils::{encode_jwt, generate_refresh_token, hash_refresh_token},
+    router::Secrets,
+};
+
+pub(super) enum TokenError {
+    DbInternalError,
+    JWTEncodingError,
+}
+
+// Every login starts a session, its id is also the id of the family of refresh tokens
+// rotated within it
+pub(super) async fn start_session<C: ConnectionTrait>(
+    db: &C,
+    settings: &AuthSettings,
+    user_id: Uuid,
+    headers: &HeaderMap,
+    ip: Option<IpAddr>,
+) -> Result<Uuid, TokenError> {
+    let now = chrono::Utc::now().naive_utc();
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+
+    let session = session::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        created_at: Set(now),
+        last_used_at: Set(now),
+        expires_at: Set(now + settings.refresh_token_ttl()),
+        user_agent: Set(user_agent),
+        ip: Set(ip.map(|ip| ip.to_string())),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(|_| TokenError::DbInternalError)?;
+
+    Ok(session.id)
+}
+
+pub(super) async fn issue_tokens<C: ConnectionTrait>(
+    db: &C,
+    secrets: &Secrets,
+    settings: &AuthSettings,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<TokenPair, TokenError> {
+    let access_token_ttl = settings.access_token_ttl();
+    let token = encode_jwt(
+        secrets.jwt_secret.as_bytes(),
+        &user_id,
+        &session_id,
+        access_token_ttl,
+    )
+    .map_err(|_| TokenError::JWTEncodingError)?;
+
+    let refresh_token = generate_refresh_token();
+    let now = chrono::Utc::now().naive_utc();
+    refresh_token::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        family_id: Set(session_id),
+        token_hash: Set(hash_refresh_token(&refresh_token)),
+        created_at: Set(now),
+        expires_at: Set(now + settings.refresh_token_ttl()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(|_| TokenError::DbInternalError)?;
+
+    Ok(TokenPair {
+        token,
+        refresh_token,
+        expires_in: access_token_ttl.num_seconds(),
+    })
+}
+
+// Access tokens of a revoked session are rejected by the `UserId` extractor
+pub(super) async fn revoke_sessions<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    session_id: Option<Uuid>,
+) -> Result<u64, DbErr> {
+    let now = chrono::Utc::now().naive_utc();
+
+    let mut sessions = session::Entity::update_many()
+        .col_expr(session::Column::RevokedAt, Expr::value(now))
+        .filter(session::Column::UserId.eq(user_id))
+        .filter(session::Column::RevokedAt.is_null());
+    let mut refresh_tokens = refresh_token::Entity::update_many()
+        .col_expr(refresh_token::Column::RevokedAt, Expr::value(now))
+        .filter(refresh_token::Column::UserId.eq(user_id))
+        .filter(refresh_token::Column::RevokedAt.is_null());
+
+    if let Some(session_id) = session_id {
+        sessions = sessions.filter(session::Column::Id.eq(session_id));
+        refresh_tokens = refresh_tokens.filter(refresh_token::Column::FamilyId.eq(session_id));
+    }
+
+    let res = sessions.exec(db).await?;
+    refresh_tokens.exec(db).await?;
+
+    Ok(res.rows_affected)
+}
+
+// Signs out every session but the one making the request
+pub(super) async fn revoke_other_sessions<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    current_session_id: Uuid,
+) -> Result<u64, DbErr> {
+    let now = chrono::Utc::now().naive_utc();
+
+    let res = session::Entity::update_many()
+        .col_expr(session::Column::RevokedAt, Expr::value(now))
+        .filter(session::Column::UserId.eq(user_id))
+        .filter(session::Column::Id.ne(current_session_id))
+        .filter(session::Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    refresh_token::Entity::update_many()
+        .col_expr(refresh_token::Column::RevokedAt, Expr::value(now))
+        .filter(refresh_token::Column::UserId.eq(user_id))
+        .filter(refresh_token::Column::FamilyId.ne(current_session_id))
+        .filter(refresh_token::Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+
+    Ok(res.rows_affected)
+}
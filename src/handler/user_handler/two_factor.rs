@@ -0,0 +1,94 @@
+use sea_orm::{
+    prelude::Uuid, sea_query::Expr, ActiveValue::Set, ColumnTrait, Condition, ConnectionTrait,
+    DbErr, EntityTrait, QueryFilter,
+};
+
+use crate::entity::{recovery_code, user};
+use crate::handler::utils::{generate_recovery_codes, hash_recovery_code, verify_totp};
+
+pub(super) enum SecondFactor {
+    Totp,
+    RecoveryCode,
+}
+
+// Checks a code of the authenticator app or an unused recovery code, the code is spent
+// once accepted
+pub(super) async fn check_second_factor<C: ConnectionTrait>(
+    db: &C,
+    user: &user::Model,
+    code: Option<&str>,
+    recovery_code: Option<&str>,
+) -> Result<Option<SecondFactor>, DbErr> {
+    let now = chrono::Utc::now();
+
+    if let (Some(code), Some(secret)) = (code, &user.totp_secret) {
+        let Some(step) = verify_totp(secret, code, now.timestamp()) else {
+            return Ok(None);
+        };
+
+        // Only a step newer than the last one used is accepted, even with concurrent logins
+        let result = user::Entity::update_many()
+            .col_expr(user::Column::TotpLastStep, Expr::value(step))
+            .filter(user::Column::Id.eq(user.id))
+            .filter(
+                Condition::any()
+                    .add(user::Column::TotpLastStep.is_null())
+                    .add(user::Column::TotpLastStep.lt(step)),
+            )
+            .exec(db)
+            .await?;
+
+        return Ok((result.rows_affected > 0).then_some(SecondFactor::Totp));
+    }
+
+    if let Some(recovery_code) = recovery_code {
+        let result = recovery_code::Entity::update_many()
+            .col_expr(recovery_code::Column::UsedAt, Expr::value(now.naive_utc()))
+            .filter(recovery_code::Column::UserId.eq(user.id))
+            .filter(recovery_code::Column::CodeHash.eq(hash_recovery_code(recovery_code)))
+            .filter(recovery_code::Column::UsedAt.is_null())
+            .exec(db)
+            .await?;
+
+        return Ok((result.rows_affected > 0).then_some(SecondFactor::RecoveryCode));
+    }
+
+    Ok(None)
+}
+
+// Previous codes stop working, the new ones are only returned this once
+pub(super) async fn replace_recovery_codes<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    count: usize,
+) -> Result<Vec<String>, DbErr> {
+    delete_recovery_codes(db, user_id).await?;
+
+    let codes = generate_recovery_codes(count);
+    if codes.is_empty() {
+        return Ok(codes);
+    }
+    let now = chrono::Utc::now().naive_utc();
+    let models = codes.iter().map(|code| recovery_code::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        code_hash: Set(hash_recovery_code(code)),
+        used_at: Set(None),
+        created_at: Set(now),
+    });
+    recovery_code::Entity::insert_many(models).exec(db).await?;
+
+    Ok(codes)
+}
+
+pub(super) async fn delete_recovery_codes<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+) -> Result<(), DbErr> {
+    recovery_code::Entity::delete_many()
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
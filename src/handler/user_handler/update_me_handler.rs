@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    query::Condition, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+};
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationErrors};
+
+use crate::configuration::EmailSettings;
+use crate::dto::user::User;
+use crate::entity::user;
+use crate::handler::helpers::{ApiResponse, ApiResponseData, ResponseError};
+use crate::handler::utils::{EmailTokenPurpose, UserId};
+use crate::mailer::SharedMailer;
+use crate::router::Secrets;
+
+use super::emails::send_email_token;
+
+// Same rules as on register, omitted fields are left unchanged
+#[derive(Debug, Validate, Deserialize)]
+pub struct UpdateMeInput {
+    #[validate(length(min = 6, max = 20))]
+    pub username: Option<String>,
+    #[validate(email)]
+    pub email: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct UpdateMeResponse {
+    pub user: User,
+}
+
+enum ApiError {
+    BadClientData(ValidationErrors),
+    UsernameTaken,
+    EmailTaken,
+    UserNotFound,
+    DbInternalError,
+}
+
+impl ApiError {
+    fn taken(
+        field: &str,
+        code: &'static str,
+        message: &'static str,
+    ) -> ApiResponseData<ResponseError> {
+        ApiResponseData::error(
+            Some(ResponseError {
+                code: Some(code),
+                fields: Some(HashMap::from([(
+                    field.to_owned(),
+                    "already taken".to_owned(),
+                )])),
+            }),
+            message,
+            StatusCode::BAD_REQUEST,
+        )
+    }
+}
+
+impl From<ApiError> for ApiResponseData<ResponseError> {
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::BadClientData(err) => ApiResponseData::error(
+                Some(ResponseError::from(err)),
+                "invalid data from client",
+                StatusCode::BAD_REQUEST,
+            ),
+            ApiError::UsernameTaken => {
+                ApiError::taken("username", "username_taken", "username already taken")
+            }
+            ApiError::EmailTaken => ApiError::taken("email", "email_taken", "email already taken"),
+            ApiError::UserNotFound => ApiResponseData::status_code(StatusCode::BAD_REQUEST),
+            ApiError::DbInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+// A new email has to be verified again
+#[tracing::instrument(skip(secrets, mailer))]
+pub async fn update_me_handler(
+    UserId(user_id): UserId,
+    State(db_connection): State<DatabaseConnection>,
+    State(secrets): State<Secrets>,
+    State(mailer): State<SharedMailer>,
+    State(email_settings): State<EmailSettings>,
+    Json(input): Json<UpdateMeInput>,
+) -> ApiResponse<UpdateMeResponse, ResponseError> {
+    input.validate().map_err(ApiError::BadClientData)?;
+
+    let current = user::Entity::find_by_id(user_id)
+        .one(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?
+        .ok_or(ApiError::UserNotFound)?;
+
+    let username = input
+        .username
+        .filter(|username| *username != current.username);
+    let email = input.email.filter(|email| *email != current.email);
+
+    // Deleted users keep their username and email, they can't be reused either
+    let mut taken = Condition::any();
+    if let Some(username) = &username {
+        taken = taken.add(user::Column::Username.eq(username.as_str()));
+    }
+    if let Some(email) = &email {
+        taken = taken.add(user::Column::Email.eq(email.as_str()));
+    }
+    if username.is_some() || email.is_some() {
+        let others = user::Entity::find()
+            .filter(taken)
+            .filter(user::Column::Id.ne(user_id))
+            .all(&db_connection)
+            .await
+            .map_err(|_| ApiError::DbInternalError)?;
+
+        if others
+            .iter()
+            .any(|other| Some(&other.username) == username.as_ref())
+        {
+            return Err(ApiError::UsernameTaken.into());
+        }
+        if !others.is_empty() {
+            return Err(ApiError::EmailTaken.into());
+        }
+    }
+
+    let email_changed = email.is_some();
+    let mut active_user: user::ActiveModel = current.clone().into();
+    if let Some(username) = username {
+        active_user.username = Set(username);
+    }
+    if let Some(email) = email {
+        active_user.email = Set(email);
+        active_user.email_verified_at = Set(None);
+    }
+
+    let user = if active_user.is_changed() {
+        active_user.updated_at = Set(Some(chrono::Utc::now().naive_utc()));
+        active_user
+            .update(&db_connection)
+            .await
+            .map_err(|_| ApiError::DbInternalError)?
+    } else {
+        current
+    };
+
+    if email_changed {
+        send_email_token(
+            mailer.as_ref(),
+            &email_settings,
+            &secrets,
+            EmailTokenPurpose::VerifyEmail,
+            &user,
+        )
+        .await;
+    }
+
+    let data = UpdateMeResponse { user: user.into() };
+
+    Ok(ApiResponseData::success_with_data(data, StatusCode::OK))
+}
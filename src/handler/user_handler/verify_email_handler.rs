@@ -0,0 +1,70 @@
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait};
+use serde::{Deserialize, Serialize};
+
+use crate::entity::user;
+use crate::handler::helpers::{ApiResponse, ApiResponseData};
+use crate::handler::utils::{decode_email_token, is_email_token_current, EmailTokenPurpose};
+use crate::router::Secrets;
+
+#[derive(Deserialize)]
+pub struct VerifyEmailInput {
+    pub token: String,
+}
+
+enum ApiError {
+    InvalidToken,
+    DbInternalError,
+}
+
+impl<E> From<ApiError> for ApiResponseData<E>
+where
+    E: Serialize + 'static,
+{
+    fn from(value: ApiError) -> Self {
+        match value {
+            ApiError::InvalidToken => {
+                ApiResponseData::error(None, "invalid or expired token", StatusCode::BAD_REQUEST)
+            }
+            ApiError::DbInternalError => {
+                ApiResponseData::status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+#[tracing::instrument(skip(secrets, input))]
+pub async fn verify_email_handler(
+    State(db_connection): State<DatabaseConnection>,
+    State(secrets): State<Secrets>,
+    Json(input): Json<VerifyEmailInput>,
+) -> ApiResponse<(), ()> {
+    let purpose = EmailTokenPurpose::VerifyEmail;
+    let (user_id, claims) =
+        decode_email_token(secrets.jwt_secret.as_bytes(), purpose, &input.token)
+            .ok_or(ApiError::InvalidToken)?;
+
+    let user = user::Entity::find_by_id(user_id)
+        .one(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?
+        .ok_or(ApiError::InvalidToken)?;
+
+    // A verification token is only good once
+    if user.deleted_at.is_some()
+        || user.email_verified_at.is_some()
+        || !is_email_token_current(purpose, &claims, &user)
+    {
+        return Err(ApiError::InvalidToken.into());
+    }
+
+    let mut user: user::ActiveModel = user.into();
+    user.email_verified_at = Set(Some(chrono::Utc::now().naive_utc()));
+    user.update(&db_connection)
+        .await
+        .map_err(|_| ApiError::DbInternalError)?;
+
+    Ok(ApiResponseData::status_code(StatusCode::OK))
+}
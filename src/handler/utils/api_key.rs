@@ -0,0 +1,143 @@
+use std::str::FromStr;
+
+use axum::http::Method;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use validator::ValidationError;
+
+// Keys look like `dnl_<prefix>_<secret>`, the prefix is stored in clear to find the key back
+pub const API_KEY_PREFIX: &str = "dnl_";
+const API_KEY_ID_LENGTH: usize = 8;
+const API_KEY_SECRET_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiScope {
+    #[serde(rename = "links:read")]
+    LinksRead,
+    #[serde(rename = "links:write")]
+    LinksWrite,
+}
+
+impl ApiScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::LinksRead => "links:read",
+            ApiScope::LinksWrite => "links:write",
+        }
+    }
+
+    // Scopes are stored space separated
+    pub fn parse_list(scopes: &str) -> Vec<ApiScope> {
+        scopes
+            .split_whitespace()
+            .filter_map(|scope| scope.parse().ok())
+            .collect()
+    }
+}
+
+impl FromStr for ApiScope {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "links:read" => Ok(ApiScope::LinksRead),
+            "links:write" => Ok(ApiScope::LinksWrite),
+            _ => Err(()),
+        }
+    }
+}
+
+pub fn validate_scopes(scopes: &[String]) -> Result<(), ValidationError> {
+    if scopes.is_empty()
+        || scopes
+            .iter()
+            .any(|scope| scope.parse::<ApiScope>().is_err())
+    {
+        return Err(ValidationError::new("scopes"));
+    }
+
+    Ok(())
+}
+
+// Scope needed to call an endpoint with an API key, keys can't reach the other endpoints
+pub fn required_scope(method: &Method, path: &str) -> Option<ApiScope> {
+    if path != "/api/links" && !path.starts_with("/api/links/") {
+        return None;
+    }
+
+    match *method {
+        Method::GET | Method::HEAD => Some(ApiScope::LinksRead),
+        _ => Some(ApiScope::LinksWrite),
+    }
+}
+
+pub struct GeneratedApiKey {
+    pub prefix: String,
+    pub key: String,
+}
+
+fn random_string(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+pub fn generate_api_key() -> GeneratedApiKey {
+    let prefix = format!("{API_KEY_PREFIX}{}", random_string(API_KEY_ID_LENGTH));
+    let key = format!("{prefix}_{}", random_string(API_KEY_SECRET_LENGTH));
+
+    GeneratedApiKey { prefix, key }
+}
+
+pub fn api_key_prefix(key: &str) -> Option<&str> {
+    let prefix_length = API_KEY_PREFIX.len() + API_KEY_ID_LENGTH;
+
+    match key.get(..prefix_length) {
+        Some(prefix)
+            if prefix.starts_with(API_KEY_PREFIX) && key[prefix_length..].starts_with('_') =>
+        {
+            Some(prefix)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generated_key_starts_with_its_prefix() {
+        let generated = generate_api_key();
+
+        assert_eq!(
+            api_key_prefix(&generated.key),
+            Some(generated.prefix.as_str())
+        );
+        assert_eq!(api_key_prefix("dnl_short"), None);
+        assert_eq!(api_key_prefix("eyJhbGciOiJIUzI1NiJ9.e30.sig"), None);
+    }
+
+    #[test]
+    fn links_endpoints_require_a_scope() {
+        assert_eq!(
+            required_scope(&Method::GET, "/api/links"),
+            Some(ApiScope::LinksRead)
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/api/links"),
+            Some(ApiScope::LinksWrite)
+        );
+        assert_eq!(
+            required_scope(
+                &Method::DELETE,
+                "/api/links/7d1c5b9e-3c4a-4f25-9a4e-0b1a2c3d4e5f"
+            ),
+            Some(ApiScope::LinksWrite)
+        );
+        assert_eq!(required_scope(&Method::GET, "/api/linksfoo"), None);
+        assert_eq!(required_scope(&Method::GET, "/api/user/api-keys"), None);
+    }
+}
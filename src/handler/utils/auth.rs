@@ -1,24 +1,41 @@
 use std::str::FromStr;
 
-use crate::entity::user::Entity as User;
+use crate::{
+    dto::user::Claims,
+    entity::{api_key, revoked_token, session, user::Entity as User},
+};
 use axum::{
     async_trait,
-    extract::{FromRef, FromRequestParts, State},
+    extract::{FromRef, FromRequestParts, OriginalUri, State},
     headers::{authorization::Bearer, Authorization},
     http::{request::Parts, StatusCode},
     response::{IntoResponse, Response},
     RequestPartsExt, TypedHeader,
 };
-use sea_orm::{prelude::Uuid, EntityTrait};
+use chrono::Duration;
+use sea_orm::{
+    prelude::Uuid, sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+};
 
 use crate::router::AppState;
 
-use super::decode_jwt;
+use super::{api_key_prefix, decode_jwt, required_scope, sha256_hex, ApiScope, API_KEY_PREFIX};
+
+const SESSION_TOUCH_INTERVAL_MINUTES: i64 = 5;
+const API_KEY_TOUCH_INTERVAL_MINUTES: i64 = 5;
 
 pub struct UserId(pub Uuid);
 
+// The access token a request was authenticated with, for handlers that act on the token itself
+pub struct AccessToken {
+    pub user_id: Uuid,
+    pub session_id: Uuid,
+    pub jti: Uuid,
+    pub claims: Claims,
+}
+
 #[async_trait]
-impl<S> FromRequestParts<S> for UserId
+impl<S> FromRequestParts<S> for AccessToken
 where
     S: Send + Sync,
     AppState: FromRef<S>,
@@ -38,40 +55,163 @@ where
             .map_err(|_| AuthError::InternalError)?;
 
         // Decode the user data
-        let token_data = decode_jwt(state.secrets.jwt_secret.as_bytes(), bearer.token())
+        let claims = decode_jwt(state.secrets.jwt_secret.as_bytes(), bearer.token())
             .map_err(|_| AuthError::InvalidToken)?;
 
-        let user_id = Uuid::from_str(&token_data.sub).map_err(|_| AuthError::InvalidToken)?;
+        let user_id = Uuid::from_str(&claims.sub).map_err(|_| AuthError::InvalidToken)?;
+        let jti = Uuid::from_str(&claims.jti).map_err(|_| AuthError::InvalidToken)?;
+        let session_id = Uuid::from_str(&claims.sid).map_err(|_| AuthError::InvalidToken)?;
+
+        // Tokens are revoked on logout and when a refresh token is reused
+        let revoked = revoked_token::Entity::find_by_id(jti)
+            .one(&state.db_connection)
+            .await
+            .map_err(|_| AuthError::InternalError)?;
+        if revoked.is_some() {
+            return Err(AuthError::RevokedToken);
+        }
+
+        // Signing out a session, or everywhere, revokes the access tokens issued for it
+        let session = session::Entity::find_by_id(session_id)
+            .one(&state.db_connection)
+            .await
+            .map_err(|_| AuthError::InternalError)?;
+        let session = match session {
+            Some(session) if session.user_id == user_id && session.revoked_at.is_none() => session,
+            _ => return Err(AuthError::RevokedToken),
+        };
+        touch_session(&state.db_connection, &session).await?;
 
         let user = User::find_by_id(user_id)
             .one(&state.db_connection)
             .await
             .map_err(|_| AuthError::InternalError)?;
+        // Deleted accounts have their sessions revoked, this covers tokens checked in between
         match user {
-            Some(value) => Ok(UserId(value.id)),
-            None => Err(AuthError::WrongCredentials),
+            Some(value) if value.deleted_at.is_none() => Ok(AccessToken {
+                user_id: value.id,
+                session_id,
+                jti,
+                claims,
+            }),
+            _ => Err(AuthError::WrongCredentials),
         }
     }
 }
 
+// Last use is only tracked to within a few minutes, sparing a write on every request
+async fn touch_session(
+    db: &DatabaseConnection,
+    session: &session::Model,
+) -> Result<(), AuthError> {
+    let now = chrono::Utc::now().naive_utc();
+    if now - session.last_used_at < Duration::minutes(SESSION_TOUCH_INTERVAL_MINUTES) {
+        return Ok(());
+    }
+
+    session::Entity::update_many()
+        .col_expr(session::Column::LastUsedAt, Expr::value(now))
+        .filter(session::Column::Id.eq(session.id))
+        .exec(db)
+        .await
+        .map_err(|_| AuthError::InternalError)?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for UserId
+where
+    S: Send + Sync,
+    AppState: FromRef<S>,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) = parts
+            .extract::<TypedHeader<Authorization<Bearer>>>()
+            .await
+            .map_err(|_| AuthError::InvalidToken)?;
+
+        // Personal api keys are accepted wherever a user id is enough
+        if bearer.token().starts_with(API_KEY_PREFIX) {
+            let State(state) = parts
+                .extract_with_state::<State<AppState>, S>(state)
+                .await
+                .map_err(|_| AuthError::InternalError)?;
+
+            return authenticate_api_key(&state.db_connection, parts, bearer.token()).await;
+        }
+
+        let token = AccessToken::from_request_parts(parts, state).await?;
+
+        Ok(UserId(token.user_id))
+    }
+}
+
+async fn authenticate_api_key(
+    db: &DatabaseConnection,
+    parts: &Parts,
+    key: &str,
+) -> Result<UserId, AuthError> {
+    let prefix = api_key_prefix(key).ok_or(AuthError::WrongCredentials)?;
+
+    let api_key = api_key::Entity::find()
+        .filter(api_key::Column::Prefix.eq(prefix))
+        .one(db)
+        .await
+        .map_err(|_| AuthError::InternalError)?
+        .ok_or(AuthError::WrongCredentials)?;
+    if api_key.key_hash != sha256_hex(key) {
+        return Err(AuthError::WrongCredentials);
+    }
+
+    // Nested routers strip their prefix from the uri, the original one holds the full path
+    let path = match parts.extensions.get::<OriginalUri>() {
+        Some(OriginalUri(uri)) => uri.path(),
+        None => parts.uri.path(),
+    };
+    let scope = required_scope(&parts.method, path).ok_or(AuthError::InsufficientScope)?;
+    if !ApiScope::parse_list(&api_key.scopes).contains(&scope) {
+        return Err(AuthError::InsufficientScope);
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let stale = api_key.last_used_at.is_none_or(|last_used_at| {
+        now - last_used_at >= Duration::minutes(API_KEY_TOUCH_INTERVAL_MINUTES)
+    });
+    if stale {
+        api_key::Entity::update_many()
+            .col_expr(api_key::Column::LastUsedAt, Expr::value(now))
+            .filter(api_key::Column::Id.eq(api_key.id))
+            .exec(db)
+            .await
+            .map_err(|_| AuthError::InternalError)?;
+    }
+
+    Ok(UserId(api_key.user_id))
+}
+
 #[derive(Debug)]
 pub enum AuthError {
     WrongCredentials,
     MissingCredentials,
     TokenCreation,
     InvalidToken,
+    RevokedToken,
+    InsufficientScope,
     InternalError,
 }
 
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
         let status = match self {
-            AuthError::WrongCredentials => StatusCode::UNAUTHORIZED,
+            AuthError::WrongCredentials | AuthError::RevokedToken => StatusCode::UNAUTHORIZED,
             AuthError::MissingCredentials => StatusCode::BAD_REQUEST,
             AuthError::TokenCreation | AuthError::InternalError => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
-            AuthError::InvalidToken => StatusCode::FORBIDDEN,
+            AuthError::InvalidToken | AuthError::InsufficientScope => StatusCode::FORBIDDEN,
         };
         status.into_response()
     }
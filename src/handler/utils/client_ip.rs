@@ -0,0 +1,107 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::{header, request::Parts, StatusCode},
+};
+
+use crate::configuration::ProxySettings;
+
+// Address of the client, taken from the proxy headers when the app is deployed behind a
+// trusted proxy
+pub struct ClientIp(pub Option<IpAddr>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+    ProxySettings: FromRef<S>,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let peer_ip = peer_ip(parts);
+
+        let proxy = ProxySettings::from_ref(state);
+        let ip = match peer_ip {
+            Some(peer_ip) if proxy.is_trusted(peer_ip) => {
+                forwarded_ip(parts, &proxy).or(Some(peer_ip))
+            }
+            _ => peer_ip,
+        };
+
+        Ok(ClientIp(ip))
+    }
+}
+
+// Scheme and host the client reached the app on. Like the client address, the forwarded ones
+// are only read from a trusted proxy, anyone else could point them at another site.
+pub struct RequestOrigin(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for RequestOrigin
+where
+    S: Send + Sync,
+    ProxySettings: FromRef<S>,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let proxy = ProxySettings::from_ref(state);
+        let behind_proxy = peer_ip(parts).is_some_and(|ip| proxy.is_trusted(ip));
+
+        // The last value is the one our proxy set
+        let forwarded = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.rsplit(',').next())
+                .map(str::trim)
+                .filter(|_| behind_proxy)
+        };
+
+        let scheme = forwarded("x-forwarded-proto").unwrap_or("http");
+        let host = forwarded("x-forwarded-host")
+            .or_else(|| {
+                parts
+                    .headers
+                    .get(header::HOST)
+                    .and_then(|value| value.to_str().ok())
+            })
+            .or_else(|| parts.uri.authority().map(|authority| authority.as_str()))
+            .ok_or((StatusCode::BAD_REQUEST, "No host found in request"))?;
+
+        Ok(RequestOrigin(format!("{scheme}://{host}")))
+    }
+}
+
+fn peer_ip(parts: &Parts) -> Option<IpAddr> {
+    parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+}
+
+// Each proxy appends the address it got the request from, so the right-most hop that isn't
+// one of ours is the client. Anything left of it was sent by the client and can't be trusted.
+fn forwarded_ip(parts: &Parts, proxy: &ProxySettings) -> Option<IpAddr> {
+    let hops: Vec<&str> = parts
+        .headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+
+    for hop in hops.into_iter().rev() {
+        match hop.trim().parse::<IpAddr>() {
+            Ok(ip) if proxy.is_trusted(ip) => continue,
+            Ok(ip) => return Some(ip),
+            Err(_) => return None,
+        }
+    }
+
+    None
+}
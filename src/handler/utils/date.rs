@@ -0,0 +1,25 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use validator::{ValidationError, ValidationErrors};
+
+pub fn validate_future_date(date: &DateTime<Utc>) -> Result<(), ValidationError> {
+    if *date <= Utc::now() {
+        return Err(ValidationError::new("past_date"));
+    }
+
+    Ok(())
+}
+
+// A link can't go live after it has already expired
+pub fn validate_active_window(
+    active_from: Option<NaiveDateTime>,
+    expires_at: Option<NaiveDateTime>,
+) -> Result<(), ValidationErrors> {
+    match (active_from, expires_at) {
+        (Some(active_from), Some(expires_at)) if active_from >= expires_at => {
+            let mut errors = ValidationErrors::new();
+            errors.add("active_from", ValidationError::new("after_expiration"));
+            Err(errors)
+        }
+        _ => Ok(()),
+    }
+}
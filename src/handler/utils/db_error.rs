@@ -0,0 +1,18 @@
+use sea_orm::{DbErr, RuntimeErr};
+
+const UNIQUE_VIOLATION_CODE: &str = "23505";
+
+// Name of the unique constraint a failed insert or update ran into, if any
+pub fn unique_violation(err: &DbErr) -> Option<String> {
+    let (DbErr::Exec(RuntimeErr::SqlxError(err)) | DbErr::Query(RuntimeErr::SqlxError(err))) = err
+    else {
+        return None;
+    };
+    let db_err = err.as_database_error()?;
+
+    if db_err.code()? != UNIQUE_VIOLATION_CODE {
+        return None;
+    }
+
+    Some(db_err.constraint().unwrap_or_default().to_owned())
+}
@@ -0,0 +1,82 @@
+use chrono::Duration;
+use jsonwebtoken::{decode, errors, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use sea_orm::prelude::Uuid;
+use std::str::FromStr;
+
+use crate::{dto::user::EmailTokenClaims, entity::user};
+
+use super::sha256_hex;
+
+const FINGERPRINT_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, Copy)]
+pub enum EmailTokenPurpose {
+    VerifyEmail,
+    ResetPassword,
+}
+
+impl EmailTokenPurpose {
+    fn audience(&self) -> &'static str {
+        match self {
+            EmailTokenPurpose::VerifyEmail => "verify_email",
+            EmailTokenPurpose::ResetPassword => "reset_password",
+        }
+    }
+
+    // Verifying ties the token to the email, resetting to the current password, so
+    // a token stops working once it has been used
+    fn fingerprint(&self, user: &user::Model) -> String {
+        let source = match self {
+            EmailTokenPurpose::VerifyEmail => user.email.as_str(),
+            EmailTokenPurpose::ResetPassword => user.password_hash.as_deref().unwrap_or_default(),
+        };
+
+        sha256_hex(source)[..FINGERPRINT_LENGTH].to_owned()
+    }
+}
+
+pub fn encode_email_token(
+    secret: &[u8],
+    purpose: EmailTokenPurpose,
+    user: &user::Model,
+    ttl: Duration,
+) -> errors::Result<String> {
+    let claims = EmailTokenClaims {
+        sub: user.id.to_string(),
+        aud: purpose.audience().into(),
+        fp: purpose.fingerprint(user),
+        exp: (chrono::Utc::now() + ttl).timestamp(),
+    };
+
+    jsonwebtoken::encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret),
+    )
+}
+
+// The id of the user the token was issued to, its fingerprint is checked with
+// `is_email_token_current` once the user is loaded
+pub fn decode_email_token(
+    secret: &[u8],
+    purpose: EmailTokenPurpose,
+    token: &str,
+) -> Option<(Uuid, EmailTokenClaims)> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_audience(&[purpose.audience()]);
+
+    let claims = decode::<EmailTokenClaims>(token, &DecodingKey::from_secret(secret), &validation)
+        .ok()?
+        .claims;
+    let user_id = Uuid::from_str(&claims.sub).ok()?;
+
+    Some((user_id, claims))
+}
+
+pub fn is_email_token_current(
+    purpose: EmailTokenPurpose,
+    claims: &EmailTokenClaims,
+    user: &user::Model,
+) -> bool {
+    claims.sub == user.id.to_string() && claims.fp == purpose.fingerprint(user)
+}
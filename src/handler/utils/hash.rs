@@ -4,72 +4,160 @@ use argon2::{
     },
     Algorithm, Argon2, Params, Version,
 };
-pub fn hash_password(secret: &[u8], password: &[u8]) -> Result<String> {
-    let salt = SaltString::generate(&mut OsRng);
+use sha2::{Digest, Sha256};
 
-    // Argon2 with default params (Argon2id v19)
-    let argon2 = Argon2::new_with_secret(
-        secret,
-        Algorithm::default(),
-        Version::default(),
-        Params::default(),
-    )?;
+use crate::configuration::PasswordHashingSettings;
 
-    // Hash password to PHC string ($argon2id$v=19$...)
+// Fast hash for high entropy secrets (refresh tokens, api keys), passwords go through argon2
+pub fn sha256_hex(value: &str) -> String {
+    Sha256::digest(value.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+// Argon2 is slow on purpose, it runs on the blocking pool so it doesn't hold up the other
+// requests served by the executor. Other CPU heavy work goes through it too.
+pub(crate) async fn run_blocking<T, F>(task: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    match tokio::task::spawn_blocking(task).await {
+        Ok(value) => value,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
+
+pub async fn hash_password(
+    secret: &[u8],
+    settings: &PasswordHashingSettings,
+    password: &[u8],
+) -> Result<String> {
+    let params = settings.params()?;
+    let (secret, password) = (secret.to_vec(), password.to_vec());
+
+    run_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
 
-    Ok(argon2.hash_password(password, &salt)?.to_string())
+        // Argon2id v19 with the configured params
+        let argon2 = Argon2::new_with_secret(&secret, Algorithm::Argon2id, Version::V0x13, params)?;
+
+        // Hash password to PHC string ($argon2id$v=19$...)
+        Ok(argon2.hash_password(&password, &salt)?.to_string())
+    })
+    .await
 }
 
-pub fn verify_password(secret: &[u8], password: &[u8], password_hash: &str) -> Result<bool> {
-    // Argon2 with default params (Argon2id v19)
-    let argon2 = Argon2::new_with_secret(
-        secret,
-        Algorithm::default(),
-        Version::default(),
-        Params::default(),
-    )?;
-
-    // Verify password against PHC string.
-    //
-    // NOTE: hash params from `parsed_hash` are used instead of what is configured in the
-    // `Argon2` instance.
-    let parsed_hash = PasswordHash::new(password_hash)?;
-
-    Ok(argon2.verify_password(password, &parsed_hash).is_ok())
+pub async fn verify_password(secret: &[u8], password: &[u8], password_hash: &str) -> Result<bool> {
+    let (secret, password, password_hash) =
+        (secret.to_vec(), password.to_vec(), password_hash.to_owned());
+
+    run_blocking(move || {
+        let argon2 = Argon2::new_with_secret(
+            &secret,
+            Algorithm::default(),
+            Version::default(),
+            Params::default(),
+        )?;
+
+        // Verify password against PHC string.
+        //
+        // NOTE: hash params from `parsed_hash` are used instead of what is configured in the
+        // `Argon2` instance.
+        let parsed_hash = PasswordHash::new(&password_hash)?;
+
+        Ok(argon2.verify_password(&password, &parsed_hash).is_ok())
+    })
+    .await
+}
+
+// Whether the hash was made with another algorithm or a lower cost than the configured one.
+// Lowering the cost doesn't downgrade existing hashes.
+pub fn needs_rehash(password_hash: &str, settings: &PasswordHashingSettings) -> bool {
+    let Ok(parsed_hash) = PasswordHash::new(password_hash) else {
+        return false;
+    };
+    let Ok(params) = Params::try_from(&parsed_hash) else {
+        return true;
+    };
+
+    parsed_hash.algorithm != Algorithm::Argon2id.ident()
+        || parsed_hash.version != Some(Version::V0x13.into())
+        || params.m_cost() < settings.memory_kib
+        || params.t_cost() < settings.iterations
+        || params.p_cost() < settings.parallelism
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
-    #[test]
-    fn hash_and_verify_correct_password() {
+    #[tokio::test]
+    async fn hash_and_verify_correct_password() {
         let password = "it_should_work";
         let secret = "secret";
 
-        let hashed_password = hash_password(secret.as_bytes(), password.as_bytes()).unwrap();
+        let hashed_password = hash_password(
+            secret.as_bytes(),
+            &PasswordHashingSettings::default(),
+            password.as_bytes(),
+        )
+        .await
+        .unwrap();
 
         assert!(verify_password(
             secret.as_bytes(),
             password.as_bytes(),
             hashed_password.as_str()
         )
+        .await
         .unwrap())
     }
 
-    #[test]
-    fn hash_and_verify_wrong_password() {
+    #[tokio::test]
+    async fn hash_and_verify_wrong_password() {
         let password = "it_should_not_work";
         let secret = "secret";
 
-        let hashed_password = hash_password(password.as_bytes(), secret.as_bytes()).unwrap();
+        let hashed_password = hash_password(
+            password.as_bytes(),
+            &PasswordHashingSettings::default(),
+            secret.as_bytes(),
+        )
+        .await
+        .unwrap();
 
         assert!(!verify_password(
             secret.as_bytes(),
             "wrong_password".as_bytes(),
             hashed_password.as_str()
         )
+        .await
         .unwrap())
     }
-}
 
+    #[tokio::test]
+    async fn weaker_hashes_need_a_rehash() {
+        let settings = PasswordHashingSettings::default();
+        let hashed_password = hash_password(b"secret", &settings, b"password")
+            .await
+            .unwrap();
+
+        assert!(!needs_rehash(&hashed_password, &settings));
+        assert!(needs_rehash(
+            &hashed_password,
+            &PasswordHashingSettings {
+                iterations: settings.iterations + 1,
+                ..settings.clone()
+            }
+        ));
+        assert!(!needs_rehash(
+            &hashed_password,
+            &PasswordHashingSettings {
+                memory_kib: settings.memory_kib / 2,
+                ..settings
+            }
+        ));
+    }
+}
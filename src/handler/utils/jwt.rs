@@ -4,13 +4,20 @@ use sea_orm::prelude::Uuid;
 
 use crate::dto::user::Claims;
 
-pub fn encode_jwt(secret: &[u8], user_id: &Uuid) -> errors::Result<String> {
+pub fn encode_jwt(
+    secret: &[u8],
+    user_id: &Uuid,
+    session_id: &Uuid,
+    ttl: Duration,
+) -> errors::Result<String> {
     let now = chrono::Utc::now();
 
     let claims = Claims {
         sub: user_id.to_string(),
+        jti: Uuid::new_v4().to_string(),
+        sid: session_id.to_string(),
         iat: now.timestamp(),
-        exp: (now + Duration::hours(4)).timestamp(),
+        exp: (now + ttl).timestamp(),
     };
 
     jsonwebtoken::encode(
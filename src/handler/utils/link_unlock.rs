@@ -0,0 +1,72 @@
+use axum::{
+    headers::{Cookie, HeaderMapExt},
+    http::HeaderMap,
+};
+use chrono::Duration;
+use jsonwebtoken::{decode, errors, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use sha2::{Digest, Sha256};
+
+use crate::{dto::url::LinkUnlockClaims, entity::url};
+
+pub const LINK_UNLOCK_COOKIE: &str = "link_unlock";
+const LINK_UNLOCK_AUDIENCE: &str = "link_unlock";
+
+// Changing the password of a link invalidates the cookies issued for the previous one
+fn password_fingerprint(password_hash: &str) -> String {
+    let digest = Sha256::digest(password_hash.as_bytes());
+
+    digest[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+pub fn encode_link_unlock(
+    secret: &[u8],
+    link: &url::Model,
+    password_hash: &str,
+    ttl: Duration,
+) -> errors::Result<String> {
+    let claims = LinkUnlockClaims {
+        sub: link.id.to_string(),
+        aud: LINK_UNLOCK_AUDIENCE.into(),
+        fp: password_fingerprint(password_hash),
+        exp: (chrono::Utc::now() + ttl).timestamp(),
+    };
+
+    jsonwebtoken::encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret),
+    )
+}
+
+pub fn is_link_unlocked(secret: &[u8], link: &url::Model, headers: &HeaderMap) -> bool {
+    let Some(password_hash) = &link.password_hash else {
+        return true;
+    };
+    let Some(token) = headers
+        .typed_get::<Cookie>()
+        .and_then(|cookie| cookie.get(LINK_UNLOCK_COOKIE).map(str::to_owned))
+    else {
+        return false;
+    };
+
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_audience(&[LINK_UNLOCK_AUDIENCE]);
+
+    decode::<LinkUnlockClaims>(&token, &DecodingKey::from_secret(secret), &validation)
+        .map(|data| {
+            data.claims.sub == link.id.to_string()
+                && data.claims.fp == password_fingerprint(password_hash)
+        })
+        .unwrap_or(false)
+}
+
+// The cookie is only sent back on the path of the link it unlocks
+pub fn link_unlock_cookie(slug: &str, token: &str, ttl: Duration) -> String {
+    format!(
+        "{LINK_UNLOCK_COOKIE}={token}; Path=/{slug}; Max-Age={}; HttpOnly; SameSite=Lax",
+        ttl.num_seconds()
+    )
+}
@@ -0,0 +1,40 @@
+use chrono::Duration;
+use jsonwebtoken::{decode, errors, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use sea_orm::prelude::Uuid;
+use std::str::FromStr;
+
+use crate::dto::user::LoginChallengeClaims;
+
+const LOGIN_CHALLENGE_AUDIENCE: &str = "login_challenge";
+
+// Proves the password of the user was checked, the login completes once a second factor
+// is given along with it
+pub fn encode_login_challenge(
+    secret: &[u8],
+    user_id: Uuid,
+    ttl: Duration,
+) -> errors::Result<String> {
+    let claims = LoginChallengeClaims {
+        sub: user_id.to_string(),
+        aud: LOGIN_CHALLENGE_AUDIENCE.into(),
+        exp: (chrono::Utc::now() + ttl).timestamp(),
+    };
+
+    jsonwebtoken::encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret),
+    )
+}
+
+pub fn decode_login_challenge(secret: &[u8], token: &str) -> Option<Uuid> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_audience(&[LOGIN_CHALLENGE_AUDIENCE]);
+
+    let claims =
+        decode::<LoginChallengeClaims>(token, &DecodingKey::from_secret(secret), &validation)
+            .ok()?
+            .claims;
+
+    Uuid::from_str(&claims.sub).ok()
+}
@@ -1,7 +1,35 @@
+mod api_key;
 mod auth;
+mod client_ip;
+mod date;
+mod db_error;
+mod email_token;
 mod hash;
 mod jwt;
+mod link_unlock;
+mod login_challenge;
+mod nullable;
+mod oidc_login;
+mod qr_code;
+mod refresh_token;
+mod slug;
+mod tag;
+mod totp;
 
+pub use api_key::*;
 pub use auth::*;
+pub use client_ip::*;
+pub use date::*;
+pub use db_error::*;
+pub use email_token::*;
 pub use hash::*;
 pub use jwt::*;
+pub use link_unlock::*;
+pub use login_challenge::*;
+pub use nullable::*;
+pub use oidc_login::*;
+pub use qr_code::*;
+pub use refresh_token::*;
+pub use slug::*;
+pub use tag::*;
+pub use totp::*;
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Deserializer};
+
+// Tells a missing field (`None`) apart from an explicit null (`Some(None)`) in partial updates,
+// to be used along with `#[serde(default)]`
+pub fn deserialize_nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,63 @@
+use axum::{
+    headers::{Cookie, HeaderMapExt},
+    http::HeaderMap,
+};
+use chrono::Duration;
+use jsonwebtoken::{decode, errors, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+
+use crate::dto::user::OidcLoginClaims;
+
+pub const OIDC_LOGIN_COOKIE: &str = "oidc_login";
+const OIDC_LOGIN_AUDIENCE: &str = "oidc_login";
+const OIDC_LOGIN_PATH: &str = "/api/user/oidc";
+
+pub fn encode_oidc_login(
+    secret: &[u8],
+    state: &str,
+    nonce: &str,
+    code_verifier: &str,
+    ttl: Duration,
+) -> errors::Result<String> {
+    let claims = OidcLoginClaims {
+        aud: OIDC_LOGIN_AUDIENCE.into(),
+        state: state.into(),
+        nonce: nonce.into(),
+        code_verifier: code_verifier.into(),
+        exp: (chrono::Utc::now() + ttl).timestamp(),
+    };
+
+    jsonwebtoken::encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret),
+    )
+}
+
+pub fn decode_oidc_login(secret: &[u8], headers: &HeaderMap) -> Option<OidcLoginClaims> {
+    let token = headers
+        .typed_get::<Cookie>()
+        .and_then(|cookie| cookie.get(OIDC_LOGIN_COOKIE).map(str::to_owned))?;
+
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_audience(&[OIDC_LOGIN_AUDIENCE]);
+
+    decode::<OidcLoginClaims>(&token, &DecodingKey::from_secret(secret), &validation)
+        .map(|data| data.claims)
+        .ok()
+}
+
+// Lax so the cookie comes along when the provider redirects the user back, and secure
+// because it carries the pkce verifier
+pub fn oidc_login_cookie(token: &str, ttl: Duration) -> String {
+    format!(
+        "{OIDC_LOGIN_COOKIE}={token}; Path={OIDC_LOGIN_PATH}; Max-Age={}; HttpOnly; Secure; SameSite=Lax",
+        ttl.num_seconds()
+    )
+}
+
+// A login state is only good for one callback
+pub fn clear_oidc_login_cookie() -> String {
+    format!(
+        "{OIDC_LOGIN_COOKIE}=; Path={OIDC_LOGIN_PATH}; Max-Age=0; HttpOnly; Secure; SameSite=Lax"
+    )
+}
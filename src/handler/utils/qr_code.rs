@@ -0,0 +1,172 @@
+use std::{fmt::Write, str::FromStr};
+
+use qrcode::{
+    types::{Color, QrError},
+    EcLevel, QrCode,
+};
+use validator::ValidationError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+
+    fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+// Hex colors, with or without the leading #
+impl FromStr for Rgb {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let hex = value.strip_prefix('#').unwrap_or(value);
+        if hex.len() != 6 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(());
+        }
+
+        let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).map_err(|_| ());
+
+        Ok(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+pub fn validate_color(color: &str) -> Result<(), ValidationError> {
+    Rgb::from_str(color)
+        .map(|_| ())
+        .map_err(|_| ValidationError::new("color"))
+}
+
+pub struct QrStyle {
+    // Requested width of the image in pixels, modules are drawn with a whole number of pixels
+    pub size: u32,
+    // Quiet zone around the code, in modules
+    pub margin: u32,
+    pub foreground: Rgb,
+    pub background: Rgb,
+}
+
+pub struct QrMatrix {
+    width: u32,
+    dark: Vec<bool>,
+}
+
+impl QrMatrix {
+    pub fn encode(data: &str, ec_level: EcLevel) -> Result<Self, QrError> {
+        let code = QrCode::with_error_correction_level(data, ec_level)?;
+
+        Ok(Self {
+            width: code.width() as u32,
+            dark: code
+                .to_colors()
+                .into_iter()
+                .map(|color| color == Color::Dark)
+                .collect(),
+        })
+    }
+
+    fn is_dark(&self, x: u32, y: u32) -> bool {
+        self.dark[(y * self.width + x) as usize]
+    }
+
+    pub fn to_svg(&self, style: &QrStyle) -> String {
+        let total = self.width + 2 * style.margin;
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{size}" height="{size}" viewBox="0 0 {total} {total}" shape-rendering="crispEdges"><rect width="{total}" height="{total}" fill="{background}"/><path fill="{foreground}" d=""#,
+            size = style.size,
+            background = style.background.to_hex(),
+            foreground = style.foreground.to_hex(),
+        );
+
+        for y in 0..self.width {
+            for x in 0..self.width {
+                if self.is_dark(x, y) {
+                    let _ = write!(svg, "M{},{}h1v1h-1z", x + style.margin, y + style.margin);
+                }
+            }
+        }
+        svg.push_str(r#""/></svg>"#);
+
+        svg
+    }
+
+    pub fn to_png(&self, style: &QrStyle) -> Result<Vec<u8>, png::EncodingError> {
+        let total = self.width + 2 * style.margin;
+        let scale = (style.size / total).max(1);
+        let side = total * scale;
+
+        let mut pixels = Vec::with_capacity((side * side * 3) as usize);
+        for row in 0..side {
+            for column in 0..side {
+                let (x, y) = (column / scale, row / scale);
+                let inside = (style.margin..style.margin + self.width).contains(&x)
+                    && (style.margin..style.margin + self.width).contains(&y);
+                let color = if inside && self.is_dark(x - style.margin, y - style.margin) {
+                    style.foreground
+                } else {
+                    style.background
+                };
+                pixels.extend_from_slice(&[color.0, color.1, color.2]);
+            }
+        }
+
+        let mut image = Vec::new();
+        let mut encoder = png::Encoder::new(&mut image, side, side);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&pixels)?;
+        writer.finish()?;
+
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn style() -> QrStyle {
+        QrStyle {
+            size: 100,
+            margin: 4,
+            foreground: Rgb::BLACK,
+            background: Rgb::WHITE,
+        }
+    }
+
+    #[test]
+    fn colors_are_parsed_from_hex() {
+        assert_eq!(Rgb::from_str("#ff8000"), Ok(Rgb(255, 128, 0)));
+        assert_eq!(Rgb::from_str("00FF7f"), Ok(Rgb(0, 255, 127)));
+        assert!(Rgb::from_str("#fff").is_err());
+        assert!(Rgb::from_str("#gg0000").is_err());
+    }
+
+    #[test]
+    fn svg_covers_the_code_and_its_margin() {
+        let matrix = QrMatrix::encode("http://localhost/abc", EcLevel::M).unwrap();
+        let total = matrix.width + 8;
+
+        let svg = matrix.to_svg(&style());
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(&format!(r#"viewBox="0 0 {total} {total}""#)));
+        assert!(svg.contains(r##"fill="#000000""##));
+    }
+
+    #[test]
+    fn png_is_scaled_by_whole_pixels() {
+        let matrix = QrMatrix::encode("http://localhost/abc", EcLevel::M).unwrap();
+        let total = matrix.width + 8;
+
+        let image = matrix.to_png(&style()).unwrap();
+
+        let decoder = png::Decoder::new(image.as_slice());
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().width, total * (100 / total).max(1));
+    }
+}
@@ -0,0 +1,18 @@
+use rand::{distributions::Alphanumeric, Rng};
+
+use super::sha256_hex;
+
+const REFRESH_TOKEN_LENGTH: usize = 48;
+
+// Refresh tokens are random strings, only their hash is stored
+pub fn generate_refresh_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(REFRESH_TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+pub fn hash_refresh_token(token: &str) -> String {
+    sha256_hex(token)
+}
@@ -0,0 +1,76 @@
+use rand::seq::SliceRandom;
+use validator::ValidationError;
+
+use crate::router::RESERVED_PATHS;
+
+pub const MIN_SLUG_LENGTH: usize = 5;
+pub const MAX_SLUG_LENGTH: usize = 20;
+
+// Slugs are served from the root path so they can't shadow other top level routes. They are
+// kept to ascii so they go into urls and cookie paths without being percent-encoded.
+pub fn validate_slug(slug: &str) -> Result<(), ValidationError> {
+    let slug = slug.replace(' ', "");
+
+    if !slug
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~'))
+    {
+        return Err(ValidationError::new("characters"));
+    }
+
+    if RESERVED_PATHS
+        .iter()
+        .any(|path| path.eq_ignore_ascii_case(&slug))
+    {
+        return Err(ValidationError::new("reserved_slug"));
+    }
+
+    Ok(())
+}
+
+pub fn generate_slug(alphabet: &[char], length: usize) -> String {
+    let mut rng = rand::thread_rng();
+
+    (0..length)
+        .filter_map(|_| alphabet.choose(&mut rng))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generate_slug_from_alphabet() {
+        let alphabet: Vec<char> = "ab3".chars().collect();
+        let slug = generate_slug(&alphabet, 12);
+
+        assert_eq!(slug.len(), 12);
+        assert!(slug.chars().all(|c| alphabet.contains(&c)));
+        assert!(validate_slug(&slug).is_ok());
+    }
+
+    #[test]
+    fn accept_regular_slug() {
+        assert!(validate_slug("my-link_1").is_ok());
+    }
+
+    #[test]
+    fn reject_reserved_slug() {
+        assert!(validate_slug("api").is_err());
+        assert!(validate_slug("Health_Check").is_err());
+    }
+
+    #[test]
+    fn reject_slug_with_path_characters() {
+        assert!(validate_slug("foo/bar").is_err());
+        assert!(validate_slug("foo?bar").is_err());
+    }
+
+    #[test]
+    fn reject_non_ascii_slug() {
+        assert!(validate_slug("café").is_err());
+        assert!(validate_slug("ссылка").is_err());
+        assert!(validate_slug("٣٤٥٦٧").is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use std::{collections::HashSet, fmt};
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserializer,
+};
+use validator::ValidationError;
+
+pub const MAX_TAGS: usize = 10;
+pub const MAX_TAG_LENGTH: usize = 32;
+
+// Lowercase words, so filtering by tag doesn't depend on how it was typed
+pub fn validate_tag(tag: &str) -> Result<(), ValidationError> {
+    if tag.is_empty()
+        || tag.chars().count() > MAX_TAG_LENGTH
+        || !tag
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_'))
+    {
+        return Err(ValidationError::new("tag"));
+    }
+
+    Ok(())
+}
+
+pub fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    let unique: HashSet<&String> = tags.iter().collect();
+
+    if tags.len() > MAX_TAGS
+        || unique.len() != tags.len()
+        || tags.iter().any(|tag| validate_tag(tag).is_err())
+    {
+        return Err(ValidationError::new("tags"));
+    }
+
+    Ok(())
+}
+
+// Tags come as a list, or as a single comma or space separated string for csv files
+pub fn deserialize_tags<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_option(TagsVisitor)
+}
+
+struct TagsVisitor;
+
+impl<'de> Visitor<'de> for TagsVisitor {
+    type Value = Option<Vec<String>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of tags or a comma separated string")
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_str<E: de::Error>(self, text: &str) -> Result<Self::Value, E> {
+        let tags = text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_owned)
+            .collect();
+
+        Ok(Some(tags))
+    }
+
+    // Csv fields holding a single tag like 2023 are read as numbers
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(Some(vec![value.to_string()]))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(Some(vec![value.to_string()]))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut tags = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(tag) = seq.next_element()? {
+            tags.push(tag);
+        }
+
+        Ok(Some(tags))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[test]
+    fn tags_are_lowercase_words() {
+        assert!(validate_tags(&["marketing".into(), "q1-2023".into(), "a_b".into()]).is_ok());
+        assert!(validate_tags(&["Marketing".into()]).is_err());
+        assert!(validate_tags(&["two words".into()]).is_err());
+        assert!(validate_tags(&["".into()]).is_err());
+        assert!(validate_tags(&["dup".into(), "dup".into()]).is_err());
+
+        let too_many: Vec<String> = (0..=MAX_TAGS).map(|i| format!("tag{i}")).collect();
+        assert!(validate_tags(&too_many).is_err());
+    }
+
+    #[test]
+    fn tags_are_read_from_a_list_or_a_string() {
+        #[derive(Deserialize)]
+        struct Input {
+            #[serde(default, deserialize_with = "deserialize_tags")]
+            tags: Option<Vec<String>>,
+        }
+
+        let parse = |json: &str| serde_json::from_str::<Input>(json).unwrap().tags;
+
+        assert_eq!(
+            parse(r#"{"tags": ["a", "b"]}"#),
+            Some(vec!["a".into(), "b".into()])
+        );
+        assert_eq!(
+            parse(r#"{"tags": "a, b c"}"#),
+            Some(vec!["a".into(), "b".into(), "c".into()])
+        );
+        assert_eq!(parse(r#"{"tags": ""}"#), Some(vec![]));
+        assert_eq!(parse(r#"{"tags": null}"#), None);
+        assert_eq!(parse(r#"{}"#), None);
+    }
+}
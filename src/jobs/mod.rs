@@ -0,0 +1,5 @@
+mod token_cleanup;
+mod trash_purge;
+
+pub use token_cleanup::*;
+pub use trash_purge::*;
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use sea_orm::{
+    sea_query::Condition, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+use crate::entity::{login_throttle, refresh_token, revoked_token, session};
+
+const TOKEN_CLEANUP_INTERVAL: Duration = Duration::from_secs(3_600);
+// Login failures older than this no longer count toward any lockout
+const LOGIN_THROTTLE_RETENTION_HOURS: i64 = 24;
+
+// Expired tokens are rejected on their own, their rows are only kept around until the next run
+pub fn spawn_token_cleanup(db: DatabaseConnection) -> JoinHandle<()> {
+    let mut interval = tokio::time::interval(TOKEN_CLEANUP_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = cleanup_expired_tokens(&db).await {
+                tracing::error!("couldn't clean up expired tokens: {err}");
+            }
+        }
+    })
+}
+
+pub async fn cleanup_expired_tokens(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    let now = chrono::Utc::now().naive_utc();
+
+    let refresh_tokens = refresh_token::Entity::delete_many()
+        .filter(refresh_token::Column::ExpiresAt.lt(now))
+        .exec(db)
+        .await?;
+    let revoked_tokens = revoked_token::Entity::delete_many()
+        .filter(revoked_token::Column::ExpiresAt.lt(now))
+        .exec(db)
+        .await?;
+
+    // Their remaining refresh tokens go with them
+    let sessions = session::Entity::delete_many()
+        .filter(session::Column::ExpiresAt.lt(now))
+        .exec(db)
+        .await?;
+
+    let login_throttles = login_throttle::Entity::delete_many()
+        .filter(
+            login_throttle::Column::LastFailureAt
+                .lt(now - chrono::Duration::hours(LOGIN_THROTTLE_RETENTION_HOURS)),
+        )
+        .filter(
+            Condition::any()
+                .add(login_throttle::Column::LockedUntil.is_null())
+                .add(login_throttle::Column::LockedUntil.lt(now)),
+        )
+        .exec(db)
+        .await?;
+
+    Ok(refresh_tokens.rows_affected
+        + revoked_tokens.rows_affected
+        + sessions.rows_affected
+        + login_throttles.rows_affected)
+}
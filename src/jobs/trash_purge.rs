@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+use crate::{configuration::TrashSettings, entity::url};
+
+// Periodically removes links that have been in the trash for longer than the retention period.
+// Their clicks go with them through the cascading foreign key.
+pub fn spawn_trash_purge(db: DatabaseConnection, settings: &TrashSettings) -> JoinHandle<()> {
+    let retention = settings.retention();
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(settings.purge_interval_secs.max(1)));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+
+            match purge_trash(&db, retention).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!("purged {purged} links from the trash"),
+                Err(err) => tracing::error!("couldn't purge the trash: {err}"),
+            }
+        }
+    })
+}
+
+pub async fn purge_trash(
+    db: &DatabaseConnection,
+    retention: chrono::Duration,
+) -> Result<u64, DbErr> {
+    let cutoff = chrono::Utc::now().naive_utc() - retention;
+
+    let res = url::Entity::delete_many()
+        .filter(url::Column::DeletedAt.lt(cutoff))
+        .exec(db)
+        .await?;
+
+    Ok(res.rows_affected)
+}
@@ -1,8 +1,12 @@
+pub mod analytics;
 pub mod configuration;
 pub mod cors;
 pub mod dto;
 pub mod entity;
 pub mod handler;
+pub mod jobs;
+pub mod mailer;
+pub mod oidc;
 pub mod router;
 pub mod server;
 pub mod telemetry;
@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use axum::async_trait;
+use sea_orm::prelude::Uuid;
+
+use super::{Email, Mailer, MailerError};
+
+// Writes every email as a json file, one per email
+pub struct FileMailer {
+    directory: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), MailerError> {
+        tokio::fs::create_dir_all(&self.directory).await?;
+
+        let path = self.directory.join(format!("{}.json", Uuid::new_v4()));
+        tokio::fs::write(path, serde_json::to_vec_pretty(&email)?).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+use axum::async_trait;
+
+use super::{Email, Mailer, MailerError};
+
+// Logs emails instead of sending them, they hold live tokens so it is only fit for development
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<(), MailerError> {
+        tracing::info!(
+            to = %email.to,
+            subject = %email.subject,
+            "email not sent, logging it instead:\n{}",
+            email.body
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+mod file_mailer;
+mod log_mailer;
+mod smtp_mailer;
+
+use std::sync::Arc;
+
+use axum::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::configuration::{EmailSettings, MailerSettings};
+
+pub use file_mailer::*;
+pub use log_mailer::*;
+pub use smtp_mailer::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Email {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Error)]
+pub enum MailerError {
+    #[error("invalid email address: {0}")]
+    Address(#[from] lettre::address::AddressError),
+    #[error("couldn't build email: {0}")]
+    Message(#[from] lettre::error::Error),
+    #[error("couldn't send email: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("couldn't write email: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("couldn't serialize email: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), MailerError>;
+}
+
+pub type SharedMailer = Arc<dyn Mailer>;
+
+pub fn make_mailer(settings: &EmailSettings) -> Result<SharedMailer, MailerError> {
+    let mailer: SharedMailer = match &settings.mailer {
+        MailerSettings::Smtp {
+            host,
+            port,
+            username,
+            password,
+        } => Arc::new(SmtpMailer::new(host, *port, username, password)?),
+        MailerSettings::File { directory } => Arc::new(FileMailer::new(directory.clone())),
+        MailerSettings::Log => Arc::new(LogMailer),
+    };
+
+    Ok(mailer)
+}
@@ -3,6 +3,7 @@ use std::net::TcpListener;
 
 use lib::{
     configuration::{DatabaseSettings, GlobalConfig},
+    mailer::{make_mailer, MailerError},
     server::run,
     telemetry::init_telemetry,
 };
@@ -16,16 +17,24 @@ async fn main() -> Result<(), Error> {
     let env = std::env::var("ENVIRONMENT").ok();
     let config_path = std::env::current_dir()?.join("config");
     let config = GlobalConfig::build(env, config_path)?;
+    // refuse hashing params argon2 would reject on every request
+    config
+        .application
+        .password_hashing
+        .params()
+        .map_err(Error::PasswordHashing)?;
     // connect to database
     let connection_options =
         DatabaseSettings::get_connection_options(&config.database.get_connection_string());
     let db = Database::connect(connection_options).await?;
+    // setup mailer
+    let mailer = make_mailer(&config.application.email)?;
     // make listener
     let listener = TcpListener::bind(config.application.address())?;
 
     // run server
     tracing::debug!("listening on {}", config.application.address());
-    Ok(run(listener, db, &config).await?)
+    Ok(run(listener, db, mailer, &config).await?)
 }
 
 #[derive(Error, Debug)]
@@ -38,4 +47,8 @@ enum Error {
     Config(#[from] config::ConfigError),
     #[error("error connecting to the database")]
     DBConnection(#[from] sea_orm::DbErr),
+    #[error("mailer setup error")]
+    Mailer(#[from] MailerError),
+    #[error("invalid password hashing params: {0}")]
+    PasswordHashing(argon2::Error),
 }
@@ -0,0 +1,255 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde::Deserialize;
+use thiserror::Error;
+use url::Url;
+
+use crate::configuration::OidcSettings;
+
+#[derive(Debug, Error)]
+pub enum OidcError {
+    #[error("couldn't reach the provider: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("invalid provider url: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("invalid id token: {0}")]
+    IdToken(#[from] jsonwebtoken::errors::Error),
+    #[error("no provider key matches the id token")]
+    UnknownKey,
+    #[error("id token nonce doesn't match the login")]
+    NonceMismatch,
+}
+
+impl OidcError {
+    // Whether the provider couldn't be talked to, as opposed to handing out bad data
+    pub fn is_unreachable(&self) -> bool {
+        matches!(self, OidcError::Request(_) | OidcError::Url(_))
+    }
+}
+
+// Subset of the discovery document the login flow needs
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+    nonce: Option<String>,
+}
+
+#[derive(Debug)]
+struct Cached<T> {
+    value: Arc<T>,
+    fetched_at: Instant,
+}
+
+// Documents of the provider shared by every login, they only change on key rotation
+#[derive(Debug, Default)]
+struct ProviderCache {
+    metadata: Option<Cached<ProviderMetadata>>,
+    jwks: Option<Cached<JwkSet>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OidcClient {
+    settings: OidcSettings,
+    http: reqwest::Client,
+    cache: Arc<Mutex<ProviderCache>>,
+}
+
+impl OidcClient {
+    pub fn new(settings: OidcSettings) -> Self {
+        Self {
+            settings,
+            http: reqwest::Client::new(),
+            cache: Arc::default(),
+        }
+    }
+
+    pub fn settings(&self) -> &OidcSettings {
+        &self.settings
+    }
+
+    fn fresh<T>(&self, cached: &Option<Cached<T>>) -> Option<Arc<T>> {
+        cached
+            .as_ref()
+            .filter(|cached| cached.fetched_at.elapsed() < self.settings.metadata_ttl())
+            .map(|cached| cached.value.clone())
+    }
+
+    async fn metadata(&self) -> Result<Arc<ProviderMetadata>, OidcError> {
+        if let Some(metadata) = self.fresh(&self.cache.lock().unwrap().metadata) {
+            return Ok(metadata);
+        }
+
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.settings.issuer_url.trim_end_matches('/')
+        );
+        let metadata: Arc<ProviderMetadata> = Arc::new(
+            self.http
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?,
+        );
+
+        self.cache.lock().unwrap().metadata = Some(Cached {
+            value: metadata.clone(),
+            fetched_at: Instant::now(),
+        });
+
+        Ok(metadata)
+    }
+
+    async fn fetch_jwks(&self, jwks_uri: &str) -> Result<Arc<JwkSet>, OidcError> {
+        let jwks: Arc<JwkSet> = Arc::new(
+            self.http
+                .get(jwks_uri)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?,
+        );
+
+        self.cache.lock().unwrap().jwks = Some(Cached {
+            value: jwks.clone(),
+            fetched_at: Instant::now(),
+        });
+
+        Ok(jwks)
+    }
+
+    async fn signing_key(&self, jwks_uri: &str, kid: Option<&str>) -> Result<Jwk, OidcError> {
+        let find = |jwks: &JwkSet| {
+            match kid {
+                Some(kid) => jwks.find(kid),
+                None => jwks.keys.first(),
+            }
+            .cloned()
+        };
+
+        if let Some(jwk) = self
+            .fresh(&self.cache.lock().unwrap().jwks)
+            .and_then(|jwks| find(&jwks))
+        {
+            return Ok(jwk);
+        }
+
+        // An unknown key may have been rotated in since the keys were cached
+        let jwks = self.fetch_jwks(jwks_uri).await?;
+        find(&jwks).ok_or(OidcError::UnknownKey)
+    }
+
+    pub async fn authorization_url(
+        &self,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> Result<String, OidcError> {
+        let metadata = self.metadata().await?;
+        let url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", &self.settings.client_id),
+                ("redirect_uri", &self.settings.redirect_url),
+                ("scope", &self.settings.scopes),
+                ("state", state),
+                ("nonce", nonce),
+                ("code_challenge", code_challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        )?;
+
+        Ok(url.into())
+    }
+
+    // Trades the authorization code for the id token of the user, once verified
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, OidcError> {
+        let metadata = self.metadata().await?;
+        let response: TokenResponse = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.settings.redirect_url),
+                ("client_id", &self.settings.client_id),
+                ("client_secret", &self.settings.client_secret),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let claims = self.verify_id_token(&metadata, &response.id_token).await?;
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(OidcError::NonceMismatch);
+        }
+
+        Ok(claims)
+    }
+
+    async fn verify_id_token(
+        &self,
+        metadata: &ProviderMetadata,
+        id_token: &str,
+    ) -> Result<IdTokenClaims, OidcError> {
+        let header = decode_header(id_token)?;
+
+        let key = match header.alg {
+            // Symmetric id tokens are signed with the client secret
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                DecodingKey::from_secret(self.settings.client_secret.as_bytes())
+            }
+            _ => {
+                let jwk = self
+                    .signing_key(&metadata.jwks_uri, header.kid.as_deref())
+                    .await?;
+
+                DecodingKey::from_jwk(&jwk)?
+            }
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.settings.client_id]);
+
+        Ok(decode::<IdTokenClaims>(id_token, &key, &validation)?.claims)
+    }
+}
@@ -0,0 +1,5 @@
+mod client;
+mod pkce;
+
+pub use client::*;
+pub use pkce::*;
@@ -0,0 +1,36 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+const CODE_VERIFIER_LENGTH: usize = 64;
+
+// Random value used for the state, the nonce and the code verifier of a login
+pub fn random_token(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+pub fn generate_code_verifier() -> String {
+    random_token(CODE_VERIFIER_LENGTH)
+}
+
+// S256 method, the provider only sees the challenge until the code is exchanged
+pub fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn code_challenge_is_unpadded_url_safe_sha256() {
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mJ92Xg_-M-2Zx5g5Ah3T6bVNQrYbiY"),
+            "38_FQA6TJkrNJ8oYgOKJRR1jG7rb7qz6Duv-7dX-AKo"
+        );
+    }
+}
@@ -1,19 +1,37 @@
 use crate::{
-    configuration::ApplicationSettings,
+    analytics::ClickIngestor,
+    configuration::{
+        ApplicationSettings, AuthSettings, EmailSettings, LinkSettings, PasswordHashingSettings,
+        ProxySettings, TrashSettings,
+    },
     cors::get_cors_settings,
+    mailer::SharedMailer,
+    oidc::OidcClient,
     handler::{
         create_url_handler, get_url_list_handler, login_handler, me_handler, register_handler,
         status_handler, update_url_handler, delete_url_handler, get_url_handler,
+        redirect_slug_handler, get_url_stats_handler, metrics_handler, get_url_trash_handler,
+        restore_url_handler, unlock_slug_handler, refresh_handler, logout_handler,
+        get_sessions_handler, delete_session_handler, delete_sessions_handler,
+        create_api_key_handler, get_api_keys_handler, delete_api_key_handler,
+        oidc_authorize_handler, oidc_callback_handler, verify_email_handler,
+        forgot_password_handler, reset_password_handler, update_me_handler,
+        change_password_handler, delete_me_handler, enroll_totp_handler, confirm_totp_handler,
+        disable_totp_handler, login_two_factor_handler, get_url_qr_handler,
+        import_url_handler, export_url_handler, batch_url_handler,
     },
 };
 use axum::{
     extract::FromRef,
-    routing::{get, post, put},
+    routing::{delete, get, post, put},
     Router,
 };
 use sea_orm::DatabaseConnection;
 use tower_http::trace::TraceLayer;
 
+// Top level paths that can't be used as link slugs
+pub const RESERVED_PATHS: &[&str] = &["api", "health_check", "metrics"];
+
 #[derive(Clone)]
 pub struct Secrets {
     pub hash_secret: String,
@@ -24,10 +42,21 @@ pub struct Secrets {
 pub struct AppState {
     pub db_connection: DatabaseConnection,
     pub secrets: Secrets,
+    pub links: LinkSettings,
+    pub clicks: ClickIngestor,
+    pub trash: TrashSettings,
+    pub auth: AuthSettings,
+    pub oidc: Option<OidcClient>,
+    pub mailer: SharedMailer,
+    pub email: EmailSettings,
+    pub password_hashing: PasswordHashingSettings,
+    pub proxy: ProxySettings,
 }
 
 pub fn make_router(
     db_connection: DatabaseConnection,
+    clicks: ClickIngestor,
+    mailer: SharedMailer,
     app_settings: &ApplicationSettings,
 ) -> Router {
     // Innit shared state
@@ -37,28 +66,62 @@ pub fn make_router(
             hash_secret: app_settings.hash_secret.clone(),
             jwt_secret: app_settings.jwt_secret.clone(),
         },
+        links: app_settings.links.clone(),
+        clicks,
+        trash: app_settings.trash.clone(),
+        auth: app_settings.auth.clone(),
+        oidc: app_settings.oidc.clone().map(OidcClient::new),
+        mailer,
+        email: app_settings.email.clone(),
+        password_hashing: app_settings.password_hashing.clone(),
+        proxy: app_settings.proxy.clone(),
     };
     // Create axum router
     let user_routes = Router::new()
         .route("/register", post(register_handler))
         .route("/login", post(login_handler))
-        .route("/me", get(me_handler));
+        .route("/login/2fa", post(login_two_factor_handler))
+        .route("/refresh", post(refresh_handler))
+        .route("/logout", post(logout_handler))
+        .route("/verify-email", post(verify_email_handler))
+        .route("/forgot-password", post(forgot_password_handler))
+        .route("/reset-password", post(reset_password_handler))
+        .route("/oidc/authorize", get(oidc_authorize_handler))
+        .route("/oidc/callback", get(oidc_callback_handler))
+        .route("/sessions", get(get_sessions_handler).delete(delete_sessions_handler))
+        .route("/sessions/:session_id", delete(delete_session_handler))
+        .route("/api-keys", post(create_api_key_handler).get(get_api_keys_handler))
+        .route("/api-keys/:key_id", delete(delete_api_key_handler))
+        .route("/password", post(change_password_handler))
+        .route("/2fa/enroll", post(enroll_totp_handler))
+        .route("/2fa/confirm", post(confirm_totp_handler))
+        .route("/2fa/disable", post(disable_totp_handler))
+        .route("/me", get(me_handler).patch(update_me_handler).delete(delete_me_handler));
 
     let links_route = Router::new()
         .route("/", post(create_url_handler))
         .route("/:link_id", put(update_url_handler).delete(delete_url_handler).get(get_url_handler))
+        .route("/trash", get(get_url_trash_handler))
+        .route("/import", post(import_url_handler))
+        .route("/export", get(export_url_handler))
+        .route("/batch", post(batch_url_handler))
+        .route("/:link_id/stats", get(get_url_stats_handler))
+        .route("/:link_id/qr", get(get_url_qr_handler))
+        .route("/:link_id/restore", post(restore_url_handler))
         .route("/", get(get_url_list_handler));
 
     let api_routes = Router::new()
         .nest("/user", user_routes)
-        .nest("/links", links_route)
-        .with_state(state);
+        .nest("/links", links_route);
 
     let cors_layer = get_cors_settings(app_settings);
 
     Router::new()
         .route("/health_check", get(status_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/:slug", get(redirect_slug_handler).post(unlock_slug_handler))
         .nest("/api", api_routes)
+        .with_state(state)
         .layer(cors_layer)
         .layer(TraceLayer::new_for_http())
 }
@@ -1,28 +1,79 @@
-use axum::{routing::IntoMakeService, Router, Server};
+use axum::{extract::connect_info::IntoMakeServiceWithConnectInfo, Router, Server};
 use hyper::{server::conn::AddrIncoming, Error};
 use sea_orm::DatabaseConnection;
-use std::net::TcpListener;
+use std::net::{SocketAddr, TcpListener};
 
-use crate::{configuration::GlobalConfig, router::make_router};
+use crate::{
+    analytics::ClickIngestor,
+    configuration::GlobalConfig,
+    jobs::{spawn_token_cleanup, spawn_trash_purge},
+    mailer::SharedMailer,
+    router::make_router,
+};
 
 fn make_server(
     listener: TcpListener,
     db_connection: DatabaseConnection,
+    clicks: ClickIngestor,
+    mailer: SharedMailer,
     config: &GlobalConfig,
-) -> Result<Server<AddrIncoming, IntoMakeService<Router>>, Error> {
+) -> Result<Server<AddrIncoming, IntoMakeServiceWithConnectInfo<Router, SocketAddr>>, Error> {
     // make router
-    let router = make_router(db_connection, &config.application);
+    let router = make_router(db_connection, clicks, mailer, &config.application);
 
     // Start server
-    Ok(Server::from_tcp(listener)?.serve(router.into_make_service()))
+    Ok(Server::from_tcp(listener)?
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>()))
 }
 
 pub async fn run(
     listener: TcpListener,
     db_connection: DatabaseConnection,
+    mailer: SharedMailer,
     config: &GlobalConfig,
 ) -> Result<(), Error> {
-    let server = make_server(listener, db_connection, config);
+    let (clicks, click_writer) =
+        ClickIngestor::spawn(db_connection.clone(), &config.application.clicks);
+    let trash_purge = spawn_trash_purge(db_connection.clone(), &config.application.trash);
+    let token_cleanup = spawn_token_cleanup(db_connection.clone());
 
-    server?.await
+    let server = make_server(listener, db_connection, clicks, mailer, config)?;
+    let result = server.with_graceful_shutdown(shutdown_signal()).await;
+
+    // Both jobs are idempotent, the next start picks up where they were stopped
+    trash_purge.abort();
+    token_cleanup.abort();
+
+    // The router is dropped with the server, the click writer flushes its queue and stops
+    if click_writer.await.is_err() {
+        tracing::error!("click writer panicked before flushing");
+    }
+
+    result
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install ctrl+c handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install signal handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::debug!("shutdown signal received, draining connections");
 }
@@ -0,0 +1,134 @@
+use axum::http::HeaderMap;
+use lib::{
+    analytics::{ClickEvent, ClickIngestor},
+    configuration::ClickSettings,
+    entity::{click, url},
+};
+use std::time::Duration;
+
+use sea_orm::{prelude::Uuid, ColumnTrait, EntityTrait, ModelTrait, PaginatorTrait, QueryFilter};
+
+use crate::{
+    helpers::server::TestApp,
+    seeds::{links::seed_one_link_for_user, users::seed_one_local_user},
+};
+
+#[tokio::test]
+async fn click_ingestor_flushes_queue_on_shutdown() {
+    let app = TestApp::new().await;
+
+    // Seed database with one user and a link
+    let (user, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+
+    // Thresholds that are never reached, only the shutdown can flush
+    let settings = ClickSettings {
+        queue_size: 100,
+        batch_size: 100,
+        flush_interval_ms: 3_600_000,
+    };
+    let (clicks, writer) = ClickIngestor::spawn(app.database.clone(), &settings);
+
+    for _ in 0..3 {
+        clicks.record(ClickEvent::new(link.id, &HeaderMap::new(), None, b"secret"));
+    }
+    assert_eq!(clicks.stats().queued, 3);
+
+    // Dropping the last handle shuts the writer down
+    drop(clicks);
+    writer.await.expect("click writer panicked");
+
+    assert_eq!(count_clicks(&app, &link.id).await, 3);
+
+    // The counter of the link follows the clicks written
+    let link = url::Entity::find_by_id(link.id)
+        .one(&app.database)
+        .await
+        .expect("couldn't get link")
+        .expect("link is missing");
+    assert_eq!(link.click_count, 3);
+}
+
+#[tokio::test]
+async fn click_ingestor_drops_clicks_when_queue_is_full() {
+    let app = TestApp::new().await;
+
+    // Seed database with one user and a link
+    let (user, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+
+    let settings = ClickSettings {
+        queue_size: 2,
+        batch_size: 100,
+        flush_interval_ms: 3_600_000,
+    };
+    let (clicks, writer) = ClickIngestor::spawn(app.database.clone(), &settings);
+
+    // The writer can't run between these calls, everything past the queue size is dropped
+    for _ in 0..10 {
+        clicks.record(ClickEvent::new(link.id, &HeaderMap::new(), None, b"secret"));
+    }
+
+    let stats = clicks.stats();
+    assert_eq!(stats.received, 10);
+    assert_eq!(stats.dropped, 8);
+    assert_eq!(stats.queued, 2);
+
+    drop(clicks);
+    writer.await.expect("click writer panicked");
+
+    assert_eq!(count_clicks(&app, &link.id).await, 2);
+}
+
+#[tokio::test]
+async fn click_ingestor_drops_clicks_on_deleted_links() {
+    let app = TestApp::new().await;
+
+    // Seed database with one user and two links, one of them deleted for good
+    let (user, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    let deleted_link = seed_one_link_for_user(&app.database, &user.id).await;
+    let deleted_link_id = deleted_link.id;
+    deleted_link
+        .delete(&app.database)
+        .await
+        .expect("couldn't delete link");
+
+    let settings = ClickSettings {
+        queue_size: 100,
+        batch_size: 100,
+        flush_interval_ms: 50,
+    };
+    let (clicks, writer) = ClickIngestor::spawn(app.database.clone(), &settings);
+
+    // Both links end up in the same batch, the second one is gone by the time it is written
+    for link_id in [link.id, deleted_link_id, link.id] {
+        clicks.record(ClickEvent::new(link_id, &HeaderMap::new(), None, b"secret"));
+    }
+
+    // The clicks of the other link are still written
+    let mut stats = clicks.stats();
+    for _ in 0..40 {
+        if stats.flushed + stats.dropped + stats.failed == 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stats = clicks.stats();
+    }
+    assert_eq!(stats.flushed, 2);
+    assert_eq!(stats.dropped, 1);
+    assert_eq!(stats.failed, 0);
+
+    drop(clicks);
+    writer.await.expect("click writer panicked");
+
+    assert_eq!(count_clicks(&app, &link.id).await, 2);
+}
+
+async fn count_clicks(app: &TestApp, link_id: &Uuid) -> u64 {
+    click::Entity::find()
+        .filter(click::Column::UrlId.eq(*link_id))
+        .count(&app.database)
+        .await
+        .expect("couldn't count clicks")
+}
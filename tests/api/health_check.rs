@@ -35,3 +35,34 @@ async fn health_route_status() {
 
     assert_eq!(expected_body, body);
 }
+
+#[tokio::test]
+async fn metrics_route_exposes_click_counters() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Create request
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(app.get_http_uri(Some("/metrics")))
+        .body(Body::empty())
+        .expect("could not make request");
+    // Send request
+    let response = app
+        .client
+        .request(request)
+        .await
+        .expect("couldn't send request");
+
+    assert!(response.status().is_success());
+
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .expect("couldn't read body");
+    let body = String::from_utf8_lossy(&body);
+
+    assert!(body.contains("dinoly_clicks_received_total 0"));
+    assert!(body.contains("dinoly_clicks_dropped_total 0"));
+    assert!(body.contains("dinoly_clicks_flushed_total 0"));
+}
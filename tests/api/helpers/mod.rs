@@ -1,4 +1,5 @@
 mod json;
+pub mod mock_idp;
 pub mod server;
 pub mod testing;
 pub use json::*;
@@ -1,8 +1,10 @@
-use std::net::TcpListener;
+use std::net::{SocketAddr, TcpListener};
 
 use hyper::{client::HttpConnector, Body, Client, Method, Request};
 use lib::{
-    configuration::{DatabaseSettings, GlobalConfig},
+    analytics::ClickIngestor,
+    configuration::{DatabaseSettings, GlobalConfig, MailerSettings},
+    mailer::{make_mailer, Email},
     router,
 };
 
@@ -35,6 +37,13 @@ impl TestApp {
         // Setup database
         let db = Self::setup_db(&mut config.database).await;
 
+        // Emails are written to a directory of their own
+        config.application.email.mailer = MailerSettings::File {
+            directory: std::env::temp_dir()
+                .join("dinoly-mail")
+                .join(Uuid::new_v4().to_string()),
+        };
+
         Self {
             config,
             database: db,
@@ -78,12 +87,20 @@ impl TestApp {
         let local_addr = listener
             .local_addr()
             .expect("couldn't get local address from listener");
-        let router = router::make_router(self.database.clone(), &self.config.application);
+        let (clicks, _) =
+            ClickIngestor::spawn(self.database.clone(), &self.config.application.clicks);
+        let mailer = make_mailer(&self.config.application.email).expect("couldn't create mailer");
+        let router = router::make_router(
+            self.database.clone(),
+            clicks,
+            mailer,
+            &self.config.application,
+        );
 
         tokio::spawn(async move {
             axum::Server::from_tcp(listener)
                 .unwrap()
-                .serve(router.into_make_service())
+                .serve(router.into_make_service_with_connect_info::<SocketAddr>())
                 .await
                 .unwrap()
         });
@@ -91,12 +108,34 @@ impl TestApp {
         self.config.application.port = local_addr.port();
     }
 
+    // Lets the test client stand in for a reverse proxy, so X-Forwarded-For is read
+    pub fn trust_local_proxy(&mut self) {
+        self.config.application.proxy.trusted_proxies = vec!["127.0.0.0/8".parse().unwrap()];
+    }
+
     pub fn get_http_uri(&self, path: Option<&str>) -> String {
         let path = path.unwrap_or("");
 
         format!("http://{}{}", &self.config.application.address(), path)
     }
 
+    pub fn sent_emails(&self) -> Vec<Email> {
+        let MailerSettings::File { directory } = &self.config.application.email.mailer else {
+            panic!("emails are only kept by the file mailer");
+        };
+        let Ok(entries) = std::fs::read_dir(directory) else {
+            return Vec::new();
+        };
+
+        entries
+            .map(|entry| {
+                let content = std::fs::read(entry.expect("couldn't read mail entry").path())
+                    .expect("couldn't read mail");
+                serde_json::from_slice(&content).expect("couldn't parse mail")
+            })
+            .collect()
+    }
+
     pub async fn login_user(&self, username: &str, password: &str) -> String {
         let user_input = json!({
             "username": username,
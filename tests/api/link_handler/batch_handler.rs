@@ -0,0 +1,182 @@
+use axum::http::StatusCode;
+use hyper::{Body, Method, Request, Response};
+use lib::entity::url;
+use sea_orm::EntityTrait;
+use serde_json::{json, Value};
+
+use crate::{
+    helpers::{server::TestApp, ParseJson},
+    seeds::{links::seed_one_link_for_user, users::seed_one_local_user},
+};
+
+async fn send_batch(app: &TestApp, token: &str, batch: Value) -> Response<Body> {
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some("/api/links/batch")))
+        .method(Method::POST)
+        .header("Authorization", format!("Bearer {token}"))
+        .header("Content-Type", "application/json")
+        .body(Body::from(batch.to_string()))
+        .expect("couldn't create request");
+
+    app.client
+        .request(req)
+        .await
+        .expect("coudln't send request")
+}
+
+#[tokio::test]
+async fn batch_links_handler_best_effort_keeps_successful_operations() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with two users and their links
+    let (user1, password1) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (user2, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link_to_update = seed_one_link_for_user(&app.database, &user1.id).await;
+    let link_to_delete = seed_one_link_for_user(&app.database, &user1.id).await;
+    let other_link = seed_one_link_for_user(&app.database, &user2.id).await;
+    // Get token by logging in
+    let token = app.login_user(&user1.username, &password1).await;
+
+    let batch = json!({
+        "mode": "best_effort",
+        "operations": [
+            { "op": "create", "link": { "name": "batch link", "redirect_to": "https://example.com" } },
+            { "op": "create", "link": { "name": "bad link", "redirect_to": "not a url" } },
+            { "op": "update", "id": link_to_update.id, "link": { "redirect_to": "https://example.org" } },
+            { "op": "update", "id": other_link.id, "link": { "redirect_to": "https://example.org" } },
+            { "op": "delete", "id": link_to_delete.id },
+        ]
+    });
+    let res = send_batch(&app, &token, batch).await;
+
+    // Checking server response
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body: Value = res
+        .json_from_body()
+        .await
+        .expect("couldn't get json from body");
+    let data = &body["data"];
+    assert_eq!(data["succeeded"], 3);
+    assert_eq!(data["failed"], 2);
+
+    let results = data["results"].as_array().expect("couldn't get results");
+    let statuses: Vec<_> = results.iter().map(|result| result["status"].as_u64()).collect();
+    assert_eq!(statuses, [200, 400, 200, 403, 200].map(Some));
+    assert_eq!(results[0]["link"]["name"], "batch link");
+    assert_eq!(results[1]["error"]["message"], "invalid data from client");
+    assert_eq!(results[1]["error"]["error"]["fields"]["redirect_to"], "invalid url");
+    assert_eq!(results[2]["link"]["redirect_to"], "https://example.org");
+
+    // Successful operations were applied
+    let updated_link = url::Entity::find_by_id(link_to_update.id)
+        .one(&app.database)
+        .await
+        .expect("couldn't get link")
+        .expect("link is missing");
+    assert_eq!(updated_link.redirect_to, "https://example.org");
+
+    let deleted_link = url::Entity::find_by_id(link_to_delete.id)
+        .one(&app.database)
+        .await
+        .expect("couldn't get link")
+        .expect("link is missing");
+    assert!(deleted_link.deleted_at.is_some());
+
+    let other_link = url::Entity::find_by_id(other_link.id)
+        .one(&app.database)
+        .await
+        .expect("couldn't get link")
+        .expect("link is missing");
+    assert_ne!(other_link.redirect_to, "https://example.org");
+}
+
+#[tokio::test]
+async fn batch_links_handler_atomic_applies_all_or_nothing() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user and a link
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    // The second creation reuses the slug of the first one
+    let operations = json!([
+        { "op": "update", "id": link.id, "link": { "redirect_to": "https://example.org" } },
+        { "op": "create", "link": { "name": "first link", "slug": "batch-slug", "redirect_to": "https://example.com" } },
+        { "op": "create", "link": { "name": "second link", "slug": "batch-slug", "redirect_to": "https://example.com" } },
+    ]);
+    let res = send_batch(&app, &token, json!({ "operations": operations })).await;
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: Value = res
+        .json_from_body()
+        .await
+        .expect("couldn't get json from body");
+    let report = &body["error"]["error"];
+    assert_eq!(report["mode"], "atomic");
+    assert_eq!(report["succeeded"], 0);
+    assert_eq!(report["failed"], 1);
+    assert_eq!(report["rolled_back"], 2);
+    assert_eq!(report["results"][1]["status"], 409);
+    assert_eq!(report["results"][1]["rolled_back"], true);
+    assert!(report["results"][1]["link"].is_null());
+    assert_eq!(report["results"][0]["rolled_back"], true);
+    assert!(report["results"][2]["rolled_back"].is_null());
+    assert_eq!(report["results"][2]["error"]["error"]["code"], "slug_taken");
+
+    // Nothing was applied
+    let links = url::Entity::find()
+        .all(&app.database)
+        .await
+        .expect("couldn't get links");
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].redirect_to, link.redirect_to);
+
+    // Without the conflicting operation the whole batch goes through
+    let mut operations = operations;
+    operations.as_array_mut().expect("operations aren't an array").pop();
+    let res = send_batch(&app, &token, json!({ "mode": "atomic", "operations": operations })).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let links = url::Entity::find()
+        .all(&app.database)
+        .await
+        .expect("couldn't get links");
+    assert_eq!(links.len(), 2);
+    assert!(links.iter().any(|link| link.slug == "batch-slug"));
+    assert!(links.iter().any(|link| link.redirect_to == "https://example.org"));
+}
+
+#[tokio::test]
+async fn batch_links_handler_limits_the_number_of_operations() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.config.application.links.max_batch_operations = 2;
+    app.spawn_server().await;
+
+    // Seed database with one user and a link
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    let operation = json!({ "op": "delete", "id": link.id });
+    let res = send_batch(&app, &token, json!({ "operations": [operation, operation, operation] })).await;
+
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let res = send_batch(&app, &token, json!({ "operations": [] })).await;
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
@@ -1,11 +1,13 @@
 use assert_json_diff::{assert_json_eq, assert_json_include};
-use hyper::{Body, Method, Request};
+use hyper::{Body, Method, Request, StatusCode};
+use lib::entity::url;
+use sea_orm::{ActiveModelTrait, ActiveValue::Set};
 use serde_json::{json, Value};
 
 use crate::helpers::testing::TestCase;
 use crate::{
     helpers::{server::TestApp, ParseJson},
-    seeds::users::seed_one_local_user,
+    seeds::{links::seed_one_link_for_user, users::seed_one_local_user},
 };
 
 #[tokio::test]
@@ -152,3 +154,219 @@ async fn create_link_handler_with_bad_jwt_token() {
     // Checking server response
     assert!(res.status().is_client_error());
 }
+
+#[tokio::test]
+async fn create_link_handler_generates_slug_when_omitted() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    let create_link_input = json!({
+        "name": "link_name",
+        "redirect_to": "http://google.com",
+    });
+
+    // Create request
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some("/api/links")))
+        .method(Method::POST)
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::from(create_link_input.to_string()))
+        .expect("couldn't create request");
+
+    // Send request
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+    // Checking server response
+    assert!(res.status().is_success());
+
+    let body: Value = res
+        .json_from_body()
+        .await
+        .expect("couldn't get json from body");
+
+    assert!(body["error"].is_null());
+
+    let settings = &app.config.application.links;
+    let slug = body["data"]["link"]["slug"]
+        .as_str()
+        .expect("link should have a slug");
+    assert_eq!(slug.chars().count(), settings.slug_length);
+    assert!(slug.chars().all(|c| settings.slug_alphabet.contains(c)));
+}
+
+#[tokio::test]
+async fn create_link_handler_allows_same_name_for_different_users() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with two users, each having a link called "docs"
+    let (first_user, _) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (second_user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &first_user.id).await;
+    let mut link: url::ActiveModel = link.into();
+    link.name = Set("docs".into());
+    link.update(&app.database)
+        .await
+        .expect("couldn't rename seeded link");
+
+    // Get token by logging in
+    let token = app.login_user(&second_user.username, &password).await;
+
+    let create_link_input = json!({
+        "name": "docs",
+        "redirect_to": "http://google.com",
+    });
+
+    // Create request
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some("/api/links")))
+        .method(Method::POST)
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::from(create_link_input.to_string()))
+        .expect("couldn't create request");
+
+    // Send request
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+    // Checking server response
+    assert!(res.status().is_success());
+}
+
+#[tokio::test]
+async fn create_link_handler_reports_slug_and_name_conflicts() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with two users, the first one owning a link
+    let (first_user, first_password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (second_user, second_password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &first_user.id).await;
+
+    let test_cases = [
+        // Slugs are global, another user can't take it
+        (
+            &second_user.username,
+            &second_password,
+            json!({ "name": "other", "slug": link.slug, "redirect_to": "http://google.com" }),
+            "slug_taken",
+            "slug",
+        ),
+        // Names are unique among the links of the same owner
+        (
+            &first_user.username,
+            &first_password,
+            json!({ "name": link.name, "redirect_to": "http://google.com" }),
+            "name_taken",
+            "name",
+        ),
+    ];
+
+    for (username, password, create_link_input, code, field) in test_cases {
+        // Get token by logging in
+        let token = app.login_user(username, password).await;
+
+        // Create request
+        let req = Request::builder()
+            .uri(app.get_http_uri(Some("/api/links")))
+            .method(Method::POST)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::from(create_link_input.to_string()))
+            .expect("couldn't create request");
+
+        // Send request
+        let res = app
+            .client
+            .request(req)
+            .await
+            .expect("coudln't send request");
+        // Checking server response
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let body: Value = res
+            .json_from_body()
+            .await
+            .expect("couldn't get json from body");
+
+        assert_eq!(body["error"]["error"]["code"], code);
+        assert_eq!(body["error"]["error"]["fields"][field], "already taken");
+    }
+}
+
+#[tokio::test]
+async fn create_link_handler_with_tags() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    // Tags are given as a list or as a comma separated string
+    let cases = [
+        (json!(["docs", "q1-2023"]), StatusCode::OK, json!(["docs", "q1-2023"])),
+        (json!("docs, blog"), StatusCode::OK, json!(["docs", "blog"])),
+        (json!(["Docs"]), StatusCode::BAD_REQUEST, Value::Null),
+        (json!(["docs", "docs"]), StatusCode::BAD_REQUEST, Value::Null),
+    ];
+    for (index, (tags, status, expected)) in cases.into_iter().enumerate() {
+        let create_link_input = json!({
+            "name": format!("tagged link {index}"),
+            "redirect_to": "https://example.com",
+            "tags": tags,
+        });
+
+        // Create request
+        let req = Request::builder()
+            .uri(app.get_http_uri(Some("/api/links")))
+            .method(Method::POST)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::from(create_link_input.to_string()))
+            .expect("couldn't create request");
+
+        // Send request
+        let res = app
+            .client
+            .request(req)
+            .await
+            .expect("coudln't send request");
+        // Checking server response
+        assert_eq!(res.status(), status);
+
+        let body: Value = res
+            .json_from_body()
+            .await
+            .expect("couldn't get json from body");
+
+        if status == StatusCode::OK {
+            assert_eq!(body["data"]["link"]["tags"], expected);
+        } else {
+            assert_eq!(body["error"]["error"]["fields"]["tags"], "invalid tags");
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use axum::http::StatusCode;
 use hyper::{Body, Method, Request};
+use serde_json::json;
 
 use crate::{
     helpers::server::TestApp,
-    seeds::{links::seed_one_link_for_user, users::seed_one_local_user},
+    seeds::{
+        links::{seed_links_for_user, seed_one_link_for_user},
+        users::seed_one_local_user,
+    },
 };
 
 #[tokio::test]
@@ -25,7 +29,7 @@ async fn delete_link_handler_with_success() {
     let mut req = Request::builder()
         .uri(app.get_http_uri(Some(path)))
         .method(Method::DELETE)
-        .header("Authorization", format!("Bearer {}",&token))
+        .header("Authorization", format!("Bearer {}", &token))
         .body(Body::empty())
         .expect("couldn't create request");
 
@@ -43,7 +47,7 @@ async fn delete_link_handler_with_success() {
     req = Request::builder()
         .uri(app.get_http_uri(Some(path)))
         .method(Method::GET)
-        .header("Authorization", format!("Bearer {}",&token))
+        .header("Authorization", format!("Bearer {}", &token))
         .body(Body::empty())
         .expect("couldn't create request");
 
@@ -57,3 +61,91 @@ async fn delete_link_handler_with_success() {
     // Checking the status code is 404 (not found)
     assert_eq!(res.status(), StatusCode::NOT_FOUND);
 }
+
+#[tokio::test]
+async fn delete_link_handler_with_several_links() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Seed database with links to delete
+    let links = seed_links_for_user(&app.database, &user.id, 3).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    for link in links {
+        // Create request
+        let path = &format!("/api/links/{}", &link.id);
+        let req = Request::builder()
+            .uri(app.get_http_uri(Some(path)))
+            .method(Method::DELETE)
+            .header("Authorization", format!("Bearer {}", &token))
+            .body(Body::empty())
+            .expect("couldn't create request");
+
+        // Send request
+        let res = app
+            .client
+            .request(req)
+            .await
+            .expect("coudln't send request");
+
+        // Checking server response
+        assert!(res.status().is_success());
+    }
+}
+
+#[tokio::test]
+async fn deleted_link_slug_can_be_reused() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Seed database with a link to delete
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    // Delete the link
+    let path = &format!("/api/links/{}", &link.id);
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(path)))
+        .method(Method::DELETE)
+        .header("Authorization", format!("Bearer {}", &token))
+        .body(Body::empty())
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+    assert!(res.status().is_success());
+
+    // Create a new link with the same name and slug
+    let create_link_input = json!({
+        "name": &link.name,
+        "slug": &link.slug,
+        "redirect_to": "https://google.com",
+    });
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some("/api/links")))
+        .method(Method::POST)
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {}", &token))
+        .body(Body::from(create_link_input.to_string()))
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+
+    // Checking server response
+    assert_eq!(res.status(), StatusCode::OK);
+}
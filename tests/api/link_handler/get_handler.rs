@@ -1,6 +1,7 @@
 use assert_json_diff::{assert_json_eq, assert_json_include};
 use hyper::{Body, Method, Request};
-use lib::dto::url::Url;
+use lib::{dto::url::Url, entity::url};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, IntoActiveModel};
 use serde_json::{json, Value};
 
 use crate::{
@@ -99,3 +100,73 @@ async fn get_one_link_handler_with_success() {
     let expected_data = json!({ "link": link});
     assert_json_eq!(data, expected_data);
 }
+
+#[tokio::test]
+async fn get_links_handler_filters_by_state() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user and a link in each state
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let now = chrono::Utc::now().naive_utc();
+    let day = chrono::Duration::days(1);
+
+    let active_link = seed_one_link_for_user(&app.database, &user.id).await;
+
+    let scheduled_link = seed_one_link_for_user(&app.database, &user.id).await;
+    let mut scheduled_link: url::ActiveModel = scheduled_link.into_active_model();
+    scheduled_link.active_from = Set(Some(now + day));
+    let scheduled_link = scheduled_link
+        .update(&app.database)
+        .await
+        .expect("couldn't schedule link");
+
+    let expired_link = seed_one_link_for_user(&app.database, &user.id).await;
+    let mut expired_link: url::ActiveModel = expired_link.into_active_model();
+    expired_link.expires_at = Set(Some(now - day));
+    let expired_link = expired_link
+        .update(&app.database)
+        .await
+        .expect("couldn't expire link");
+
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    for (state, link) in [
+        ("active", active_link),
+        ("scheduled", scheduled_link),
+        ("expired", expired_link),
+    ] {
+        // Create request
+        let path = &format!("/api/links?state={state}");
+        let req = Request::builder()
+            .uri(app.get_http_uri(Some(path)))
+            .method(Method::GET)
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .expect("couldn't create request");
+
+        // Send request
+        let res = app
+            .client
+            .request(req)
+            .await
+            .expect("coudln't send request");
+        // Checking server response
+        assert!(res.status().is_success());
+
+        let body: Value = res
+            .json_from_body()
+            .await
+            .expect("couldn't get json from body");
+
+        let links = body["data"]["links"]
+            .as_array()
+            .expect("couldn't get links");
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0]["id"], link.id.to_string());
+        assert_eq!(links[0]["state"], state);
+    }
+}
@@ -0,0 +1,306 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use hyper::{Body, Method, Request, Response};
+use lib::entity::url;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use serde_json::Value;
+
+use crate::{
+    helpers::{server::TestApp, ParseJson},
+    seeds::{links::seed_links_for_user, users::seed_one_local_user},
+};
+
+async fn import_links(
+    app: &TestApp,
+    token: &str,
+    query: &str,
+    content_type: &str,
+    body: &str,
+) -> Response<Body> {
+    let path = &format!("/api/links/import?{query}");
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(path)))
+        .method(Method::POST)
+        .header("Authorization", format!("Bearer {token}"))
+        .header("Content-Type", content_type)
+        .body(Body::from(body.to_owned()))
+        .expect("couldn't create request");
+
+    app.client
+        .request(req)
+        .await
+        .expect("coudln't send request")
+}
+
+#[tokio::test]
+async fn import_links_handler_dry_run_reports_every_row() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    let csv = "\
+name,slug,redirect_to,max_clicks
+first link,first-link,https://example.com/1,
+second link,,not a url,
+first link,other-link,https://example.com/3,
+fourth link,first-link,https://example.com/4,
+fifth link,fifth-link,https://example.com/5,not a number
+sixth link,,https://example.com/6,3
+";
+    let res = import_links(&app, &token, "", "text/csv", csv).await;
+
+    // Checking server response
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body: Value = res
+        .json_from_body()
+        .await
+        .expect("couldn't get json from body");
+    let data = &body["data"];
+    assert_eq!(data["mode"], "dry_run");
+    assert_eq!(data["valid"], 2);
+    assert_eq!(data["invalid"], 4);
+    assert_eq!(data["created"], 0);
+
+    let rows = data["rows"].as_array().expect("couldn't get rows");
+    let lines: Vec<_> = rows.iter().map(|row| row["line"].as_u64()).collect();
+    assert_eq!(lines, [2, 3, 4, 5, 6, 7].map(Some));
+    let statuses: Vec<_> = rows.iter().map(|row| row["status"].as_str()).collect();
+    assert_eq!(
+        statuses,
+        ["valid", "invalid", "invalid", "invalid", "invalid", "valid"].map(Some)
+    );
+    assert_eq!(rows[1]["error"]["fields"]["redirect_to"], "invalid url");
+    assert_eq!(rows[2]["error"]["code"], "name_taken");
+    assert_eq!(rows[3]["error"]["code"], "slug_taken");
+    assert_eq!(rows[4]["error"]["code"], "malformed_row");
+
+    // Nothing was written
+    let links = url::Entity::find()
+        .filter(url::Column::OwnerId.eq(user.id))
+        .all(&app.database)
+        .await
+        .expect("couldn't get links");
+    assert!(links.is_empty());
+}
+
+#[tokio::test]
+async fn import_links_handler_commits_all_rows_or_none() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    // A single invalid row keeps the others from being created
+    let rows = r#"{"name": "first link", "slug": "first-link", "redirect_to": "https://example.com/1"}
+{"name": "second link", "redirect_to": "https://example.com/2", "password": "hunter22"}
+{"name": "third link", "redirect_to": "https://example.com/3", "max_clicks": 0}
+"#;
+    let res = import_links(&app, &token, "mode=commit", "application/x-ndjson", rows).await;
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body: Value = res
+        .json_from_body()
+        .await
+        .expect("couldn't get json from body");
+    let report = &body["error"]["error"];
+    assert_eq!(report["valid"], 2);
+    assert_eq!(report["invalid"], 1);
+    assert_eq!(report["rows"][2]["error"]["fields"]["max_clicks"], "invalid range");
+
+    let links = url::Entity::find()
+        .filter(url::Column::OwnerId.eq(user.id))
+        .all(&app.database)
+        .await
+        .expect("couldn't get links");
+    assert!(links.is_empty());
+
+    // Once fixed every row is created
+    let rows = rows.replace(r#""max_clicks": 0"#, r#""max_clicks": 5"#);
+    let res = import_links(&app, &token, "mode=commit&format=json", "text/plain", &rows).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body: Value = res
+        .json_from_body()
+        .await
+        .expect("couldn't get json from body");
+    let data = &body["data"];
+    assert_eq!(data["created"], 3);
+    assert_eq!(data["rows"][0]["link"]["slug"], "first-link");
+    assert_eq!(data["rows"][1]["link"]["has_password"], true);
+    assert_eq!(data["rows"][2]["link"]["max_clicks"], 5);
+
+    let links = url::Entity::find()
+        .filter(url::Column::OwnerId.eq(user.id))
+        .all(&app.database)
+        .await
+        .expect("couldn't get links");
+    assert_eq!(links.len(), 3);
+
+    // Importing the same rows again conflicts with the links just created
+    let res = import_links(&app, &token, "mode=commit", "application/x-ndjson", &rows).await;
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn import_links_handler_with_unsupported_format() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    let res = import_links(&app, &token, "", "application/xml", "<links/>").await;
+
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn export_links_handler_streams_every_link() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user and a few links
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let links = seed_links_for_user(&app.database, &user.id, 3).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    for (format, content_type) in [("csv", "text/csv"), ("json", "application/x-ndjson")] {
+        // Create request
+        let path = &format!("/api/links/export?format={format}");
+        let req = Request::builder()
+            .uri(app.get_http_uri(Some(path)))
+            .method(Method::GET)
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .expect("couldn't create request");
+
+        // Send request
+        let res = app
+            .client
+            .request(req)
+            .await
+            .expect("coudln't send request");
+
+        // Checking server response
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], content_type);
+
+        let body = hyper::body::to_bytes(res.into_body())
+            .await
+            .expect("couldn't read body");
+        let body = String::from_utf8(body.to_vec()).expect("export isn't utf-8");
+        let mut lines: Vec<&str> = body.lines().collect();
+
+        if format == "csv" {
+            assert!(lines.remove(0).starts_with("name,slug,redirect_to,"));
+        }
+        assert_eq!(lines.len(), links.len());
+        for link in &links {
+            assert!(lines.iter().any(|line| line.contains(&link.slug)));
+        }
+    }
+}
+
+#[tokio::test]
+async fn import_links_handler_takes_back_expired_links_from_an_export() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    for (format, content_type) in [("csv", "text/csv"), ("json", "application/x-ndjson")] {
+        // Seed database with a link that has expired
+        let link = seed_links_for_user(&app.database, &user.id, 1)
+            .await
+            .remove(0);
+        let expires_at = (Utc::now() - Duration::days(1)).naive_utc();
+        let mut expired: url::ActiveModel = link.clone().into();
+        expired.expires_at = Set(Some(expires_at));
+        expired.active_from = Set(None);
+        expired
+            .update(&app.database)
+            .await
+            .expect("couldn't expire link");
+
+        // Export it
+        let path = &format!("/api/links/export?format={format}");
+        let req = Request::builder()
+            .uri(app.get_http_uri(Some(path)))
+            .method(Method::GET)
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .expect("couldn't create request");
+        let res = app
+            .client
+            .request(req)
+            .await
+            .expect("coudln't send request");
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body())
+            .await
+            .expect("couldn't read body");
+        let export = String::from_utf8(body.to_vec()).expect("export isn't utf-8");
+
+        // Free its slug and name, then import it back
+        url::Entity::delete_by_id(link.id)
+            .exec(&app.database)
+            .await
+            .expect("couldn't delete link");
+        let res = import_links(&app, &token, "mode=commit", content_type, &export).await;
+
+        // Checking server response
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body: Value = res
+            .json_from_body()
+            .await
+            .expect("couldn't get json from body");
+        assert_eq!(body["data"]["created"], 1);
+        let imported = &body["data"]["rows"][0]["link"];
+        assert_eq!(imported["slug"], link.slug);
+        assert_eq!(imported["is_expired"], true);
+
+        let imported = url::Entity::find()
+            .filter(url::Column::Slug.eq(link.slug))
+            .one(&app.database)
+            .await
+            .expect("couldn't get link")
+            .expect("link wasn't imported");
+        assert_eq!(
+            imported.expires_at.map(|date| date.and_utc().timestamp()),
+            Some(expires_at.and_utc().timestamp())
+        );
+
+        url::Entity::delete_by_id(imported.id)
+            .exec(&app.database)
+            .await
+            .expect("couldn't delete link");
+    }
+}
@@ -0,0 +1,229 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use hyper::{Body, Method, Request};
+use lib::entity::url;
+use sea_orm::{prelude::Uuid, ActiveModelTrait, ActiveValue::Set};
+use serde_json::Value;
+
+use crate::{
+    helpers::{server::TestApp, ParseJson},
+    seeds::users::seed_one_local_user,
+};
+
+// Three links told apart by every filter and sort of the list
+async fn seed_searchable_links(app: &TestApp, owner_id: Uuid) -> [url::Model; 3] {
+    let now = Utc::now().naive_utc();
+    let links = [
+        ("alpha docs", "alpha-1", "https://docs.example.com/a", vec!["docs"], 3, 5),
+        ("beta blog", "beta-22", "https://blog.other.org/", vec!["blog", "docs"], 2, 50),
+        ("gamma", "gamma_3", "https://example.com/100%", vec![], 1, 0),
+    ];
+
+    let mut models = Vec::new();
+    for (name, slug, redirect_to, tags, days_ago, click_count) in links {
+        let link = url::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            name: Set(name.to_owned()),
+            slug: Set(slug.to_owned()),
+            redirect_to: Set(redirect_to.to_owned()),
+            owner_id: Set(owner_id),
+            created_at: Set(now - Duration::days(days_ago)),
+            tags: Set(tags.into_iter().map(str::to_owned).collect()),
+            click_count: Set(click_count),
+            ..Default::default()
+        };
+        models.push(link.insert(&app.database).await.expect("couldn't insert link"));
+    }
+
+    models.try_into().expect("three links were seeded")
+}
+
+async fn list_links(app: &TestApp, token: &str, query: &str) -> (StatusCode, Value) {
+    let path = &format!("/api/links?{query}");
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(path)))
+        .method(Method::GET)
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .expect("couldn't create request");
+
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+    let status = res.status();
+    let body = res.json_from_body().await.unwrap_or(Value::Null);
+
+    (status, body)
+}
+
+fn slugs(body: &Value) -> Vec<&str> {
+    body["data"]["links"]
+        .as_array()
+        .expect("couldn't get links")
+        .iter()
+        .filter_map(|link| link["slug"].as_str())
+        .collect()
+}
+
+#[tokio::test]
+async fn get_links_handler_searches_and_filters() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user and the links to look for
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    seed_searchable_links(&app, user.id).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    let created_after = (Utc::now() - Duration::hours(60)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let cases = [
+        ("q=ALPHA".to_owned(), vec!["alpha-1"]),
+        ("q=blog.other".to_owned(), vec!["beta-22"]),
+        // Wildcards are searched for literally
+        ("q=100%25".to_owned(), vec!["gamma_3"]),
+        ("q=_".to_owned(), vec!["gamma_3"]),
+        ("tag=docs".to_owned(), vec!["beta-22", "alpha-1"]),
+        ("domain=example.com".to_owned(), vec!["gamma_3", "alpha-1"]),
+        ("domain=Other.org".to_owned(), vec!["beta-22"]),
+        ("domain=ther.org".to_owned(), vec![]),
+        (format!("created_after={created_after}"), vec!["gamma_3", "beta-22"]),
+        ("tag=docs&q=alpha".to_owned(), vec!["alpha-1"]),
+    ];
+    for (query, expected) in cases {
+        let (status, body) = list_links(&app, &token, &query).await;
+
+        assert_eq!(status, StatusCode::OK, "{query}");
+        assert_eq!(slugs(&body), expected, "{query}");
+    }
+}
+
+#[tokio::test]
+async fn get_links_handler_sorts_links() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user and the links to sort
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let [alpha, ..] = seed_searchable_links(&app, user.id).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    // The oldest link is the last one updated
+    let mut alpha: url::ActiveModel = alpha.into();
+    alpha.updated_at = Set(Some(Utc::now().naive_utc()));
+    alpha.update(&app.database).await.expect("couldn't update link");
+
+    let cases = [
+        ("", vec!["gamma_3", "beta-22", "alpha-1"]),
+        ("order=asc", vec!["alpha-1", "beta-22", "gamma_3"]),
+        ("sort=name&order=asc", vec!["alpha-1", "beta-22", "gamma_3"]),
+        ("sort=click_count", vec!["beta-22", "alpha-1", "gamma_3"]),
+        ("sort=updated_at", vec!["alpha-1", "gamma_3", "beta-22"]),
+    ];
+    for (query, expected) in cases {
+        let (status, body) = list_links(&app, &token, query).await;
+
+        assert_eq!(status, StatusCode::OK, "{query}");
+        assert_eq!(slugs(&body), expected, "{query}");
+    }
+}
+
+#[tokio::test]
+async fn get_links_handler_rejects_invalid_filters() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    let cases = [
+        ("tag=Not%20a%20tag", "tag"),
+        ("domain=example.com/path", "domain"),
+        ("q=", "q"),
+        (
+            "created_after=2023-02-01T00:00:00Z&created_before=2023-01-01T00:00:00Z",
+            "created_before",
+        ),
+        ("limit=101", "limit"),
+        ("limit=0", "limit"),
+        ("cursor=not-a-cursor", "cursor"),
+    ];
+    for (query, field) in cases {
+        let (status, body) = list_links(&app, &token, query).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+        assert!(body["error"]["error"]["fields"][field].is_string(), "{query}");
+    }
+
+    // Unknown sorts are rejected when parsing the query
+    let (status, _) = list_links(&app, &token, "sort=owner_id").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn get_links_handler_pages_with_cursors() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user and the links to page through
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    seed_searchable_links(&app, user.id).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    let (status, first) = list_links(&app, &token, "limit=1").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(slugs(&first), vec!["gamma_3"]);
+    assert_eq!(first["data"]["total"], 3);
+    assert!(first["data"]["prev_cursor"].is_null());
+
+    // A link created while paging doesn't shift the next pages
+    url::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        name: Set("delta".to_owned()),
+        slug: Set("delta".to_owned()),
+        redirect_to: Set("https://example.com/".to_owned()),
+        owner_id: Set(user.id),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(&app.database)
+    .await
+    .expect("couldn't insert link");
+
+    let next = first["data"]["next_cursor"].as_str().expect("no next cursor");
+    let (status, second) = list_links(&app, &token, &format!("limit=1&cursor={next}")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(slugs(&second), vec!["beta-22"]);
+    assert_eq!(second["data"]["total"], 4);
+
+    let next = second["data"]["next_cursor"].as_str().expect("no next cursor");
+    let (_, last) = list_links(&app, &token, &format!("limit=1&cursor={next}")).await;
+    assert_eq!(slugs(&last), vec!["alpha-1"]);
+    assert!(last["data"]["next_cursor"].is_null());
+
+    // Paging back returns the same links
+    let prev = last["data"]["prev_cursor"].as_str().expect("no prev cursor");
+    let (_, back) = list_links(&app, &token, &format!("limit=2&cursor={prev}")).await;
+    assert_eq!(slugs(&back), vec!["gamma_3", "beta-22"]);
+    assert!(back["data"]["prev_cursor"].is_string());
+    assert!(back["data"]["next_cursor"].is_string());
+
+    // Cursors are tied to the sort they were issued for
+    let (status, body) = list_links(&app, &token, &format!("sort=name&cursor={prev}")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"]["error"]["fields"]["cursor"].is_string());
+}
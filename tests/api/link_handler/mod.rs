@@ -2,3 +2,9 @@ mod create_handler;
 mod get_handler;
 mod update_handler;
 mod delete_handler;
+mod stats_handler;
+mod trash_handler;
+mod qr_handler;
+mod import_handler;
+mod batch_handler;
+mod list_handler;
@@ -0,0 +1,200 @@
+use axum::http::StatusCode;
+use hyper::{Body, Method, Request};
+use serde_json::Value;
+
+use crate::{
+    helpers::{server::TestApp, ParseJson},
+    seeds::{links::seed_one_link_for_user, users::seed_one_local_user},
+};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[tokio::test]
+async fn get_link_qr_handler_with_success() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Seed database with a link to share
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    // The format query takes precedence over the Accept header, svg is the default
+    let cases = [
+        ("", None, "image/svg+xml"),
+        ("", Some("image/png"), "image/png"),
+        ("?format=svg", Some("image/png"), "image/svg+xml"),
+        (
+            "?format=png&size=128&margin=2&error_correction=H&foreground=%23112233&background=fafafa",
+            None,
+            "image/png",
+        ),
+    ];
+    for (query, accept, content_type) in cases {
+        // Create request
+        let path = &format!("/api/links/{}/qr{query}", &link.id);
+        let mut req = Request::builder()
+            .uri(app.get_http_uri(Some(path)))
+            .method(Method::GET)
+            .header("Authorization", format!("Bearer {token}"));
+        if let Some(accept) = accept {
+            req = req.header("Accept", accept);
+        }
+        let req = req.body(Body::empty()).expect("couldn't create request");
+
+        // Send request
+        let res = app
+            .client
+            .request(req)
+            .await
+            .expect("coudln't send request");
+
+        // Checking server response
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], content_type);
+
+        let body = hyper::body::to_bytes(res.into_body())
+            .await
+            .expect("couldn't read body");
+        if content_type == "image/png" {
+            assert!(body.starts_with(PNG_SIGNATURE));
+        } else {
+            let svg = String::from_utf8(body.to_vec()).expect("svg isn't utf-8");
+            assert!(svg.starts_with("<svg"));
+            assert!(svg.contains("viewBox"));
+        }
+    }
+}
+
+#[tokio::test]
+async fn get_link_qr_handler_rejects_invalid_options() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Seed database with a link to share
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    for query in ["foreground=red", "size=16", "margin=100"] {
+        // Create request
+        let path = &format!("/api/links/{}/qr?{query}", &link.id);
+        let req = Request::builder()
+            .uri(app.get_http_uri(Some(path)))
+            .method(Method::GET)
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .expect("couldn't create request");
+
+        // Send request
+        let res = app
+            .client
+            .request(req)
+            .await
+            .expect("coudln't send request");
+
+        // Checking server response
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let body: Value = res
+            .json_from_body()
+            .await
+            .expect("couldn't get json from body");
+        assert_eq!(body["error"]["message"], "invalid data from client");
+    }
+}
+
+#[tokio::test]
+async fn get_link_qr_handler_for_another_user_link() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with two users
+    let (user1, password1) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (user2, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Seed database with a link owned by the second user
+    let link = seed_one_link_for_user(&app.database, &user2.id).await;
+    // Get token by logging in
+    let token = app.login_user(&user1.username, &password1).await;
+
+    // Create request
+    let path = &format!("/api/links/{}/qr", &link.id);
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(path)))
+        .method(Method::GET)
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .expect("couldn't create request");
+
+    // Send request
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+
+    // Checking server response
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn get_link_qr_handler_only_trusts_forwarded_host_from_proxies() {
+    for trusted in [false, true] {
+        // Run server
+        let mut app = TestApp::new().await;
+        if trusted {
+            app.trust_local_proxy();
+        }
+        app.spawn_server().await;
+
+        // Seed database with one user
+        let (user, password) =
+            seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+        // Seed database with a link to share
+        let link = seed_one_link_for_user(&app.database, &user.id).await;
+        // Get token by logging in
+        let token = app.login_user(&user.username, &password).await;
+
+        let mut svgs = vec![];
+        for forwarded_host in [None, Some("phishing.example")] {
+            // Create request
+            let path = &format!("/api/links/{}/qr", &link.id);
+            let mut req = Request::builder()
+                .uri(app.get_http_uri(Some(path)))
+                .method(Method::GET)
+                .header("Authorization", format!("Bearer {token}"));
+            if let Some(forwarded_host) = forwarded_host {
+                req = req
+                    .header("X-Forwarded-Host", forwarded_host)
+                    .header("X-Forwarded-Proto", "https");
+            }
+            let req = req.body(Body::empty()).expect("couldn't create request");
+
+            // Send request
+            let res = app
+                .client
+                .request(req)
+                .await
+                .expect("coudln't send request");
+            assert_eq!(res.status(), StatusCode::OK);
+
+            let body = hyper::body::to_bytes(res.into_body())
+                .await
+                .expect("couldn't read body");
+            svgs.push(body);
+        }
+
+        // The code only points somewhere else when a proxy forwarded the request
+        assert_eq!(svgs[0] != svgs[1], trusted);
+    }
+}
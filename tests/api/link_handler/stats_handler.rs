@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use hyper::{Body, Method, Request};
+use serde_json::Value;
+
+use crate::{
+    helpers::{server::TestApp, ParseJson},
+    seeds::{links::seed_one_link_for_user, users::seed_one_local_user},
+};
+
+const CHROME_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/108.0.0.0 Safari/537.36";
+const FIREFOX_USER_AGENT: &str =
+    "Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:107.0) Gecko/20100101 Firefox/107.0";
+
+#[tokio::test]
+async fn get_link_stats_handler_with_success() {
+    // Run server, behind a proxy telling the visitors apart
+    let mut app = TestApp::new().await;
+    app.trust_local_proxy();
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Seed database with a link to visit
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    // Visit the link from two different clients
+    let visits = [
+        (CHROME_USER_AGENT, "10.0.0.1", "https://twitter.com/"),
+        (CHROME_USER_AGENT, "10.0.0.1", "https://twitter.com/"),
+        (
+            FIREFOX_USER_AGENT,
+            "10.0.0.2",
+            "https://news.ycombinator.com/",
+        ),
+    ];
+    for (user_agent, ip, referrer) in visits {
+        let req = Request::builder()
+            .uri(app.get_http_uri(Some(&format!("/{}", &link.slug))))
+            .method(Method::GET)
+            .header("User-Agent", user_agent)
+            .header("X-Forwarded-For", ip)
+            .header("Referer", referrer)
+            .body(Body::empty())
+            .expect("couldn't create request");
+        let res = app
+            .client
+            .request(req)
+            .await
+            .expect("coudln't send request");
+        assert!(res.status().is_redirection());
+    }
+
+    // Clicks are written in the background, wait for them to be flushed
+    let mut stats = Value::Null;
+    for _ in 0..20 {
+        stats = get_link_stats(&app, &token, &link.id.to_string()).await;
+        if stats["total_clicks"] == 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    assert_eq!(stats["total_clicks"], 3);
+    assert_eq!(stats["unique_visitors"], 2);
+    assert_eq!(stats["interval"], "hour");
+
+    let series_clicks: i64 = stats["series"]
+        .as_array()
+        .expect("series should be an array")
+        .iter()
+        .map(|bucket| bucket["clicks"].as_i64().unwrap())
+        .sum();
+    assert_eq!(series_clicks, 3);
+
+    assert_eq!(stats["top_browsers"][0]["value"], "Chrome");
+    assert_eq!(stats["top_browsers"][0]["clicks"], 2);
+    assert_eq!(stats["top_referrers"][0]["value"], "https://twitter.com/");
+}
+
+#[tokio::test]
+async fn get_link_stats_handler_with_wrong_owner() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with two users
+    let (user1, password1) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (user2, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Seed database with a link owned by the second user
+    let link = seed_one_link_for_user(&app.database, &user2.id).await;
+    // Get token by logging in
+    let token = app.login_user(&user1.username, &password1).await;
+
+    // Create request
+    let path = &format!("/api/links/{}/stats", &link.id);
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(path)))
+        .method(Method::GET)
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .expect("couldn't create request");
+
+    // Send request
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+
+    // Checking server response
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+async fn get_link_stats(app: &TestApp, token: &str, link_id: &str) -> Value {
+    // Create request
+    let path = &format!("/api/links/{}/stats?interval=hour", link_id);
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(path)))
+        .method(Method::GET)
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .expect("couldn't create request");
+
+    // Send request
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+
+    // Checking server response
+    assert!(res.status().is_success());
+
+    let body: Value = res
+        .json_from_body()
+        .await
+        .expect("couldn't get json from body");
+
+    assert!(body["error"].is_null());
+
+    body["data"]["stats"].to_owned()
+}
@@ -0,0 +1,187 @@
+use axum::http::StatusCode;
+use hyper::{Body, Method, Request};
+use lib::{entity::url, jobs::purge_trash};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, EntityTrait, IntoActiveModel};
+use serde_json::Value;
+
+use crate::{
+    helpers::{server::TestApp, ParseJson},
+    seeds::{links::seed_one_link_for_user, users::seed_one_local_user},
+};
+
+async fn trash_link(app: &TestApp, link: url::Model, deleted_at: chrono::NaiveDateTime) {
+    let mut link = link.into_active_model();
+    link.deleted_at = Set(Some(deleted_at));
+    link.update(&app.database)
+        .await
+        .expect("couldn't move link to the trash");
+}
+
+async fn send_request(
+    app: &TestApp,
+    method: Method,
+    path: &str,
+    token: &str,
+) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(path)))
+        .method(method)
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .expect("couldn't create request");
+
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+    let status = res.status();
+    let body = res.json_from_body().await.unwrap_or(Value::Null);
+
+    (status, body)
+}
+
+#[tokio::test]
+async fn trash_handler_lists_deleted_links() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user, one live link and one deleted link
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    seed_one_link_for_user(&app.database, &user.id).await;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    let token = app.login_user(&user.username, &password).await;
+
+    let path = format!("/api/links/{}", link.id);
+    let (status, _) = send_request(&app, Method::DELETE, &path, &token).await;
+    assert!(status.is_success());
+
+    let (status, body) = send_request(&app, Method::GET, "/api/links/trash", &token).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let links = body["data"]["links"]
+        .as_array()
+        .expect("trash should contain a list of links");
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0]["id"], link.id.to_string());
+    assert!(links[0]["deleted_at"].is_string());
+    assert!(links[0]["purge_at"].is_string());
+}
+
+#[tokio::test]
+async fn restore_handler_with_success() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user and a deleted link
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    let link_id = link.id;
+    trash_link(&app, link, chrono::Utc::now().naive_utc()).await;
+    let token = app.login_user(&user.username, &password).await;
+
+    let path = format!("/api/links/{link_id}/restore");
+    let (status, body) = send_request(&app, Method::POST, &path, &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["link"]["id"], link_id.to_string());
+
+    // The link is live again
+    let path = format!("/api/links/{link_id}");
+    let (status, _) = send_request(&app, Method::GET, &path, &token).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn restore_handler_refuses_when_slug_was_taken() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with two users, the second one reusing the slug of a deleted link
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (other_user, _) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    let (link_id, slug) = (link.id, link.slug.clone());
+    trash_link(&app, link, chrono::Utc::now().naive_utc()).await;
+
+    let other_link = seed_one_link_for_user(&app.database, &other_user.id).await;
+    let mut other_link = other_link.into_active_model();
+    other_link.slug = Set(slug);
+    other_link
+        .update(&app.database)
+        .await
+        .expect("couldn't reuse slug");
+
+    let token = app.login_user(&user.username, &password).await;
+
+    let path = format!("/api/links/{link_id}/restore");
+    let (status, body) = send_request(&app, Method::POST, &path, &token).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["error"]["code"], "slug_taken");
+}
+
+#[tokio::test]
+async fn delete_handler_permanently_removes_link() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user and a deleted link
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    let link_id = link.id;
+    trash_link(&app, link, chrono::Utc::now().naive_utc()).await;
+    let token = app.login_user(&user.username, &password).await;
+
+    let path = format!("/api/links/{link_id}?permanent=true");
+    let (status, _) = send_request(&app, Method::DELETE, &path, &token).await;
+    assert!(status.is_success());
+
+    let link = url::Entity::find_by_id(link_id)
+        .one(&app.database)
+        .await
+        .expect("couldn't query link");
+    assert!(link.is_none());
+}
+
+#[tokio::test]
+async fn purge_trash_removes_links_past_retention() {
+    let app = TestApp::new().await;
+
+    // Seed database with one user, a link trashed long ago and one trashed just now
+    let (user, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let retention = app.config.application.trash.retention();
+    let now = chrono::Utc::now().naive_utc();
+
+    let old_link = seed_one_link_for_user(&app.database, &user.id).await;
+    let old_link_id = old_link.id;
+    trash_link(&app, old_link, now - retention - chrono::Duration::days(1)).await;
+
+    let recent_link = seed_one_link_for_user(&app.database, &user.id).await;
+    let recent_link_id = recent_link.id;
+    trash_link(&app, recent_link, now).await;
+
+    let purged = purge_trash(&app.database, retention)
+        .await
+        .expect("couldn't purge trash");
+    assert_eq!(purged, 1);
+
+    let old_link = url::Entity::find_by_id(old_link_id)
+        .one(&app.database)
+        .await
+        .expect("couldn't query link");
+    assert!(old_link.is_none());
+
+    let recent_link = url::Entity::find_by_id(recent_link_id)
+        .one(&app.database)
+        .await
+        .expect("couldn't query link");
+    assert!(recent_link.is_some());
+}
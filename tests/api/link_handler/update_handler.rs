@@ -1,5 +1,7 @@
 use assert_json_diff::assert_json_include;
 use hyper::{Body, Method, Request};
+use lib::entity::url;
+use sea_orm::{ActiveModelTrait, Set};
 use serde_json::{json, Value};
 
 use crate::{
@@ -115,3 +117,202 @@ async fn update_link_handler_with_wrong_owner() {
     // Checking server response
     assert!(res.status().is_client_error());
 }
+
+#[tokio::test]
+async fn update_link_handler_sets_and_clears_expiration() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Seed database with a link to update
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    let expires_at = chrono::Utc::now() + chrono::Duration::days(7);
+    let test_cases = [
+        (
+            json!({
+                "expires_at": expires_at,
+                "expired_redirect_to": "https://example.com",
+            }),
+            json!({
+                "expired_redirect_to": "https://example.com",
+                "is_expired": false,
+            }),
+        ),
+        // Explicit nulls remove the expiration, missing fields are left alone
+        (
+            json!({ "expires_at": null }),
+            json!({
+                "expires_at": null,
+                "expired_redirect_to": "https://example.com",
+                "is_expired": false,
+            }),
+        ),
+    ];
+
+    for (update_link_input, expected_link) in test_cases {
+        // Create request
+        let path = &format!("/api/links/{}", &link.id);
+        let req = Request::builder()
+            .uri(app.get_http_uri(Some(path)))
+            .method(Method::PUT)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::from(update_link_input.to_string()))
+            .expect("couldn't create request");
+
+        // Send request
+        let res = app
+            .client
+            .request(req)
+            .await
+            .expect("coudln't send request");
+        // Checking server response
+        assert!(res.status().is_success());
+
+        let body: Value = res
+            .json_from_body()
+            .await
+            .expect("couldn't get json from body");
+
+        assert_json_include!(actual: body["data"]["link"], expected: expected_link);
+    }
+}
+
+#[tokio::test]
+async fn update_link_handler_carries_used_clicks_over() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Seed database with a link that was visited twice out of three times
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    let mut link: url::ActiveModel = link.into();
+    link.max_clicks = Set(Some(3));
+    link.remaining_clicks = Set(Some(1));
+    let link = link
+        .update(&app.database)
+        .await
+        .expect("couldn't update link");
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    let test_cases = [
+        (
+            json!({ "max_clicks": 5 }),
+            json!({ "max_clicks": 5, "remaining_clicks": 3 }),
+        ),
+        (
+            json!({ "max_clicks": 1 }),
+            json!({ "max_clicks": 1, "remaining_clicks": 0 }),
+        ),
+        (
+            json!({ "name": "renamed" }),
+            json!({ "max_clicks": 1, "remaining_clicks": 0 }),
+        ),
+        (
+            json!({ "max_clicks": null }),
+            json!({ "max_clicks": null, "remaining_clicks": null }),
+        ),
+    ];
+
+    for (update_link_input, expected_link) in test_cases {
+        // Create request
+        let path = &format!("/api/links/{}", &link.id);
+        let req = Request::builder()
+            .uri(app.get_http_uri(Some(path)))
+            .method(Method::PUT)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::from(update_link_input.to_string()))
+            .expect("couldn't create request");
+
+        // Send request
+        let res = app
+            .client
+            .request(req)
+            .await
+            .expect("coudln't send request");
+        // Checking server response
+        assert!(res.status().is_success());
+
+        let body: Value = res
+            .json_from_body()
+            .await
+            .expect("couldn't get json from body");
+
+        assert_json_include!(actual: body["data"]["link"], expected: expected_link);
+    }
+}
+
+#[tokio::test]
+async fn update_link_handler_counts_past_visits_against_a_first_limit() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    // Seed database with a link without limit that was visited twice
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    let mut link: url::ActiveModel = link.into();
+    link.click_count = Set(2);
+    let link = link
+        .update(&app.database)
+        .await
+        .expect("couldn't update link");
+    // Get token by logging in
+    let token = app.login_user(&user.username, &password).await;
+
+    let test_cases = [
+        (
+            json!({ "max_clicks": 5 }),
+            json!({ "max_clicks": 5, "remaining_clicks": 3 }),
+        ),
+        (
+            json!({ "max_clicks": null }),
+            json!({ "max_clicks": null, "remaining_clicks": null }),
+        ),
+        (
+            json!({ "max_clicks": 1 }),
+            json!({ "max_clicks": 1, "remaining_clicks": 0 }),
+        ),
+    ];
+
+    for (update_link_input, expected_link) in test_cases {
+        // Create request
+        let path = &format!("/api/links/{}", &link.id);
+        let req = Request::builder()
+            .uri(app.get_http_uri(Some(path)))
+            .method(Method::PUT)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::from(update_link_input.to_string()))
+            .expect("couldn't create request");
+
+        // Send request
+        let res = app
+            .client
+            .request(req)
+            .await
+            .expect("coudln't send request");
+        // Checking server response
+        assert!(res.status().is_success());
+
+        let body: Value = res
+            .json_from_body()
+            .await
+            .expect("couldn't get json from body");
+
+        assert_json_include!(actual: body["data"]["link"], expected: expected_link);
+    }
+}
@@ -1,5 +1,8 @@
+mod click_ingestor;
 mod health_check;
 mod helpers;
 mod link_handler;
+mod oidc_handler;
+mod redirect_handler;
 mod seeds;
 mod user_handler;
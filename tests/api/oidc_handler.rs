@@ -0,0 +1,327 @@
+use hyper::{header, Body, Method, Request, StatusCode};
+use serde_json::{json, Value};
+
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+use lib::{configuration::OidcSettings, entity::user, handler::utils::generate_totp};
+
+use crate::{
+    helpers::{mock_idp::MockIdp, server::TestApp, ParseJson},
+    seeds::users::seed_one_local_user,
+};
+
+async fn spawn_app_with_idp() -> (TestApp, MockIdp) {
+    spawn_app_with_oidc(|_| {}).await
+}
+
+async fn spawn_app_with_oidc(configure: impl FnOnce(&mut OidcSettings)) -> (TestApp, MockIdp) {
+    let idp = MockIdp::spawn();
+    let mut settings = idp.settings();
+    configure(&mut settings);
+    let mut app = TestApp::new().await;
+    app.config.application.oidc = Some(settings);
+    app.spawn_server().await;
+
+    (app, idp)
+}
+
+// Returns the provider url the user is sent to, along with the login cookie
+async fn start_login(app: &TestApp) -> (String, String) {
+    let req = Request::builder()
+        .method(Method::GET)
+        .uri(app.get_http_uri(Some("/api/user/oidc/authorize")))
+        .body(Body::empty())
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request");
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+
+    let location = res.headers()[header::LOCATION].to_str().unwrap().to_owned();
+    let cookie = res.headers()[header::SET_COOKIE]
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_owned();
+
+    (location, cookie)
+}
+
+async fn finish_login(app: &TestApp, cookie: &str, code: &str, state: &str) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(Method::GET)
+        .uri(app.get_http_uri(Some(&format!(
+            "/api/user/oidc/callback?code={code}&state={state}"
+        ))))
+        .header(header::COOKIE, cookie)
+        .body(Body::empty())
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request");
+    let status = res.status();
+    let body = res.json_from_body().await.unwrap_or(Value::Null);
+
+    (status, body)
+}
+
+async fn oidc_login(app: &TestApp, idp: &MockIdp, claims: Value) -> (StatusCode, Value) {
+    let (location, cookie) = start_login(app).await;
+    let (code, state) = idp.approve(&location, claims);
+
+    finish_login(app, &cookie, &code, &state).await
+}
+
+async fn get_me(app: &TestApp, token: &str) -> Value {
+    let req = Request::builder()
+        .method(Method::GET)
+        .uri(app.get_http_uri(Some("/api/user/me")))
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request");
+    assert_eq!(res.status(), StatusCode::OK);
+
+    res.json_from_body()
+        .await
+        .expect("couldn't get json from body")
+}
+
+async fn post_json(app: &TestApp, path: &str, token: Option<&str>, input: Value) -> Value {
+    let mut req = Request::builder()
+        .method(Method::POST)
+        .uri(app.get_http_uri(Some(path)))
+        .header("Content-Type", "application/json");
+    if let Some(token) = token {
+        req = req.header("Authorization", format!("Bearer {token}"));
+    }
+    let req = req
+        .body(Body::from(input.to_string()))
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request");
+    assert_eq!(res.status(), StatusCode::OK, "{path}");
+
+    res.json_from_body()
+        .await
+        .expect("couldn't get json from body")
+}
+
+#[tokio::test]
+async fn oidc_login_creates_the_user_then_signs_it_back_in() {
+    let (app, idp) = spawn_app_with_idp().await;
+    let claims = json!({
+        "sub": "108432576",
+        "email": "jane.doe@example.com",
+        "email_verified": true,
+        "preferred_username": "jane.doe",
+    });
+
+    // The first login creates the user
+    let (status, body) = oidc_login(&app, &idp, claims.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["data"]["refresh_token"].is_string());
+    let me = get_me(&app, body["data"]["token"].as_str().unwrap()).await;
+    assert_eq!(me["data"]["user"]["provider"], "Google");
+    assert_eq!(me["data"]["user"]["username"], "janedoe");
+    let user_id = me["data"]["user"]["id"].clone();
+
+    // The next one finds it back
+    let (status, body) = oidc_login(&app, &idp, claims).await;
+    assert_eq!(status, StatusCode::OK);
+    let me = get_me(&app, body["data"]["token"].as_str().unwrap()).await;
+    assert_eq!(me["data"]["user"]["id"], user_id);
+
+    let users = user::Entity::find()
+        .filter(user::Column::Email.eq("jane.doe@example.com"))
+        .all(&app.database)
+        .await
+        .expect("couldn't get users");
+    assert_eq!(users.len(), 1);
+}
+
+#[tokio::test]
+async fn oidc_login_links_an_existing_account_by_verified_email() {
+    let (app, idp) = spawn_app_with_oidc(|settings| {
+        settings.trusted_link_issuers = vec![settings.issuer_url.clone()];
+    })
+    .await;
+
+    // Seed database with one user
+    let (user, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+
+    // An email the provider didn't verify isn't enough
+    let (status, _) = oidc_login(
+        &app,
+        &idp,
+        json!({ "sub": "2231", "email": user.email, "email_verified": false }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = oidc_login(
+        &app,
+        &idp,
+        json!({ "sub": "2231", "email": user.email, "email_verified": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let me = get_me(&app, body["data"]["token"].as_str().unwrap()).await;
+    assert_eq!(me["data"]["user"]["id"], user.id.to_string());
+
+    let linked_user = user::Entity::find_by_id(user.id)
+        .one(&app.database)
+        .await
+        .expect("couldn't get user")
+        .expect("user should exist");
+    assert_eq!(linked_user.oidc_subject.as_deref(), Some("2231"));
+    assert_eq!(linked_user.oidc_issuer, Some(idp.settings().issuer_url));
+}
+
+#[tokio::test]
+async fn oidc_login_doesnt_link_a_password_account_for_untrusted_issuers() {
+    let (app, idp) = spawn_app_with_idp().await;
+
+    // Seed database with one user
+    let (user, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+
+    let (status, body) = oidc_login(
+        &app,
+        &idp,
+        json!({ "sub": "2231", "email": user.email, "email_verified": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(body["data"].is_null());
+
+    let user = user::Entity::find_by_id(user.id)
+        .one(&app.database)
+        .await
+        .expect("couldn't get user")
+        .expect("user should exist");
+    assert!(user.oidc_subject.is_none());
+}
+
+#[tokio::test]
+async fn oidc_login_asks_for_the_second_factor() {
+    let (app, idp) = spawn_app_with_oidc(|settings| {
+        settings.trusted_link_issuers = vec![settings.issuer_url.clone()];
+    })
+    .await;
+
+    // Seed database with one user with two-factor login enabled
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let token = app.login_user(&user.username, &password).await;
+    let enrollment = post_json(&app, "/api/user/2fa/enroll", Some(&token), json!({})).await;
+    let secret = enrollment["data"]["secret"].as_str().unwrap().to_owned();
+    let code = generate_totp(&secret, chrono::Utc::now().timestamp()).unwrap();
+    post_json(
+        &app,
+        "/api/user/2fa/confirm",
+        Some(&token),
+        json!({ "code": code }),
+    )
+    .await;
+
+    // Signing in through the provider still needs the second factor
+    let claims = json!({ "sub": "5150", "email": user.email, "email_verified": true });
+    let (status, body) = oidc_login(&app, &idp, claims).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["two_factor_required"], true);
+    assert!(body["data"]["token"].is_null());
+    assert!(body["data"]["refresh_token"].is_null());
+
+    // The challenge is completed like the one of a password login, with a code not used yet
+    let code = generate_totp(&secret, chrono::Utc::now().timestamp() + 30).unwrap();
+    let body = post_json(
+        &app,
+        "/api/user/login/2fa",
+        None,
+        json!({ "challenge_token": body["data"]["challenge_token"], "code": code }),
+    )
+    .await;
+    assert!(body["data"]["refresh_token"].is_string());
+}
+
+#[tokio::test]
+async fn oidc_callback_rejects_a_forged_state() {
+    let (app, idp) = spawn_app_with_idp().await;
+    let claims = json!({ "sub": "77", "email": "forged@example.com", "email_verified": true });
+
+    let (location, cookie) = start_login(&app).await;
+    let (code, _) = idp.approve(&location, claims);
+
+    // The state has to match the one of the login cookie
+    let (status, _) = finish_login(&app, &cookie, &code, "forged").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = finish_login(&app, "oidc_login=forged", &code, "forged").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn oidc_authorize_sets_a_secure_login_cookie() {
+    let (app, _idp) = spawn_app_with_idp().await;
+
+    let req = Request::builder()
+        .method(Method::GET)
+        .uri(app.get_http_uri(Some("/api/user/oidc/authorize")))
+        .body(Body::empty())
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request");
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+
+    let cookie = res.headers()[header::SET_COOKIE].to_str().unwrap();
+    let attributes: Vec<&str> = cookie.split("; ").collect();
+    assert!(attributes.contains(&"HttpOnly"));
+    assert!(attributes.contains(&"Secure"));
+}
+
+#[tokio::test]
+async fn oidc_login_reuses_the_provider_documents() {
+    let (app, idp) = spawn_app_with_idp().await;
+    idp.rotate_signing_key(0);
+
+    for sub in ["501", "502"] {
+        let claims =
+            json!({ "sub": sub, "email": format!("{sub}@example.com"), "email_verified": true });
+        let (status, _) = oidc_login(&app, &idp, claims).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    assert_eq!(idp.discovery_requests(), 1);
+    assert_eq!(idp.jwks_requests(), 1);
+}
+
+#[tokio::test]
+async fn oidc_login_refetches_the_keys_when_the_provider_rotates_them() {
+    let (app, idp) = spawn_app_with_idp().await;
+    let claims = json!({ "sub": "601", "email": "rotated@example.com", "email_verified": true });
+
+    idp.rotate_signing_key(0);
+    let (status, _) = oidc_login(&app, &idp, claims.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The cached keys don't know the new one, they get fetched again
+    idp.rotate_signing_key(1);
+    let (status, _) = oidc_login(&app, &idp, claims).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(idp.jwks_requests(), 2);
+}
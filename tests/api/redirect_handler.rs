@@ -0,0 +1,464 @@
+use axum::http::{header, StatusCode};
+use hyper::{Body, Method, Request};
+use lib::{entity::url, handler::utils::hash_password};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, EntityTrait, IntoActiveModel};
+use serde_json::{json, Value};
+
+use crate::{
+    helpers::{server::TestApp, ParseJson},
+    seeds::{links::seed_one_link_for_user, users::seed_one_local_user},
+};
+
+#[tokio::test]
+async fn redirect_slug_handler_with_success() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user and a link
+    let (user, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+
+    // Create request
+    let path = &format!("/{}", &link.slug);
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(path)))
+        .method(Method::GET)
+        .body(Body::empty())
+        .expect("couldn't create request");
+
+    // Send request
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+
+    // Checking server response
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(
+        res.headers()[header::LOCATION].to_str().unwrap(),
+        link.redirect_to
+    );
+}
+
+#[tokio::test]
+async fn redirect_slug_handler_with_unknown_slug() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Create request
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some("/unknown_slug")))
+        .method(Method::GET)
+        .body(Body::empty())
+        .expect("couldn't create request");
+
+    // Send request
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+
+    // Checking server response
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let body: Value = res
+        .json_from_body()
+        .await
+        .expect("couldn't get json from body");
+    let expected_body = json!({
+        "data": null,
+        "error": {
+            "message": "link not found",
+            "error": null
+        }
+    });
+    assert_eq!(body, expected_body);
+}
+
+#[tokio::test]
+async fn redirect_slug_handler_renders_html_not_found_for_browsers() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Create request
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some("/unknown_slug")))
+        .method(Method::GET)
+        .header("Accept", "text/html,application/xhtml+xml")
+        .body(Body::empty())
+        .expect("couldn't create request");
+
+    // Send request
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+
+    // Checking server response
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(res.headers()[header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+}
+
+#[tokio::test]
+async fn redirect_slug_handler_with_deleted_link() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user and a soft deleted link
+    let (user, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    let slug = link.slug.clone();
+    let mut link: url::ActiveModel = link.into_active_model();
+    link.deleted_at = Set(Some(chrono::Utc::now().naive_utc()));
+    link.update(&app.database)
+        .await
+        .expect("couldn't delete link");
+
+    // Create request
+    let path = &format!("/{}", &slug);
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(path)))
+        .method(Method::GET)
+        .body(Body::empty())
+        .expect("couldn't create request");
+
+    // Send request
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+
+    // Checking server response
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn redirect_slug_handler_with_expired_link() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user and two expired links, one of them with a fallback
+    let (user, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let expired_at = chrono::Utc::now().naive_utc() - chrono::Duration::hours(1);
+    let fallback = "https://example.com/campaign-over";
+
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    let mut link = link.into_active_model();
+    link.expires_at = Set(Some(expired_at));
+    let link = link
+        .update(&app.database)
+        .await
+        .expect("couldn't expire link");
+
+    let link_with_fallback = seed_one_link_for_user(&app.database, &user.id).await;
+    let mut link_with_fallback = link_with_fallback.into_active_model();
+    link_with_fallback.expires_at = Set(Some(expired_at));
+    link_with_fallback.expired_redirect_to = Set(Some(fallback.into()));
+    let link_with_fallback = link_with_fallback
+        .update(&app.database)
+        .await
+        .expect("couldn't expire link");
+
+    // Without a fallback the link is gone
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(&format!("/{}", link.slug))))
+        .method(Method::GET)
+        .body(Body::empty())
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+    assert_eq!(res.status(), StatusCode::GONE);
+
+    // With one visitors are sent there instead of the destination
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(&format!("/{}", link_with_fallback.slug))))
+        .method(Method::GET)
+        .body(Body::empty())
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(res.headers()[header::LOCATION].to_str().unwrap(), fallback);
+}
+
+#[tokio::test]
+async fn redirect_slug_handler_with_scheduled_link() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user and a link that goes live tomorrow
+    let (user, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    let mut link = link.into_active_model();
+    link.active_from = Set(Some(
+        chrono::Utc::now().naive_utc() + chrono::Duration::days(1),
+    ));
+    let link = link
+        .update(&app.database)
+        .await
+        .expect("couldn't schedule link");
+
+    // Create request
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(&format!("/{}", link.slug))))
+        .method(Method::GET)
+        .body(Body::empty())
+        .expect("couldn't create request");
+
+    // Send request
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+
+    // Checking server response uses the configured status and message
+    let scheduled = &app.config.application.links.scheduled;
+    assert_eq!(res.status(), StatusCode::from(scheduled.status));
+
+    let body: Value = res
+        .json_from_body()
+        .await
+        .expect("couldn't get json from body");
+    assert_eq!(body["error"]["message"], scheduled.message);
+}
+
+#[tokio::test]
+async fn redirect_slug_handler_with_password_protected_link() {
+    // Run server, without backoff so the right password can follow a wrong one
+    let mut app = TestApp::new().await;
+    app.config.application.auth.login_throttle.base_delay_secs = 0;
+    app.spawn_server().await;
+
+    // Seed database with one user and a password protected link
+    let (user, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let password = "open-sesame";
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    let mut link = link.into_active_model();
+    link.password_hash = Set(Some(
+        hash_password(
+            app.config.application.hash_secret.as_bytes(),
+            &app.config.application.password_hashing,
+            password.as_bytes(),
+        )
+        .await
+        .expect("couldn't hash password"),
+    ));
+    let link = link
+        .update(&app.database)
+        .await
+        .expect("couldn't protect link");
+    let path = format!("/{}", link.slug);
+
+    // Browsers get the unlock form instead of the redirect
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(&path)))
+        .method(Method::GET)
+        .header(header::ACCEPT, "text/html")
+        .body(Body::empty())
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let page = hyper::body::to_bytes(res.into_body())
+        .await
+        .expect("couldn't read body");
+    assert!(String::from_utf8_lossy(&page).contains(r#"name="password""#));
+
+    // A wrong password shows the form again
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(&path)))
+        .method(Method::POST)
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from("password=wrong-password"))
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert!(res.headers().get(header::SET_COOKIE).is_none());
+
+    // The right one sends the visitor back to the link with a cookie
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(&path)))
+        .method(Method::POST)
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(format!("password={password}")))
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers()[header::LOCATION].to_str().unwrap(), path);
+    let cookie = res.headers()[header::SET_COOKIE]
+        .to_str()
+        .expect("couldn't read cookie")
+        .split(';')
+        .next()
+        .expect("cookie should have a value")
+        .to_owned();
+
+    // Which is enough to be redirected
+    let req = Request::builder()
+        .uri(app.get_http_uri(Some(&path)))
+        .method(Method::GET)
+        .header(header::COOKIE, cookie)
+        .body(Body::empty())
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("coudln't send request");
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(
+        res.headers()[header::LOCATION].to_str().unwrap(),
+        link.redirect_to
+    );
+}
+
+#[tokio::test]
+async fn unlock_slug_handler_locks_out_after_repeated_wrong_passwords() {
+    // Run server, without backoff so only the lockout applies
+    let mut app = TestApp::new().await;
+    let throttle = &mut app.config.application.auth.login_throttle;
+    throttle.base_delay_secs = 0;
+    throttle.max_failures_per_username = 3;
+    throttle.lockout_minutes = 10;
+    app.spawn_server().await;
+
+    // Seed database with one user and a password protected link
+    let (user, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let password = "open-sesame";
+    let mut link = seed_one_link_for_user(&app.database, &user.id)
+        .await
+        .into_active_model();
+    link.password_hash = Set(Some(
+        hash_password(
+            app.config.application.hash_secret.as_bytes(),
+            &app.config.application.password_hashing,
+            password.as_bytes(),
+        )
+        .await
+        .expect("couldn't hash password"),
+    ));
+    let link = link
+        .update(&app.database)
+        .await
+        .expect("couldn't protect link");
+    let path = format!("/{}", link.slug);
+
+    let unlock = |password: &str| {
+        Request::builder()
+            .uri(app.get_http_uri(Some(&path)))
+            .method(Method::POST)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(format!("password={password}")))
+            .expect("couldn't create request")
+    };
+
+    for _ in 0..3 {
+        let res = app
+            .client
+            .request(unlock("wrong-password"))
+            .await
+            .expect("coudln't send request");
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    // Even the right password is turned away until the lockout ends
+    let res = app
+        .client
+        .request(unlock(password))
+        .await
+        .expect("coudln't send request");
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(res.headers().get(header::SET_COOKIE).is_none());
+    let retry_after: u64 = res.headers()[header::RETRY_AFTER]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 9 * 60 && retry_after <= 10 * 60);
+}
+
+#[tokio::test]
+async fn redirect_slug_handler_with_click_limited_link() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user and a link limited to a few visits
+    let (user, _) = seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let max_clicks = 3;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    let mut link = link.into_active_model();
+    link.max_clicks = Set(Some(max_clicks));
+    link.remaining_clicks = Set(Some(max_clicks));
+    let link = link
+        .update(&app.database)
+        .await
+        .expect("couldn't limit link");
+
+    // Concurrent visitors can't go past the limit
+    let uri = app.get_http_uri(Some(&format!("/{}", link.slug)));
+    let requests: Vec<_> = (0..10)
+        .map(|_| {
+            let client = app.client.clone();
+            let req = Request::builder()
+                .uri(&uri)
+                .method(Method::GET)
+                .body(Body::empty())
+                .expect("couldn't create request");
+            tokio::spawn(async move {
+                client
+                    .request(req)
+                    .await
+                    .expect("coudln't send request")
+                    .status()
+            })
+        })
+        .collect();
+
+    let mut redirected = 0;
+    for request in requests {
+        match request.await.expect("request task panicked") {
+            StatusCode::TEMPORARY_REDIRECT => redirected += 1,
+            status => assert_eq!(status, StatusCode::GONE),
+        }
+    }
+    assert_eq!(redirected, max_clicks);
+
+    let link = url::Entity::find_by_id(link.id)
+        .one(&app.database)
+        .await
+        .expect("couldn't query link")
+        .expect("link should exist");
+    assert_eq!(link.remaining_clicks, Some(0));
+}
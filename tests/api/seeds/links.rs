@@ -11,6 +11,14 @@ use sea_orm::{
     EntityTrait, QueryFilter, QueryOrder,
 };
 
+// Random words collide quickly, a short random suffix keeps slugs unique. Words are
+// shortened so the slug stays within what the create endpoint accepts.
+fn fake_slug() -> String {
+    let word: String = Word().fake::<String>().chars().take(10).collect();
+
+    format!("{}-{}", word, &Uuid::new_v4().simple().to_string()[..6])
+}
+
 pub async fn seed_links_for_user(
     db: &DatabaseConnection,
     user_id: &Uuid,
@@ -19,13 +27,13 @@ pub async fn seed_links_for_user(
     let link_models = (0..number_of_links).map(|_| url::ActiveModel {
         id: Set(Uuid::new_v4()),
         name: Set(Username().fake()),
-        slug: Set(Word().fake()),
+        slug: Set(fake_slug()),
         redirect_to: Set(format!(
             "https://{}.{}",
             Word().fake::<String>(),
             DomainSuffix().fake::<String>()
         )),
-        owner_id: Set(user_id.clone()),
+        owner_id: Set(*user_id),
         created_at: Set(chrono::Utc::now().naive_utc()),
         ..Default::default()
     });
@@ -35,7 +43,7 @@ pub async fn seed_links_for_user(
         .expect("couldn't insert all links");
 
     url::Entity::find()
-        .filter(url::Column::OwnerId.eq(user_id.clone()))
+        .filter(url::Column::OwnerId.eq(*user_id))
         .order_by_desc(url::Column::CreatedAt)
         .all(db)
         .await
@@ -46,13 +54,13 @@ pub async fn seed_one_link_for_user(db: &DatabaseConnection, user_id: &Uuid) ->
     let link_model = url::ActiveModel {
         id: Set(Uuid::new_v4()),
         name: Set(Username().fake()),
-        slug: Set(Word().fake()),
+        slug: Set(fake_slug()),
         redirect_to: Set(format!(
             "https://{}.{}",
             Word().fake::<String>(),
             DomainSuffix().fake::<String>()
         )),
-        owner_id: Set(user_id.clone()),
+        owner_id: Set(*user_id),
         created_at: Set(chrono::Utc::now().naive_utc()),
         ..Default::default()
     };
@@ -4,22 +4,39 @@ use fake::{
 };
 use lib::{
     entity::{sea_orm_active_enums::Provider, user},
+    configuration::PasswordHashingSettings,
     handler::utils::hash_password,
 };
 use sea_orm::{prelude::Uuid, ActiveModelTrait, ActiveValue::Set, DatabaseConnection};
 
+// Usernames are at most 20 characters long and fake ones can collide
+fn fake_username() -> String {
+    let username: String = Username().fake();
+
+    format!(
+        "{}_{}",
+        username.chars().take(13).collect::<String>(),
+        &Uuid::new_v4().simple().to_string()[..6]
+    )
+}
+
 pub async fn seed_one_local_user(
     db: &DatabaseConnection,
     hash_secret: &str,
 ) -> (user::Model, String) {
     let password: String = Password(6..25).fake();
 
-    let hashed_password =
-        hash_password(hash_secret.as_bytes(), password.as_bytes()).expect("couldn't hash password");
+    let hashed_password = hash_password(
+        hash_secret.as_bytes(),
+        &PasswordHashingSettings::default(),
+        password.as_bytes(),
+    )
+    .await
+    .expect("couldn't hash password");
 
     let user = user::ActiveModel {
         id: Set(Uuid::new_v4()),
-        username: Set(Username().fake()),
+        username: Set(fake_username()),
         email: Set(SafeEmail().fake()),
         password_hash: Set(Some(hashed_password)),
         provider: Set(Provider::Local),
@@ -1,15 +1,16 @@
 use assert_json_diff::assert_json_eq;
-use hyper::{Body, Method, Request};
+use hyper::{Body, Method, Request, StatusCode};
 use serde_json::{json, Value};
 
 use sea_orm::{query::Condition, ColumnTrait, EntityTrait, QueryFilter};
 
-use lib::entity::user;
+use lib::entity::{security_event, user};
+use lib::handler::utils::generate_totp;
 
 use crate::helpers::testing::TestCase;
 use crate::{
     helpers::{server::TestApp, ParseJson},
-    seeds::users::seed_one_local_user,
+    seeds::{links::seed_one_link_for_user, users::seed_one_local_user},
 };
 
 #[tokio::test]
@@ -219,9 +220,1026 @@ async fn me_handler_with_success() {
             "username": user.username,
             "email": user.email,
             "provider": user.provider,
+            "email_verified_at": null,
+            "two_factor_enabled": false,
             "created_at": user.created_at,
             "updated_at": null
         }
     });
     assert_json_eq!(data, expected_data);
 }
+
+async fn login_for_tokens(app: &TestApp, username: &str, password: &str) -> (String, String) {
+    let user_input = json!({
+        "username": username,
+        "password": password,
+    });
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(app.get_http_uri(Some("/api/user/login")))
+        .header("Content-Type", "application/json")
+        .body(Body::from(user_input.to_string()))
+        .expect("couldn't create request");
+
+    let body: Value = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request")
+        .json_from_body()
+        .await
+        .expect("couldn't get json from body");
+
+    (
+        body["data"]["token"].as_str().unwrap().to_owned(),
+        body["data"]["refresh_token"].as_str().unwrap().to_owned(),
+    )
+}
+
+async fn refresh_tokens(app: &TestApp, refresh_token: &str) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(app.get_http_uri(Some("/api/user/refresh")))
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!({ "refresh_token": refresh_token }).to_string(),
+        ))
+        .expect("couldn't create request");
+
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request");
+    let status = res.status();
+    let body = res.json_from_body().await.unwrap_or(Value::Null);
+
+    (status, body)
+}
+
+#[tokio::test]
+async fn refresh_handler_rotates_and_detects_reuse() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (_, refresh_token) = login_for_tokens(&app, &user.username, &password).await;
+
+    // A refresh hands out a new pair
+    let (status, body) = refresh_tokens(&app, &refresh_token).await;
+    assert_eq!(status, StatusCode::OK);
+    let rotated_refresh_token = body["data"]["refresh_token"].as_str().unwrap().to_owned();
+    assert_ne!(rotated_refresh_token, refresh_token);
+    assert!(body["data"]["token"].is_string());
+
+    // Presenting the old token again revokes the whole family
+    let (status, _) = refresh_tokens(&app, &refresh_token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = refresh_tokens(&app, &rotated_refresh_token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn logout_handler_revokes_tokens() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (token, refresh_token) = login_for_tokens(&app, &user.username, &password).await;
+
+    // Logout
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(app.get_http_uri(Some("/api/user/logout")))
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::from(
+            json!({ "refresh_token": refresh_token }).to_string(),
+        ))
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request");
+    assert!(res.status().is_success());
+
+    // The access token no longer works
+    let req = Request::builder()
+        .method(Method::GET)
+        .uri(app.get_http_uri(Some("/api/user/me")))
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request");
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    // Neither does the refresh token
+    let (status, _) = refresh_tokens(&app, &refresh_token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+async fn send_authorized(
+    app: &TestApp,
+    method: Method,
+    path: &str,
+    token: &str,
+) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(app.get_http_uri(Some(path)))
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .expect("couldn't create request");
+
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request");
+    let status = res.status();
+    let body = res.json_from_body().await.unwrap_or(Value::Null);
+
+    (status, body)
+}
+
+#[tokio::test]
+async fn sessions_handler_lists_and_signs_out_sessions() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user logged in from two places
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (laptop_token, _) = login_for_tokens(&app, &user.username, &password).await;
+    let (phone_token, _) = login_for_tokens(&app, &user.username, &password).await;
+
+    // Both sessions are listed, the one making the request is flagged
+    let (status, body) =
+        send_authorized(&app, Method::GET, "/api/user/sessions", &laptop_token).await;
+    assert_eq!(status, StatusCode::OK);
+    let sessions = body["data"]["sessions"]
+        .as_array()
+        .expect("couldn't get sessions");
+    assert_eq!(sessions.len(), 2);
+    let phone_session = sessions
+        .iter()
+        .find(|session| session["current"] == false)
+        .expect("the other session should be listed");
+    assert!(phone_session["ip"].is_string());
+
+    // Signing out the phone revokes its access token
+    let path = format!(
+        "/api/user/sessions/{}",
+        phone_session["id"].as_str().unwrap()
+    );
+    let (status, _) = send_authorized(&app, Method::DELETE, &path, &laptop_token).await;
+    assert!(status.is_success());
+    let (status, _) = send_authorized(&app, Method::GET, "/api/user/me", &phone_token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Signing out everywhere takes the current session with it
+    let (status, _) =
+        send_authorized(&app, Method::DELETE, "/api/user/sessions", &laptop_token).await;
+    assert!(status.is_success());
+    let (status, _) = send_authorized(&app, Method::GET, "/api/user/me", &laptop_token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+async fn send_authorized_json(
+    app: &TestApp,
+    method: Method,
+    path: &str,
+    token: &str,
+    body: Value,
+) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(app.get_http_uri(Some(path)))
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::from(body.to_string()))
+        .expect("couldn't create request");
+
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request");
+    let status = res.status();
+    let body = res.json_from_body().await.unwrap_or(Value::Null);
+
+    (status, body)
+}
+
+#[tokio::test]
+async fn api_keys_authenticate_within_their_scopes() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (token, _) = login_for_tokens(&app, &user.username, &password).await;
+
+    // Unknown scopes are rejected
+    let (status, _) = send_authorized_json(
+        &app,
+        Method::POST,
+        "/api/user/api-keys",
+        &token,
+        json!({ "name": "ci", "scopes": ["links:admin"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The key is only handed out once
+    let (status, body) = send_authorized_json(
+        &app,
+        Method::POST,
+        "/api/user/api-keys",
+        &token,
+        json!({ "name": "read only", "scopes": ["links:read"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let read_key = body["data"]["key"].as_str().unwrap().to_owned();
+    let read_key_id = body["data"]["api_key"]["id"].as_str().unwrap().to_owned();
+    assert!(read_key.starts_with("dnl_"));
+    assert!(body["data"]["api_key"].get("key_hash").is_none());
+
+    let (status, body) = send_authorized_json(
+        &app,
+        Method::POST,
+        "/api/user/api-keys",
+        &token,
+        json!({ "name": "automation", "scopes": ["links:write", "links:read"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let write_key = body["data"]["key"].as_str().unwrap().to_owned();
+
+    // Keys only reach the endpoints their scopes allow
+    let link_input = json!({
+        "name": "automated",
+        "slug": "automated",
+        "redirect_to": "https://www.example.com",
+    });
+    let (status, _) = send_authorized(&app, Method::GET, "/api/links", &read_key).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send_authorized_json(
+        &app,
+        Method::POST,
+        "/api/links",
+        &read_key,
+        link_input.clone(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) =
+        send_authorized_json(&app, Method::POST, "/api/links", &write_key, link_input).await;
+    assert!(status.is_success());
+    let (status, _) = send_authorized(&app, Method::GET, "/api/user/me", &write_key).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) =
+        send_authorized(&app, Method::GET, "/api/user/api-keys", &write_key).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Keys are listed without their secret
+    let (status, body) = send_authorized(&app, Method::GET, "/api/user/api-keys", &token).await;
+    assert_eq!(status, StatusCode::OK);
+    let api_keys = body["data"]["api_keys"]
+        .as_array()
+        .expect("couldn't get api keys");
+    assert_eq!(api_keys.len(), 2);
+    assert!(api_keys.iter().all(|api_key| api_key.get("key").is_none()));
+
+    // A deleted key stops working
+    let path = format!("/api/user/api-keys/{read_key_id}");
+    let (status, _) = send_authorized(&app, Method::DELETE, &path, &token).await;
+    assert!(status.is_success());
+    let (status, _) = send_authorized(&app, Method::GET, "/api/links", &read_key).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+async fn post_json(app: &TestApp, path: &str, body: Value) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(app.get_http_uri(Some(path)))
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("couldn't create request");
+
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request");
+    let status = res.status();
+    let body = res.json_from_body().await.unwrap_or(Value::Null);
+
+    (status, body)
+}
+
+// Token of the link found in the last email sent to the address
+fn emailed_token(app: &TestApp, to: &str, subject: &str) -> String {
+    let emails = app.sent_emails();
+    let email = emails
+        .iter()
+        .find(|email| email.to == to && email.subject == subject)
+        .expect("email should have been sent");
+
+    email
+        .body
+        .split("token=")
+        .nth(1)
+        .expect("email should hold a token")
+        .split_whitespace()
+        .next()
+        .unwrap()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn verify_email_handler_verifies_the_registered_email_once() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Registering sends the verification email
+    let email = "verify@email.com";
+    let (status, _) = post_json(
+        &app,
+        "/api/user/register",
+        json!({ "username": "verify_user", "email": email, "password": "test_password" }),
+    )
+    .await;
+    assert!(status.is_success());
+    let token = emailed_token(&app, email, "Verify your email");
+
+    let (status, _) = post_json(&app, "/api/user/verify-email", json!({ "token": token })).await;
+    assert_eq!(status, StatusCode::OK);
+    let user = user::Entity::find()
+        .filter(user::Column::Email.eq(email))
+        .one(&app.database)
+        .await
+        .expect("couldn't get user")
+        .expect("user should exist");
+    assert!(user.email_verified_at.is_some());
+
+    // The token can't be used twice
+    let (status, _) = post_json(&app, "/api/user/verify-email", json!({ "token": token })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn reset_password_handler_resets_the_password_once() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one logged in user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (access_token, _) = login_for_tokens(&app, &user.username, &password).await;
+
+    // Unknown emails get the same answer, without any email sent
+    let (status, _) = post_json(
+        &app,
+        "/api/user/forgot-password",
+        json!({ "email": "nobody@email.com" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert!(app.sent_emails().is_empty());
+
+    let (status, _) = post_json(
+        &app,
+        "/api/user/forgot-password",
+        json!({ "email": user.email }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let token = emailed_token(&app, &user.email, "Reset your password");
+
+    // The new password is validated like on register
+    let (status, _) = post_json(
+        &app,
+        "/api/user/reset-password",
+        json!({ "token": token, "password": "abc" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let new_password = "new_password";
+    let (status, _) = post_json(
+        &app,
+        "/api/user/reset-password",
+        json!({ "token": token, "password": new_password }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Existing sessions are signed out, the new password works
+    let (status, _) = send_authorized(&app, Method::GET, "/api/user/me", &access_token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = post_json(
+        &app,
+        "/api/user/login",
+        json!({ "username": user.username, "password": new_password }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The token can't be used twice
+    let (status, _) = post_json(
+        &app,
+        "/api/user/reset-password",
+        json!({ "token": token, "password": "other_password" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn update_me_handler_updates_the_profile() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with two users
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (other_user, _) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (token, _) = login_for_tokens(&app, &user.username, &password).await;
+
+    // Usernames and emails of other users are taken
+    let (status, body) = send_authorized_json(
+        &app,
+        Method::PATCH,
+        "/api/user/me",
+        &token,
+        json!({ "username": other_user.username }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["error"]["code"], "username_taken");
+    let (status, body) = send_authorized_json(
+        &app,
+        Method::PATCH,
+        "/api/user/me",
+        &token,
+        json!({ "email": other_user.email }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["error"]["code"], "email_taken");
+
+    // Validated like on register
+    let (status, _) = send_authorized_json(
+        &app,
+        Method::PATCH,
+        "/api/user/me",
+        &token,
+        json!({ "email": "not an email" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // A new email has to be verified again
+    let new_email = "updated@email.com";
+    let (status, body) = send_authorized_json(
+        &app,
+        Method::PATCH,
+        "/api/user/me",
+        &token,
+        json!({ "username": "updated_user", "email": new_email }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["user"]["username"], "updated_user");
+    assert_eq!(body["data"]["user"]["email"], new_email);
+    assert!(body["data"]["user"]["email_verified_at"].is_null());
+    emailed_token(&app, new_email, "Verify your email");
+}
+
+#[tokio::test]
+async fn change_password_handler_signs_out_other_sessions() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user logged in from two places
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (laptop_token, _) = login_for_tokens(&app, &user.username, &password).await;
+    let (phone_token, _) = login_for_tokens(&app, &user.username, &password).await;
+
+    // The current password is required
+    let (status, _) = send_authorized_json(
+        &app,
+        Method::POST,
+        "/api/user/password",
+        &laptop_token,
+        json!({ "current_password": "wrong_password", "new_password": "new_password" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send_authorized_json(
+        &app,
+        Method::POST,
+        "/api/user/password",
+        &laptop_token,
+        json!({ "current_password": password, "new_password": "new_password" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Only the session that changed the password stays signed in
+    let (status, _) = send_authorized(&app, Method::GET, "/api/user/me", &laptop_token).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send_authorized(&app, Method::GET, "/api/user/me", &phone_token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = post_json(
+        &app,
+        "/api/user/login",
+        json!({ "username": user.username, "password": "new_password" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn delete_me_handler_disables_the_account() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user, a link and an api key
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let link = seed_one_link_for_user(&app.database, &user.id).await;
+    let (token, _) = login_for_tokens(&app, &user.username, &password).await;
+    let (_, body) = send_authorized_json(
+        &app,
+        Method::POST,
+        "/api/user/api-keys",
+        &token,
+        json!({ "name": "automation", "scopes": ["links:read"] }),
+    )
+    .await;
+    let api_key = body["data"]["key"].as_str().unwrap().to_owned();
+
+    let (status, _) = send_authorized(&app, Method::DELETE, "/api/user/me", &token).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Tokens, api keys and credentials stop working
+    let (status, _) = send_authorized(&app, Method::GET, "/api/user/me", &token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send_authorized(&app, Method::GET, "/api/links", &api_key).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = post_json(
+        &app,
+        "/api/user/login",
+        json!({ "username": user.username, "password": password }),
+    )
+    .await;
+    assert!(status.is_client_error());
+
+    // Links no longer redirect
+    let req = Request::builder()
+        .method(Method::GET)
+        .uri(app.get_http_uri(Some(&format!("/{}", link.slug))))
+        .body(Body::empty())
+        .expect("couldn't create request");
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request");
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let deleted_user = user::Entity::find_by_id(user.id)
+        .one(&app.database)
+        .await
+        .expect("couldn't get user")
+        .expect("user should be kept");
+    assert!(deleted_user.deleted_at.is_some());
+}
+
+// Returns the status along with the Retry-After header of the response
+async fn login_from(
+    app: &TestApp,
+    ip: &str,
+    username: &str,
+    password: &str,
+) -> (StatusCode, Option<u64>) {
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(app.get_http_uri(Some("/api/user/login")))
+        .header("Content-Type", "application/json")
+        .header("X-Forwarded-For", ip)
+        .body(Body::from(
+            json!({ "username": username, "password": password }).to_string(),
+        ))
+        .expect("couldn't create request");
+
+    let res = app
+        .client
+        .request(req)
+        .await
+        .expect("couldn't send request");
+    let retry_after = res
+        .headers()
+        .get("Retry-After")
+        .map(|value| value.to_str().unwrap().parse().unwrap());
+
+    (res.status(), retry_after)
+}
+
+#[tokio::test]
+async fn login_handler_locks_the_username_after_repeated_failures() {
+    // Run server, without backoff so only the lockout applies
+    let mut app = TestApp::new().await;
+    let throttle = &mut app.config.application.auth.login_throttle;
+    throttle.base_delay_secs = 0;
+    throttle.max_failures_per_username = 3;
+    throttle.lockout_minutes = 10;
+    app.trust_local_proxy();
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+
+    // Unknown usernames and wrong passwords get the same answer
+    let (status, _) = login_from(&app, "203.0.113.1", "nobody", "password").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    for ip in ["203.0.113.1", "203.0.113.2", "203.0.113.3"] {
+        let (status, _) = login_from(&app, ip, &user.username, "wrong_password").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    // Locked from any address, even with the right password
+    let (status, retry_after) = login_from(&app, "203.0.113.4", &user.username, &password).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    let retry_after = retry_after.expect("Retry-After should be set");
+    assert!(retry_after > 9 * 60 && retry_after <= 10 * 60);
+
+    // The lockout is recorded and the user is told about it
+    let events = security_event::Entity::find()
+        .filter(security_event::Column::UserId.eq(user.id))
+        .all(&app.database)
+        .await
+        .expect("couldn't get security events");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, "login_lockout");
+    assert_eq!(events[0].ip.as_deref(), Some("203.0.113.3"));
+    assert!(app
+        .sent_emails()
+        .iter()
+        .any(|email| email.to == user.email && email.subject == "Sign-in temporarily locked"));
+}
+
+#[tokio::test]
+async fn login_handler_throttles_an_address_across_usernames() {
+    // Run server, without backoff so only the lockout applies
+    let mut app = TestApp::new().await;
+    let throttle = &mut app.config.application.auth.login_throttle;
+    throttle.base_delay_secs = 0;
+    throttle.max_failures_per_ip = 3;
+    app.trust_local_proxy();
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+
+    for username in ["alice", "bob", "carol"] {
+        let (status, _) = login_from(&app, "198.51.100.7", username, "password").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    // Only the address is locked
+    let (status, retry_after) = login_from(&app, "198.51.100.7", &user.username, &password).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert!(retry_after.is_some());
+    let (status, _) = login_from(&app, "198.51.100.8", &user.username, &password).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn login_handler_ignores_forged_forwarded_addresses() {
+    // Run server, without backoff so only the lockout applies
+    let mut app = TestApp::new().await;
+    let throttle = &mut app.config.application.auth.login_throttle;
+    throttle.base_delay_secs = 0;
+    throttle.max_failures_per_ip = 3;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+
+    // Without a trusted proxy the header is ignored, the connection is throttled
+    for (ip, username) in [
+        ("198.51.100.1", "alice"),
+        ("198.51.100.2", "bob"),
+        ("198.51.100.3", "carol"),
+    ] {
+        let (status, _) = login_from(&app, ip, username, "password").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _) = login_from(&app, "198.51.100.4", &user.username, &password).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn login_handler_reads_the_address_appended_by_the_proxy() {
+    // Run server, without backoff so only the lockout applies
+    let mut app = TestApp::new().await;
+    let throttle = &mut app.config.application.auth.login_throttle;
+    throttle.base_delay_secs = 0;
+    throttle.max_failures_per_ip = 3;
+    app.trust_local_proxy();
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+
+    // Hops the client made up are left of the one the proxy saw
+    for (forged, username) in [
+        ("198.51.100.1", "alice"),
+        ("198.51.100.2", "bob"),
+        ("198.51.100.3", "carol"),
+    ] {
+        let ip = format!("{forged}, 203.0.113.9");
+        let (status, _) = login_from(&app, &ip, username, "password").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _) =
+        login_from(&app, "198.51.100.4, 203.0.113.9", &user.username, &password).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    let (status, _) = login_from(&app, "203.0.113.10", &user.username, &password).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn login_handler_backs_off_after_a_failure() {
+    // Run server
+    let mut app = TestApp::new().await;
+    let throttle = &mut app.config.application.auth.login_throttle;
+    throttle.base_delay_secs = 30;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+
+    let (status, _) = login_from(&app, "192.0.2.1", &user.username, "wrong_password").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // The next attempt has to wait, whatever the address
+    let (status, retry_after) = login_from(&app, "192.0.2.2", &user.username, &password).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    let retry_after = retry_after.expect("Retry-After should be set");
+    assert!(retry_after > 25 && retry_after <= 30);
+    assert!(app.sent_emails().is_empty());
+}
+
+// Enrolls and confirms two-factor login, returns the secret, the recovery codes and the time
+// of the code used to confirm
+async fn enable_totp(app: &TestApp, token: &str) -> (String, Vec<String>, i64) {
+    let (status, body) = send_authorized(app, Method::POST, "/api/user/2fa/enroll", token).await;
+    assert_eq!(status, StatusCode::OK);
+    let secret = body["data"]["secret"].as_str().unwrap().to_owned();
+    assert!(body["data"]["otpauth_uri"]
+        .as_str()
+        .unwrap()
+        .starts_with("otpauth://totp/Dinoly:"));
+
+    // A wrong code doesn't confirm the enrollment
+    let (status, _) = send_authorized_json(
+        app,
+        Method::POST,
+        "/api/user/2fa/confirm",
+        token,
+        json!({ "code": "abcdef" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let confirmed_at = chrono::Utc::now().timestamp();
+    let code = generate_totp(&secret, confirmed_at).unwrap();
+    let (status, body) = send_authorized_json(
+        app,
+        Method::POST,
+        "/api/user/2fa/confirm",
+        token,
+        json!({ "code": code }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let recovery_codes = body["data"]["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|code| code.as_str().unwrap().to_owned())
+        .collect();
+
+    (secret, recovery_codes, confirmed_at)
+}
+
+#[tokio::test]
+async fn login_two_factor_handler_requires_a_second_factor() {
+    // Run server, without backoff so wrong codes can be retried right away
+    let mut app = TestApp::new().await;
+    app.config.application.auth.login_throttle.base_delay_secs = 0;
+    app.spawn_server().await;
+
+    // Seed database with one user with two-factor login enabled
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (token, _) = login_for_tokens(&app, &user.username, &password).await;
+    let (secret, recovery_codes, confirmed_at) = enable_totp(&app, &token).await;
+    assert_eq!(recovery_codes.len(), 10);
+    let (_, body) = send_authorized(&app, Method::GET, "/api/user/me", &token).await;
+    assert_eq!(body["data"]["user"]["two_factor_enabled"], true);
+
+    // The password alone only gets a challenge
+    let (status, body) = post_json(
+        &app,
+        "/api/user/login",
+        json!({ "username": user.username, "password": password }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["two_factor_required"], true);
+    assert!(body["data"]["token"].is_null());
+    let challenge_token = body["data"]["challenge_token"].as_str().unwrap().to_owned();
+
+    // The code used to confirm the enrollment can't be replayed
+    let (status, _) = post_json(
+        &app,
+        "/api/user/login/2fa",
+        json!({ "challenge_token": challenge_token, "code": generate_totp(&secret, confirmed_at) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = post_json(
+        &app,
+        "/api/user/login/2fa",
+        json!({ "challenge_token": "forged", "code": generate_totp(&secret, confirmed_at + 30) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // The code of the next step is still accepted
+    let (status, body) = post_json(
+        &app,
+        "/api/user/login/2fa",
+        json!({ "challenge_token": challenge_token, "code": generate_totp(&secret, confirmed_at + 30) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["data"]["token"].is_string());
+
+    // Recovery codes work once, whatever their formatting
+    let recovery_code = recovery_codes[0].to_uppercase();
+    let (status, body) = post_json(
+        &app,
+        "/api/user/login/2fa",
+        json!({ "challenge_token": challenge_token, "recovery_code": recovery_code }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["data"]["refresh_token"].is_string());
+    let (status, _) = post_json(
+        &app,
+        "/api/user/login/2fa",
+        json!({ "challenge_token": challenge_token, "recovery_code": recovery_code }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let mut kinds: Vec<String> = security_event::Entity::find()
+        .filter(security_event::Column::UserId.eq(user.id))
+        .all(&app.database)
+        .await
+        .expect("couldn't get security events")
+        .into_iter()
+        .map(|event| event.kind)
+        .collect();
+    kinds.sort();
+    assert_eq!(kinds, ["recovery_code_used", "totp_enabled"]);
+}
+
+#[tokio::test]
+async fn disable_totp_handler_requires_both_factors() {
+    // Run server
+    let mut app = TestApp::new().await;
+    app.spawn_server().await;
+
+    // Seed database with one user with two-factor login enabled
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+    let (token, _) = login_for_tokens(&app, &user.username, &password).await;
+    let (_, recovery_codes, _) = enable_totp(&app, &token).await;
+
+    // Enrolling again needs it disabled first
+    let (status, _) = send_authorized(&app, Method::POST, "/api/user/2fa/enroll", &token).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = send_authorized_json(
+        &app,
+        Method::POST,
+        "/api/user/2fa/disable",
+        &token,
+        json!({ "password": "wrong_password", "recovery_code": recovery_codes[0] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send_authorized_json(
+        &app,
+        Method::POST,
+        "/api/user/2fa/disable",
+        &token,
+        json!({ "password": password }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send_authorized_json(
+        &app,
+        Method::POST,
+        "/api/user/2fa/disable",
+        &token,
+        json!({ "password": password, "recovery_code": recovery_codes[0] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The password is enough again
+    let (status, body) = post_json(
+        &app,
+        "/api/user/login",
+        json!({ "username": user.username, "password": password }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["data"]["token"].is_string());
+}
+
+#[tokio::test]
+async fn login_handler_upgrades_weaker_password_hashes() {
+    // Run server with a higher hashing cost than the one of the seeded user
+    let mut app = TestApp::new().await;
+    app.config.application.password_hashing.iterations += 1;
+    app.spawn_server().await;
+
+    // Seed database with one user
+    let (user, password) =
+        seed_one_local_user(&app.database, &app.config.application.hash_secret).await;
+
+    let (status, _) = post_json(
+        &app,
+        "/api/user/login",
+        json!({ "username": user.username, "password": password }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The hash is replaced with one using the configured cost, the password stays the same
+    let upgraded_user = user::Entity::find_by_id(user.id)
+        .one(&app.database)
+        .await
+        .expect("couldn't get user")
+        .expect("user should exist");
+    let password_hash = upgraded_user.password_hash.unwrap();
+    assert_ne!(Some(&password_hash), user.password_hash.as_ref());
+    assert!(password_hash.contains(&format!(
+        "t={}",
+        app.config.application.password_hashing.iterations
+    )));
+    let (status, _) = post_json(
+        &app,
+        "/api/user/login",
+        json!({ "username": user.username, "password": password }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}